@@ -67,6 +67,10 @@ pub enum InstructionResult {
     EofAuxDataTooSmall,
     /// EXT*CALL target address needs to be padded with 0s.
     InvalidEXTCALLTarget,
+    /// Configured instruction limit was exhausted.
+    InstructionLimitReached,
+    /// Configured wall-clock deadline has passed.
+    DeadlineExceeded,
 }
 
 impl From<SuccessReason> for InstructionResult {
@@ -111,6 +115,8 @@ impl From<HaltReason> for InstructionResult {
             HaltReason::EofAuxDataOverflow => Self::EofAuxDataOverflow,
             HaltReason::EofAuxDataTooSmall => Self::EofAuxDataTooSmall,
             HaltReason::EOFFunctionStackOverflow => Self::EOFFunctionStackOverflow,
+            HaltReason::InstructionLimitReached => Self::InstructionLimitReached,
+            HaltReason::DeadlineExceeded => Self::DeadlineExceeded,
             #[cfg(feature = "optimism")]
             HaltReason::FailedDeposit => Self::FatalExternalError,
         }
@@ -171,6 +177,8 @@ macro_rules! return_error {
             | InstructionResult::EofAuxDataTooSmall
             | InstructionResult::EofAuxDataOverflow
             | InstructionResult::InvalidEXTCALLTarget
+            | InstructionResult::InstructionLimitReached
+            | InstructionResult::DeadlineExceeded
     };
 }
 
@@ -321,6 +329,10 @@ impl From<InstructionResult> for SuccessOrHalt {
             InstructionResult::InvalidExtDelegateCallTarget => {
                 Self::Internal(InternalResult::InvalidExtDelegateCallTarget)
             }
+            InstructionResult::InstructionLimitReached => {
+                Self::Halt(HaltReason::InstructionLimitReached)
+            }
+            InstructionResult::DeadlineExceeded => Self::Halt(HaltReason::DeadlineExceeded),
         }
     }
 }
@@ -389,6 +401,8 @@ mod tests {
             InstructionResult::CreateContractStartingWithEF,
             InstructionResult::CreateInitCodeSizeLimit,
             InstructionResult::FatalExternalError,
+            InstructionResult::InstructionLimitReached,
+            InstructionResult::DeadlineExceeded,
         ];
 
         for result in error_results {
@@ -138,6 +138,10 @@ pub fn returndatacopy<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interprete
         interpreter.instruction_result = InstructionResult::OutOfOffset;
         return;
     }
+    #[cfg(feature = "halt_diagnostics")]
+    {
+        interpreter.return_data_copied = true;
+    }
 
     // if len is zero memory is not resized.
     if len == 0 {
@@ -1,11 +1,15 @@
 pub mod analysis;
 mod contract;
+#[cfg(feature = "execution_limits")]
+mod execution_limits;
 #[cfg(feature = "serde")]
 pub mod serde;
 mod shared_memory;
 mod stack;
 
 pub use contract::Contract;
+#[cfg(feature = "execution_limits")]
+pub use execution_limits::{ExecutionLimits, DEADLINE_CHECK_INTERVAL};
 pub use shared_memory::{num_words, SharedMemory, EMPTY_SHARED_MEMORY};
 pub use stack::{Stack, STACK_LIMIT};
 
@@ -60,6 +64,17 @@ pub struct Interpreter {
     /// Set inside CALL or CREATE instructions and RETURN or REVERT instructions. Additionally those instructions will set
     /// InstructionResult to CallOrCreate/Return/Revert so we know the reason.
     pub next_action: InterpreterAction,
+    /// Instruction count and wall-clock budget checked before every instruction.
+    ///
+    /// The EVM sets it before running the interpreter and reads it back afterwards so the budget
+    /// is shared by all frames of the transaction.
+    #[cfg(feature = "execution_limits")]
+    pub execution_limits: ExecutionLimits,
+    /// Whether `RETURNDATACOPY` was executed since the last call or creation returned.
+    ///
+    /// Used to tell a revert that forwards the data of a failed call from a new revert.
+    #[cfg(feature = "halt_diagnostics")]
+    pub return_data_copied: bool,
 }
 
 impl Default for Interpreter {
@@ -90,6 +105,10 @@ impl Interpreter {
             shared_memory: EMPTY_SHARED_MEMORY,
             stack: Stack::new(),
             next_action: InterpreterAction::None,
+            #[cfg(feature = "execution_limits")]
+            execution_limits: ExecutionLimits::unlimited(),
+            #[cfg(feature = "halt_diagnostics")]
+            return_data_copied: false,
         }
     }
 
@@ -162,6 +181,10 @@ impl Interpreter {
     /// - May alter `instruction_result` in case of external errors.
     pub fn insert_create_outcome(&mut self, create_outcome: CreateOutcome) {
         self.instruction_result = InstructionResult::Continue;
+        #[cfg(feature = "halt_diagnostics")]
+        {
+            self.return_data_copied = false;
+        }
 
         let instruction_result = create_outcome.instruction_result();
         self.return_data_buffer = if instruction_result.is_revert() {
@@ -194,6 +217,10 @@ impl Interpreter {
 
     pub fn insert_eofcreate_outcome(&mut self, create_outcome: CreateOutcome) {
         self.instruction_result = InstructionResult::Continue;
+        #[cfg(feature = "halt_diagnostics")]
+        {
+            self.return_data_copied = false;
+        }
         let instruction_result = create_outcome.instruction_result();
 
         self.return_data_buffer = if *instruction_result == InstructionResult::Revert {
@@ -254,6 +281,10 @@ impl Interpreter {
         call_outcome: CallOutcome,
     ) {
         self.instruction_result = InstructionResult::Continue;
+        #[cfg(feature = "halt_diagnostics")]
+        {
+            self.return_data_copied = false;
+        }
 
         let out_offset = call_outcome.memory_start();
         let out_len = call_outcome.memory_length();
@@ -382,6 +413,11 @@ impl Interpreter {
         self.shared_memory = shared_memory;
         // main loop
         while self.instruction_result == InstructionResult::Continue {
+            #[cfg(feature = "execution_limits")]
+            if let Some(result) = self.execution_limits.consume() {
+                self.instruction_result = result;
+                break;
+            }
             self.step(instruction_table, host);
         }
 
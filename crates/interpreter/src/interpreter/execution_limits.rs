@@ -0,0 +1,101 @@
+use crate::{primitives::CfgEnv, InstructionResult};
+
+/// Number of executed instructions between two checks of the wall-clock deadline.
+///
+/// Reading the clock is comparatively expensive, so it is only done periodically. Must be a
+/// power of two.
+pub const DEADLINE_CHECK_INTERVAL: u64 = 1 << 10;
+
+/// Instruction count and wall-clock budget shared by all frames of a transaction.
+///
+/// The EVM hands it to each interpreter before running it and reads it back afterwards, so the
+/// budget is consumed across call frames. Once a limit trips it stays tripped, which makes every
+/// parent frame halt with the same reason as it resumes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Number of instructions executed so far.
+    executed: u64,
+    /// Maximum number of instructions that can be executed.
+    instruction_limit: u64,
+    /// Deadline after which execution is halted.
+    #[cfg(feature = "std")]
+    deadline: Option<std::time::Instant>,
+}
+
+impl Default for ExecutionLimits {
+    #[inline]
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl ExecutionLimits {
+    /// Creates limits that never trip.
+    #[inline]
+    pub const fn unlimited() -> Self {
+        Self {
+            executed: 0,
+            instruction_limit: u64::MAX,
+            #[cfg(feature = "std")]
+            deadline: None,
+        }
+    }
+
+    /// Creates limits from the configuration environment.
+    #[inline]
+    pub fn new(cfg: &CfgEnv) -> Self {
+        Self {
+            executed: 0,
+            instruction_limit: cfg.instruction_limit.unwrap_or(u64::MAX),
+            #[cfg(feature = "std")]
+            deadline: cfg.execution_deadline,
+        }
+    }
+
+    /// Sets the maximum number of instructions that can be executed.
+    #[inline]
+    pub fn with_instruction_limit(mut self, limit: u64) -> Self {
+        self.instruction_limit = limit;
+        self
+    }
+
+    /// Sets the wall-clock deadline.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the number of instructions executed so far.
+    #[inline]
+    pub const fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// Returns the number of instructions that can still be executed.
+    #[inline]
+    pub const fn remaining(&self) -> u64 {
+        self.instruction_limit.saturating_sub(self.executed)
+    }
+
+    /// Accounts for the next instruction.
+    ///
+    /// Returns the halt reason if a limit tripped and the instruction must not be executed.
+    #[inline]
+    pub fn consume(&mut self) -> Option<InstructionResult> {
+        if self.executed >= self.instruction_limit {
+            return Some(InstructionResult::InstructionLimitReached);
+        }
+        #[cfg(feature = "std")]
+        if self.executed & (DEADLINE_CHECK_INTERVAL - 1) == 0 {
+            if let Some(deadline) = self.deadline {
+                if std::time::Instant::now() >= deadline {
+                    return Some(InstructionResult::DeadlineExceeded);
+                }
+            }
+        }
+        self.executed += 1;
+        None
+    }
+}
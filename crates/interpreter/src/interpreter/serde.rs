@@ -108,6 +108,10 @@ impl<'de> Deserialize<'de> for Interpreter {
             return_data_buffer,
             is_static,
             next_action,
+            #[cfg(feature = "execution_limits")]
+            execution_limits: Default::default(),
+            #[cfg(feature = "halt_diagnostics")]
+            return_data_copied: false,
         })
     }
 }
@@ -35,6 +35,8 @@ pub use interpreter::{
     analysis, num_words, Contract, Interpreter, InterpreterResult, SharedMemory, Stack,
     EMPTY_SHARED_MEMORY, STACK_LIMIT,
 };
+#[cfg(feature = "execution_limits")]
+pub use interpreter::{ExecutionLimits, DEADLINE_CHECK_INTERVAL};
 pub use interpreter_action::{
     CallInputs, CallOutcome, CallScheme, CallValue, CreateInputs, CreateOutcome, CreateScheme,
     EOFCreateInputs, EOFCreateKind, InterpreterAction,
@@ -11,6 +11,7 @@ pub use tables::{
 
 use crate::{instructions::*, primitives::Spec, Host};
 use core::{fmt, ptr::NonNull};
+use std::vec::Vec;
 
 /// An error indicating that an opcode is invalid.
 #[derive(Debug, PartialEq, Eq)]
@@ -121,6 +122,45 @@ impl OpCode {
         }
     }
 
+    /// Maps every byte of the legacy `code` to the index of the instruction it belongs to.
+    ///
+    /// Immediates of `PUSH` instructions map to the index of the `PUSH`. Source maps of solc have
+    /// one entry per instruction, so this turns a program counter into a source map index.
+    pub fn legacy_instruction_indices(code: &[u8]) -> Vec<usize> {
+        Self::instruction_indices(code, |code, i| match code[i] {
+            op @ PUSH1..=PUSH32 => (op - PUSH1) as usize + 1,
+            _ => 0,
+        })
+    }
+
+    /// Maps every byte of the EOF code section `code` to the index of the instruction it belongs
+    /// to, see [`OpCode::legacy_instruction_indices`].
+    ///
+    /// Immediates of all EOF instructions, including the `RJUMPV` jump table, are taken into
+    /// account.
+    pub fn eof_instruction_indices(code: &[u8]) -> Vec<usize> {
+        Self::instruction_indices(code, |code, i| {
+            let op = code[i];
+            let size = Self::info_by_op(op).map_or(0, |info| info.immediate_size() as usize);
+            match code.get(i + 1) {
+                Some(max_index) if op == RJUMPV => size + (*max_index as usize + 1) * 2,
+                _ => size,
+            }
+        })
+    }
+
+    fn instruction_indices(code: &[u8], immediates: impl Fn(&[u8], usize) -> usize) -> Vec<usize> {
+        let mut indices = Vec::with_capacity(code.len());
+        let mut index = 0;
+        while indices.len() < code.len() {
+            let size = 1 + immediates(code, indices.len());
+            let end = core::cmp::min(indices.len() + size, code.len());
+            indices.resize(end, index);
+            index += 1;
+        }
+        indices
+    }
+
     /// Instantiate a new opcode from a u8 without checking if it is valid.
     ///
     /// # Safety
@@ -693,6 +733,27 @@ opcodes! {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_instruction_indices() {
+        // PUSH2 0x0102 PUSH1 0x03 ADD PUSH32 truncated
+        let code = [PUSH2, 0x01, 0x02, PUSH1, 0x03, ADD, PUSH32, 0x00];
+        assert_eq!(
+            OpCode::legacy_instruction_indices(&code),
+            [0, 0, 0, 1, 1, 2, 3, 3]
+        );
+
+        // RJUMPV with two targets, RJUMP, STOP
+        let code = [
+            RJUMPV, 0x01, 0x00, 0x03, 0x00, 0x00, RJUMP, 0x00, 0x00, STOP,
+        ];
+        assert_eq!(
+            OpCode::eof_instruction_indices(&code),
+            [0, 0, 0, 0, 0, 0, 1, 1, 1, 2]
+        );
+        // RJUMP has no immediates in legacy code.
+        assert_eq!(OpCode::legacy_instruction_indices(&code[6..]), [0, 1, 2, 3]);
+    }
+
     #[test]
     fn test_opcode() {
         let opcode = OpCode::new(0x00).unwrap();
@@ -295,6 +295,21 @@ pub struct CfgEnv {
     /// EIP-1985.
     #[cfg(feature = "memory_limit")]
     pub memory_limit: u64,
+    /// Maximum number of instructions the interpreter may execute over the whole transaction,
+    /// counted across all call frames. Execution halts with
+    /// [crate::result::HaltReason::InstructionLimitReached] once it is exhausted.
+    ///
+    /// By default there is no limit.
+    #[cfg(feature = "execution_limits")]
+    pub instruction_limit: Option<u64>,
+    /// Wall-clock deadline for the transaction execution. It is checked periodically inside the
+    /// interpreter loop and execution halts with [crate::result::HaltReason::DeadlineExceeded]
+    /// once it has passed.
+    ///
+    /// By default there is no deadline.
+    #[cfg(all(feature = "execution_limits", feature = "std"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub execution_deadline: Option<std::time::Instant>,
     /// Skip balance checks if true. Adds transaction cost to balance to ensure execution doesn't fail.
     #[cfg(feature = "optional_balance_check")]
     pub disable_balance_check: bool,
@@ -407,6 +422,10 @@ impl Default for CfgEnv {
             kzg_settings: crate::kzg::EnvKzgSettings::Default,
             #[cfg(feature = "memory_limit")]
             memory_limit: (1 << 32) - 1,
+            #[cfg(feature = "execution_limits")]
+            instruction_limit: None,
+            #[cfg(all(feature = "execution_limits", feature = "std"))]
+            execution_deadline: None,
             #[cfg(feature = "optional_balance_check")]
             disable_balance_check: false,
             #[cfg(feature = "optional_block_gas_limit")]
@@ -16,6 +16,7 @@ pub mod env;
 pub mod kzg;
 pub mod precompile;
 pub mod result;
+pub mod revert;
 pub mod specification;
 pub mod state;
 pub mod utilities;
@@ -42,6 +43,7 @@ cfg_if::cfg_if! {
 pub use kzg::{EnvKzgSettings, KzgSettings};
 pub use precompile::*;
 pub use result::*;
+pub use revert::*;
 pub use specification::*;
 pub use state::*;
 pub use utilities::*;
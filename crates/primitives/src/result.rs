@@ -1,4 +1,4 @@
-use crate::{Address, Bytes, EvmState, Log, U256};
+use crate::{Address, Bytes, EvmState, Log, RevertReason, U256};
 use core::fmt;
 use std::{boxed::Box, string::String, vec::Vec};
 
@@ -15,6 +15,39 @@ pub struct ResultAndState {
     pub result: ExecutionResult,
     /// State that got updated
     pub state: EvmState,
+    /// Location of the failure if the transaction halted or reverted.
+    #[cfg(feature = "halt_diagnostics")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub diagnostics: Option<HaltDiagnostics>,
+}
+
+/// Location of the instruction that made the transaction halt or revert.
+///
+/// This is the innermost frame that failed: a revert whose data is copied with
+/// `RETURNDATACOPY` and returned unchanged by the callers is attributed to the frame that
+/// executed `REVERT` first.
+///
+/// A call or creation that fails before running any code (call depth limit, insufficient
+/// balance, address collision, out of gas in a precompile) is reported at the calling
+/// instruction of the caller. If the transaction itself fails this way there is no location.
+#[cfg(feature = "halt_diagnostics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct HaltDiagnostics {
+    /// Address of the frame, the created address for creations.
+    pub address: Address,
+    /// Hash of the executed code, the init code for creations.
+    pub code_hash: crate::B256,
+    /// Program counter of the failing instruction, relative to the code section for EOF.
+    pub pc: usize,
+    /// Failing instruction.
+    pub opcode: u8,
+    /// Call depth, the transaction frame has depth zero.
+    pub depth: usize,
 }
 
 /// Result of a transaction execution.
@@ -74,6 +107,14 @@ impl ExecutionResult {
         }
     }
 
+    /// Returns the decoded revert data if the execution was reverted.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        match self {
+            Self::Revert { output, .. } => Some(RevertReason::decode(output)),
+            _ => None,
+        }
+    }
+
     /// Returns the logs if execution is successful, or an empty list otherwise.
     pub fn logs(&self) -> &[Log] {
         match self {
@@ -468,6 +509,12 @@ pub enum HaltReason {
     /// EOF Subroutine stack overflow
     EOFFunctionStackOverflow,
 
+    /* Execution limits configured in `CfgEnv` */
+    /// The configured instruction limit was exhausted.
+    InstructionLimitReached,
+    /// The configured wall-clock deadline has passed.
+    DeadlineExceeded,
+
     /* Optimism errors */
     #[cfg(feature = "optimism")]
     FailedDeposit,
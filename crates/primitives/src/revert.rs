@@ -0,0 +1,223 @@
+//! Decoding of the revert data returned by Solidity contracts.
+
+use crate::{Bytes, FixedBytes, U256};
+use core::fmt;
+use std::string::String;
+
+/// Selector of Solidity `Error(string)`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of Solidity `Panic(uint256)`.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Panic codes raised by the Solidity compiler.
+///
+/// See <https://docs.soliditylang.org/en/latest/control-structures.html#panic-via-assert-and-error-via-require>.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum PanicCode {
+    /// Generic compiler inserted panic.
+    Generic = 0x00,
+    /// Failed `assert`.
+    Assert = 0x01,
+    /// Arithmetic underflow or overflow outside of an `unchecked` block.
+    ArithmeticOverflow = 0x11,
+    /// Division or modulo by zero.
+    DivisionByZero = 0x12,
+    /// Conversion of a too big or negative value into an enum.
+    EnumConversion = 0x21,
+    /// Access to an incorrectly encoded storage byte array.
+    StorageEncoding = 0x22,
+    /// `pop()` on an empty array.
+    EmptyArrayPop = 0x31,
+    /// Array index out of bounds.
+    ArrayOutOfBounds = 0x32,
+    /// Too much memory allocated or too large array created.
+    OutOfMemory = 0x41,
+    /// Call of a zero-initialized internal function variable.
+    UninitializedFunction = 0x51,
+}
+
+impl PanicCode {
+    /// Returns the panic code for the value, if it is known.
+    pub fn from_code(code: U256) -> Option<Self> {
+        let code: u8 = code.try_into().ok()?;
+        Some(match code {
+            0x00 => Self::Generic,
+            0x01 => Self::Assert,
+            0x11 => Self::ArithmeticOverflow,
+            0x12 => Self::DivisionByZero,
+            0x21 => Self::EnumConversion,
+            0x22 => Self::StorageEncoding,
+            0x31 => Self::EmptyArrayPop,
+            0x32 => Self::ArrayOutOfBounds,
+            0x41 => Self::OutOfMemory,
+            0x51 => Self::UninitializedFunction,
+            _ => return None,
+        })
+    }
+
+    /// Returns the numeric code.
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns a short description of the panic.
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Generic => "generic panic",
+            Self::Assert => "assertion failed",
+            Self::ArithmeticOverflow => "arithmetic underflow or overflow",
+            Self::DivisionByZero => "division or modulo by zero",
+            Self::EnumConversion => "invalid enum conversion",
+            Self::StorageEncoding => "invalid storage byte array encoding",
+            Self::EmptyArrayPop => "pop on empty array",
+            Self::ArrayOutOfBounds => "array index out of bounds",
+            Self::OutOfMemory => "out of memory",
+            Self::UninitializedFunction => "call to uninitialized function",
+        }
+    }
+}
+
+impl fmt::Display for PanicCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// Decoded revert data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RevertReason {
+    /// Revert without data, e.g. `revert()` or a `require` without message.
+    Empty,
+    /// Solidity `Error(string)`, raised by `revert("...")` and `require(.., "...")`.
+    Error(String),
+    /// Solidity `Panic(uint256)`, see [PanicCode] for the known codes.
+    Panic(U256),
+    /// Custom error, the arguments are ABI encoded in `data`.
+    Custom {
+        selector: FixedBytes<4>,
+        data: Bytes,
+    },
+    /// Data that is not a selector followed by arguments, or a malformed `Error` or `Panic`.
+    Raw(Bytes),
+}
+
+impl RevertReason {
+    /// Decodes the revert data.
+    pub fn decode(output: &[u8]) -> Self {
+        if output.is_empty() {
+            return Self::Empty;
+        }
+        let Some((selector, data)) = output.split_first_chunk::<4>() else {
+            return Self::Raw(Bytes::copy_from_slice(output));
+        };
+        let decoded = match *selector {
+            ERROR_SELECTOR => decode_string(data).map(Self::Error),
+            PANIC_SELECTOR => data
+                .get(..32)
+                .map(|code| Self::Panic(U256::from_be_slice(code))),
+            _ => Some(Self::Custom {
+                selector: selector.into(),
+                data: Bytes::copy_from_slice(data),
+            }),
+        };
+        decoded.unwrap_or_else(|| Self::Raw(Bytes::copy_from_slice(output)))
+    }
+
+    /// Returns the message of `Error(string)`.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::Error(message) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Returns the panic code of `Panic(uint256)`, if it is known.
+    pub fn panic_code(&self) -> Option<PanicCode> {
+        match self {
+            Self::Panic(code) => PanicCode::from_code(*code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("reverted without data"),
+            Self::Error(message) => write!(f, "revert: {message}"),
+            Self::Panic(code) => match PanicCode::from_code(*code) {
+                Some(panic) => write!(f, "panic: {panic} ({code:#04x})"),
+                None => write!(f, "panic: unknown code ({code:#x})"),
+            },
+            Self::Custom { selector, data } => write!(f, "custom error {selector}: {data}"),
+            Self::Raw(data) => write!(f, "reverted with {data}"),
+        }
+    }
+}
+
+/// Decodes the ABI encoded `string` argument.
+fn decode_string(data: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        let word = data.get(offset..offset.checked_add(32)?)?;
+        U256::from_be_slice(word).try_into().ok()
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytes, hex};
+
+    #[test]
+    fn decode() {
+        let error = hex::decode(
+            "08c379a0\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             0000000000000000000000000000000000000000000000000000000000000002\
+             6e6f000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        let reason = RevertReason::decode(&error);
+        assert_eq!(reason, RevertReason::Error("no".into()));
+        assert_eq!(reason.message(), Some("no"));
+        assert_eq!(reason.to_string(), "revert: no");
+
+        let panic = hex::decode(
+            "4e487b71\
+             0000000000000000000000000000000000000000000000000000000000000011",
+        )
+        .unwrap();
+        let reason = RevertReason::decode(&panic);
+        assert_eq!(reason.panic_code(), Some(PanicCode::ArithmeticOverflow));
+        assert_eq!(
+            reason.to_string(),
+            "panic: arithmetic underflow or overflow (0x11)"
+        );
+
+        assert_eq!(RevertReason::decode(&[]), RevertReason::Empty);
+        assert_eq!(
+            RevertReason::decode(&[1, 2]),
+            RevertReason::Raw(bytes!("0102"))
+        );
+        assert_eq!(
+            RevertReason::decode(&error[..40]),
+            RevertReason::Raw(Bytes::copy_from_slice(&error[..40]))
+        );
+        assert_eq!(
+            RevertReason::decode(&hex::decode("12345678ff").unwrap()),
+            RevertReason::Custom {
+                selector: FixedBytes([0x12, 0x34, 0x56, 0x78]),
+                data: bytes!("ff")
+            }
+        );
+    }
+}
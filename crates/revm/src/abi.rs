@@ -0,0 +1,1123 @@
+//! Minimal Solidity ABI support for decoding calls, return data, errors and events in inspectors.
+//!
+//! Functions, errors and events are described by human readable signatures, like
+//! `function transfer(address to, uint256 amount) returns (bool)`, or by the JSON ABI.
+
+use crate::primitives::{
+    keccak256, Address, Bytes, HashMap, LogData, RevertReason, B256, I256, U256,
+};
+use core::fmt;
+use std::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Error returned when a signature or encoded data is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiError {
+    /// Signature or type can't be parsed.
+    InvalidSignature(String),
+    /// Encoded data doesn't match the types.
+    InvalidData,
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSignature(signature) => write!(f, "invalid ABI signature: {signature}"),
+            Self::InvalidData => f.write_str("invalid ABI encoded data"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AbiError {}
+
+/// Solidity ABI type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AbiType {
+    Address,
+    Bool,
+    /// Unsigned integer with the number of bits.
+    Uint(usize),
+    /// Signed integer with the number of bits.
+    Int(usize),
+    /// Fixed size byte array with the number of bytes.
+    FixedBytes(usize),
+    Bytes,
+    String,
+    /// Dynamic size array.
+    Array(Box<AbiType>),
+    /// Fixed size array.
+    FixedArray(Box<AbiType>, usize),
+    Tuple(Vec<AbiType>),
+}
+
+impl AbiType {
+    /// Parses a type, like `uint256`, `(address,bytes)[]` or `tuple(bool)`.
+    pub fn parse(ty: &str) -> Result<Self, AbiError> {
+        let invalid = || AbiError::InvalidSignature(ty.to_string());
+        let ty = ty.trim();
+        if let Some(inner) = ty.strip_suffix(']') {
+            let open = inner.rfind('[').ok_or_else(invalid)?;
+            let element = Box::new(Self::parse(&inner[..open])?);
+            let size = &inner[open + 1..];
+            return Ok(if size.is_empty() {
+                Self::Array(element)
+            } else {
+                Self::FixedArray(element, size.parse().map_err(|_| invalid())?)
+            });
+        }
+        if let Some(inner) = ty.strip_prefix("tuple").unwrap_or(ty).strip_prefix('(') {
+            let inner = inner.strip_suffix(')').ok_or_else(invalid)?;
+            return split_params(inner)?
+                .into_iter()
+                .map(|param| Param::parse(param).map(|param| param.ty))
+                .collect::<Result<_, _>>()
+                .map(Self::Tuple);
+        }
+        let bits = |digits: &str, max: usize, step: usize| match digits {
+            "" => Ok(max),
+            digits => match digits.parse() {
+                Ok(n) if n > 0 && n <= max && n % step == 0 => Ok(n),
+                _ => Err(invalid()),
+            },
+        };
+        Ok(match ty {
+            "address" => Self::Address,
+            "bool" => Self::Bool,
+            "string" => Self::String,
+            "bytes" => Self::Bytes,
+            "function" => Self::FixedBytes(24),
+            _ => {
+                if let Some(digits) = ty.strip_prefix("uint") {
+                    Self::Uint(bits(digits, 256, 8)?)
+                } else if let Some(digits) = ty.strip_prefix("int") {
+                    Self::Int(bits(digits, 256, 8)?)
+                } else if let Some(digits) = ty.strip_prefix("bytes") {
+                    Self::FixedBytes(bits(digits, 32, 1)?)
+                } else {
+                    return Err(invalid());
+                }
+            }
+        })
+    }
+
+    /// Returns true if the encoding of the type has a dynamic size.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Self::Bytes | Self::String | Self::Array(_) => true,
+            Self::FixedArray(ty, _) => ty.is_dynamic(),
+            Self::Tuple(types) => types.iter().any(Self::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Size of the encoding in the head of a tuple.
+    fn head_size(&self) -> usize {
+        match self {
+            _ if self.is_dynamic() => 32,
+            Self::FixedArray(ty, size) => ty.head_size() * size,
+            Self::Tuple(types) => types.iter().map(Self::head_size).sum(),
+            _ => 32,
+        }
+    }
+}
+
+impl fmt::Display for AbiType {
+    /// Formats the canonical type used in signatures.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address => f.write_str("address"),
+            Self::Bool => f.write_str("bool"),
+            Self::Uint(bits) => write!(f, "uint{bits}"),
+            Self::Int(bits) => write!(f, "int{bits}"),
+            Self::FixedBytes(size) => write!(f, "bytes{size}"),
+            Self::Bytes => f.write_str("bytes"),
+            Self::String => f.write_str("string"),
+            Self::Array(ty) => write!(f, "{ty}[]"),
+            Self::FixedArray(ty, size) => write!(f, "{ty}[{size}]"),
+            Self::Tuple(types) => {
+                f.write_str("(")?;
+                for (i, ty) in types.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{ty}")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Decoded ABI value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiValue {
+    Address(Address),
+    Bool(bool),
+    Uint(U256),
+    Int(I256),
+    /// Fixed size byte array, left aligned in the word.
+    FixedBytes(B256, usize),
+    Bytes(Bytes),
+    String(String),
+    /// Elements of a dynamic or fixed size array.
+    Array(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+impl AbiValue {
+    /// Returns the address if the value is an address.
+    pub fn as_address(&self) -> Option<Address> {
+        match self {
+            Self::Address(address) => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if the value is an unsigned integer.
+    pub fn as_uint(&self) -> Option<U256> {
+        match self {
+            Self::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if the value is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the encoding of the value has a dynamic size.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Self::Bytes(_) | Self::String(_) | Self::Array(_) => true,
+            Self::Tuple(values) => values.iter().any(Self::is_dynamic),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for AbiValue {
+    /// Formats the value as a Solidity literal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, values: &[AbiValue]| -> fmt::Result {
+            for (i, value) in values.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{value}")?;
+            }
+            Ok(())
+        };
+        match self {
+            Self::Address(address) => write!(f, "{address}"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Uint(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::FixedBytes(word, size) => write!(f, "{}", Bytes::copy_from_slice(&word[..*size])),
+            Self::Bytes(bytes) => write!(f, "{bytes}"),
+            Self::String(value) => write!(f, "{value:?}"),
+            Self::Array(values) => {
+                f.write_str("[")?;
+                list(f, values)?;
+                f.write_str("]")
+            }
+            Self::Tuple(values) => {
+                f.write_str("(")?;
+                list(f, values)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Decodes the ABI encoded tuple of `types`.
+pub fn decode(types: &[AbiType], data: &[u8]) -> Result<Vec<AbiValue>, AbiError> {
+    // Offsets can point to the same data, the decoded size is bounded by the data size so that
+    // aliased values can't blow up the decoded size.
+    let mut budget = data.len();
+    decode_tuple(types, data, 0, &mut budget)
+}
+
+/// Takes `size` bytes from the remaining decoding budget.
+fn charge(budget: &mut usize, size: usize) -> Result<(), AbiError> {
+    *budget = budget.checked_sub(size).ok_or(AbiError::InvalidData)?;
+    Ok(())
+}
+
+fn decode_tuple(
+    types: &[AbiType],
+    data: &[u8],
+    start: usize,
+    budget: &mut usize,
+) -> Result<Vec<AbiValue>, AbiError> {
+    let mut head = start;
+    let mut values = Vec::with_capacity(types.len());
+    for ty in types {
+        let value = if ty.is_dynamic() {
+            let offset = read_usize(data, head)?;
+            decode_value(
+                ty,
+                data,
+                start.checked_add(offset).ok_or(AbiError::InvalidData)?,
+                budget,
+            )?
+        } else {
+            decode_value(ty, data, head, budget)?
+        };
+        values.push(value);
+        head += ty.head_size();
+    }
+    Ok(values)
+}
+
+fn decode_value(
+    ty: &AbiType,
+    data: &[u8],
+    at: usize,
+    budget: &mut usize,
+) -> Result<AbiValue, AbiError> {
+    let word = |budget: &mut usize| {
+        charge(budget, 32)?;
+        read_word(data, at)
+    };
+    let bytes = |budget: &mut usize| {
+        let bytes = read_bytes(data, at)?;
+        charge(budget, 32 + bytes.len())?;
+        Ok(bytes)
+    };
+    Ok(match ty {
+        AbiType::Address => AbiValue::Address(Address::from_word(word(budget)?)),
+        AbiType::Bool => match U256::from_be_bytes(word(budget)?.0) {
+            U256::ZERO => AbiValue::Bool(false),
+            value if value == U256::from(1) => AbiValue::Bool(true),
+            _ => return Err(AbiError::InvalidData),
+        },
+        AbiType::Uint(_) => AbiValue::Uint(U256::from_be_bytes(word(budget)?.0)),
+        AbiType::Int(_) => AbiValue::Int(I256::from_raw(U256::from_be_bytes(word(budget)?.0))),
+        AbiType::FixedBytes(size) => AbiValue::FixedBytes(word(budget)?, *size),
+        AbiType::Bytes => AbiValue::Bytes(Bytes::copy_from_slice(bytes(budget)?)),
+        AbiType::String => AbiValue::String(String::from_utf8_lossy(bytes(budget)?).into_owned()),
+        AbiType::Array(element) => {
+            let len = read_usize(data, at)?;
+            // Every element takes at least one word, bound the length by the data size.
+            if len > data.len() / 32 {
+                return Err(AbiError::InvalidData);
+            }
+            charge(budget, 32)?;
+            let types = std::vec![(**element).clone(); len];
+            AbiValue::Array(decode_tuple(&types, data, at + 32, budget)?)
+        }
+        AbiType::FixedArray(element, len) => {
+            let types = std::vec![(**element).clone(); *len];
+            AbiValue::Array(decode_tuple(&types, data, at, budget)?)
+        }
+        AbiType::Tuple(types) => AbiValue::Tuple(decode_tuple(types, data, at, budget)?),
+    })
+}
+
+/// ABI encodes the values as a tuple.
+///
+/// [AbiValue::Array] is encoded as a dynamic size array, fixed size arrays are encoded as
+/// [AbiValue::Tuple].
+pub fn encode(values: &[AbiValue]) -> Vec<u8> {
+    let parts: Vec<_> = values
+        .iter()
+        .map(|value| (value.is_dynamic(), encode_value(value)))
+        .collect();
+    let head_size: usize = parts
+        .iter()
+        .map(|(dynamic, data)| if *dynamic { 32 } else { data.len() })
+        .sum();
+    let mut head = Vec::with_capacity(head_size);
+    let mut tail = Vec::new();
+    for (dynamic, data) in parts {
+        if dynamic {
+            head.extend_from_slice(&U256::from(head_size + tail.len()).to_be_bytes::<32>());
+            tail.extend_from_slice(&data);
+        } else {
+            head.extend_from_slice(&data);
+        }
+    }
+    head.extend_from_slice(&tail);
+    head
+}
+
+fn encode_value(value: &AbiValue) -> Vec<u8> {
+    let bytes = |data: &[u8]| {
+        let mut encoded = U256::from(data.len()).to_be_bytes::<32>().to_vec();
+        encoded.extend_from_slice(data);
+        encoded.resize(32 + data.len().div_ceil(32) * 32, 0);
+        encoded
+    };
+    match value {
+        AbiValue::Address(address) => address.into_word().to_vec(),
+        AbiValue::Bool(value) => U256::from(*value as u8).to_be_bytes::<32>().to_vec(),
+        AbiValue::Uint(value) => value.to_be_bytes::<32>().to_vec(),
+        AbiValue::Int(value) => value.into_raw().to_be_bytes::<32>().to_vec(),
+        AbiValue::FixedBytes(word, _) => word.to_vec(),
+        AbiValue::Bytes(data) => bytes(data),
+        AbiValue::String(value) => bytes(value.as_bytes()),
+        AbiValue::Array(values) => {
+            let mut encoded = U256::from(values.len()).to_be_bytes::<32>().to_vec();
+            encoded.extend_from_slice(&encode(values));
+            encoded
+        }
+        AbiValue::Tuple(values) => encode(values),
+    }
+}
+
+fn read_word(data: &[u8], at: usize) -> Result<B256, AbiError> {
+    data.get(at..at.checked_add(32).ok_or(AbiError::InvalidData)?)
+        .map(B256::from_slice)
+        .ok_or(AbiError::InvalidData)
+}
+
+fn read_usize(data: &[u8], at: usize) -> Result<usize, AbiError> {
+    U256::from_be_bytes(read_word(data, at)?.0)
+        .try_into()
+        .map_err(|_| AbiError::InvalidData)
+}
+
+fn read_bytes(data: &[u8], at: usize) -> Result<&[u8], AbiError> {
+    let len = read_usize(data, at)?;
+    let start = at + 32;
+    data.get(start..start.checked_add(len).ok_or(AbiError::InvalidData)?)
+        .ok_or(AbiError::InvalidData)
+}
+
+/// Splits a parameter list at the top level commas.
+fn split_params(params: &str) -> Result<Vec<&str>, AbiError> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| AbiError::InvalidSignature(params.to_string()))?
+            }
+            ',' if depth == 0 => {
+                parts.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(AbiError::InvalidSignature(params.to_string()));
+    }
+    if !params[start..].trim().is_empty() || !parts.is_empty() {
+        parts.push(&params[start..]);
+    }
+    Ok(parts)
+}
+
+/// Returns the content of the parenthesis that starts `s` and the rest of `s` after it.
+fn split_parenthesis(s: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[1..i], &s[i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parameter of a function, error or event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Param {
+    /// Name of the parameter, empty if it is not named.
+    pub name: String,
+    pub ty: AbiType,
+    /// Whether the event parameter is indexed.
+    pub indexed: bool,
+}
+
+impl Param {
+    /// Parses a parameter, like `uint256 amount` or `address indexed from`.
+    pub fn parse(param: &str) -> Result<Self, AbiError> {
+        let param = param.trim();
+        // Tuples contain spaces, find the end of the type at the top level.
+        let end = if param.starts_with('(') || param.starts_with("tuple(") {
+            let open = param.find('(').unwrap_or_default();
+            let (_, rest) = split_parenthesis(&param[open..])
+                .ok_or_else(|| AbiError::InvalidSignature(param.to_string()))?;
+            let rest_start = param.len() - rest.len();
+            rest_start + rest.find(char::is_whitespace).unwrap_or(rest.len())
+        } else {
+            param.find(char::is_whitespace).unwrap_or(param.len())
+        };
+        let mut param_out = Self {
+            name: String::new(),
+            ty: AbiType::parse(&param[..end])?,
+            indexed: false,
+        };
+        for word in param[end..].split_whitespace() {
+            match word {
+                "indexed" => param_out.indexed = true,
+                "memory" | "calldata" | "storage" => {}
+                name => param_out.name = name.to_string(),
+            }
+        }
+        Ok(param_out)
+    }
+}
+
+fn parse_params(params: &str) -> Result<Vec<Param>, AbiError> {
+    split_params(params)?
+        .into_iter()
+        .map(Param::parse)
+        .collect()
+}
+
+fn signature(name: &str, params: &[Param]) -> String {
+    let types: Vec<_> = params.iter().map(|param| param.ty.clone()).collect();
+    format!("{name}{}", AbiType::Tuple(types))
+}
+
+/// Parses `[keyword] name(params) [modifiers] [returns (params)]`.
+fn parse_item<'a>(
+    item: &'a str,
+    keyword: &str,
+) -> Result<(&'a str, Vec<Param>, &'a str), AbiError> {
+    let invalid = || AbiError::InvalidSignature(item.to_string());
+    let item = item.trim();
+    let item = item
+        .strip_prefix(keyword)
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .unwrap_or(item)
+        .trim_start();
+    let open = item.find('(').ok_or_else(invalid)?;
+    let name = item[..open].trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    let (params, rest) = split_parenthesis(&item[open..]).ok_or_else(invalid)?;
+    Ok((name, parse_params(params)?, rest))
+}
+
+/// Function of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub inputs: Vec<Param>,
+    pub outputs: Vec<Param>,
+}
+
+impl Function {
+    /// Parses a human readable function, like `function balanceOf(address) view returns (uint256)`.
+    pub fn parse(function: &str) -> Result<Self, AbiError> {
+        let (name, inputs, rest) = parse_item(function, "function")?;
+        let outputs = match rest.find("returns") {
+            Some(at) => {
+                let returns = rest[at + "returns".len()..].trim_start();
+                let (outputs, _) = split_parenthesis(returns)
+                    .ok_or_else(|| AbiError::InvalidSignature(function.to_string()))?;
+                parse_params(outputs)?
+            }
+            None => Vec::new(),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Returns the canonical signature, like `transfer(address,uint256)`.
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the selector of the function.
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    /// Decodes the call arguments, `input` must not include the selector.
+    pub fn decode_input(&self, input: &[u8]) -> Result<Vec<AbiValue>, AbiError> {
+        decode(&types(&self.inputs), input)
+    }
+
+    /// Decodes the returned values.
+    pub fn decode_output(&self, output: &[u8]) -> Result<Vec<AbiValue>, AbiError> {
+        decode(&types(&self.outputs), output)
+    }
+}
+
+/// Custom error of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomError {
+    pub name: String,
+    pub inputs: Vec<Param>,
+}
+
+impl CustomError {
+    /// Parses a human readable error, like `error InsufficientBalance(uint256 balance)`.
+    pub fn parse(error: &str) -> Result<Self, AbiError> {
+        let (name, inputs, _) = parse_item(error, "error")?;
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+        })
+    }
+
+    /// Returns the canonical signature, like `InsufficientBalance(uint256)`.
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the selector of the error.
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    /// Decodes the error arguments, `data` must not include the selector.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<AbiValue>, AbiError> {
+        decode(&types(&self.inputs), data)
+    }
+}
+
+/// Event of a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub inputs: Vec<Param>,
+    /// Whether the event is anonymous, without the signature topic.
+    pub anonymous: bool,
+}
+
+impl Event {
+    /// Parses a human readable event, like
+    /// `event Transfer(address indexed from, address indexed to, uint256 value)`.
+    pub fn parse(event: &str) -> Result<Self, AbiError> {
+        let (name, inputs, rest) = parse_item(event, "event")?;
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+            anonymous: rest.split_whitespace().any(|word| word == "anonymous"),
+        })
+    }
+
+    /// Returns the canonical signature, like `Transfer(address,address,uint256)`.
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the first topic of the event.
+    pub fn topic(&self) -> B256 {
+        keccak256(self.signature())
+    }
+
+    /// Decodes the arguments of the event from the topics and the data, in parameter order.
+    ///
+    /// Indexed arguments of dynamic types are only stored as their hash, they are returned as
+    /// `bytes32`.
+    pub fn decode(&self, log: &LogData) -> Result<Vec<AbiValue>, AbiError> {
+        let mut topics = log.topics().iter().skip(usize::from(!self.anonymous));
+        let indexed = self.inputs.iter().filter(|param| param.indexed).count();
+        if topics.len() != indexed {
+            return Err(AbiError::InvalidData);
+        }
+        let data_types: Vec<_> = self
+            .inputs
+            .iter()
+            .filter(|param| !param.indexed)
+            .map(|param| param.ty.clone())
+            .collect();
+        let mut data = decode(&data_types, &log.data)?.into_iter();
+        self.inputs
+            .iter()
+            .map(|param| match (param.indexed, topics.next()) {
+                (false, _) => data.next().ok_or(AbiError::InvalidData),
+                (true, Some(topic)) if param.ty.is_dynamic() => {
+                    Ok(AbiValue::FixedBytes(*topic, 32))
+                }
+                (true, Some(topic)) => decode(core::slice::from_ref(&param.ty), topic.as_slice())
+                    .map(|mut values| values.remove(0)),
+                (true, None) => Err(AbiError::InvalidData),
+            })
+            .collect()
+    }
+}
+
+fn types(params: &[Param]) -> Vec<AbiType> {
+    params.iter().map(|param| param.ty.clone()).collect()
+}
+
+/// Returns the first four bytes of the hash of the signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    keccak256(signature)[..4].try_into().unwrap()
+}
+
+/// Formats `name(values)`.
+pub fn format_call(name: &str, values: &[AbiValue]) -> String {
+    let values: Vec<_> = values.iter().map(ToString::to_string).collect();
+    format!("{name}({})", values.join(", "))
+}
+
+/// Functions, errors and events of contracts, looked up by selector or topic.
+///
+/// Selectors and topics are computed once, when the items are added.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Abi {
+    functions: Vec<Function>,
+    errors: Vec<CustomError>,
+    events: Vec<Event>,
+    /// Index of the first function with each selector.
+    function_selectors: HashMap<[u8; 4], usize>,
+    /// Index of the first error with each selector.
+    error_selectors: HashMap<[u8; 4], usize>,
+    /// Index of the first non anonymous event with each topic.
+    event_topics: HashMap<B256, usize>,
+}
+
+impl Abi {
+    /// Creates an empty ABI.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses human readable signatures. Items starting with `error` are errors, items starting
+    /// with `event` are events, all other items are functions.
+    pub fn parse<'a>(items: impl IntoIterator<Item = &'a str>) -> Result<Self, AbiError> {
+        let mut abi = Self::default();
+        for item in items {
+            if item.trim_start().starts_with("error ") {
+                abi.push_error(CustomError::parse(item)?);
+            } else if item.trim_start().starts_with("event ") {
+                abi.push_event(Event::parse(item)?);
+            } else {
+                abi.push_function(Function::parse(item)?);
+            }
+        }
+        Ok(abi)
+    }
+
+    /// Parses the functions, errors and events of a JSON ABI.
+    #[cfg(feature = "serde-json")]
+    pub fn from_json(json: &str) -> Result<Self, AbiError> {
+        json::parse(json)
+    }
+
+    /// Adds the functions, errors and events of the other ABI.
+    pub fn extend(&mut self, other: Abi) {
+        other
+            .functions
+            .into_iter()
+            .for_each(|f| self.push_function(f));
+        other.errors.into_iter().for_each(|e| self.push_error(e));
+        other.events.into_iter().for_each(|e| self.push_event(e));
+    }
+
+    /// Adds a function.
+    pub fn push_function(&mut self, function: Function) {
+        self.function_selectors
+            .entry(function.selector())
+            .or_insert(self.functions.len());
+        self.functions.push(function);
+    }
+
+    /// Adds a custom error.
+    pub fn push_error(&mut self, error: CustomError) {
+        self.error_selectors
+            .entry(error.selector())
+            .or_insert(self.errors.len());
+        self.errors.push(error);
+    }
+
+    /// Adds an event.
+    pub fn push_event(&mut self, event: Event) {
+        if !event.anonymous {
+            self.event_topics
+                .entry(event.topic())
+                .or_insert(self.events.len());
+        }
+        self.events.push(event);
+    }
+
+    /// Returns the functions, in the order they were added.
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    /// Returns the custom errors, in the order they were added.
+    pub fn errors(&self) -> &[CustomError] {
+        &self.errors
+    }
+
+    /// Returns the events, in the order they were added.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Returns the function with the selector.
+    pub fn function(&self, selector: &[u8]) -> Option<&Function> {
+        let index = self.function_selectors.get(selector)?;
+        Some(&self.functions[*index])
+    }
+
+    /// Returns the error with the selector.
+    pub fn error(&self, selector: &[u8]) -> Option<&CustomError> {
+        let index = self.error_selectors.get(selector)?;
+        Some(&self.errors[*index])
+    }
+
+    /// Returns the event with the first topic. Anonymous events have no such topic and are
+    /// never returned.
+    pub fn event(&self, topic: &B256) -> Option<&Event> {
+        let index = self.event_topics.get(topic)?;
+        Some(&self.events[*index])
+    }
+
+    /// Decodes the log of a known event.
+    pub fn decode_log(&self, log: &LogData) -> Option<(&Event, Vec<AbiValue>)> {
+        let event = self.event(log.topics().first()?)?;
+        Some((event, event.decode(log).ok()?))
+    }
+
+    /// Decodes the call to a known function.
+    pub fn decode_call(&self, input: &[u8]) -> Option<(&Function, Vec<AbiValue>)> {
+        let (selector, args) = input.split_first_chunk::<4>()?;
+        let function = self.function(selector)?;
+        Some((function, function.decode_input(args).ok()?))
+    }
+
+    /// Decodes the revert data of a known custom error.
+    pub fn decode_error(&self, output: &[u8]) -> Option<(&CustomError, Vec<AbiValue>)> {
+        let (selector, args) = output.split_first_chunk::<4>()?;
+        let error = self.error(selector)?;
+        Some((error, error.decode(args).ok()?))
+    }
+
+    /// Formats the revert data, decoding `Error(string)`, `Panic(uint256)` and the custom errors
+    /// of the ABI.
+    pub fn format_revert(&self, output: &[u8]) -> String {
+        match self.decode_error(output) {
+            Some((error, values)) => format!("custom error {}", format_call(&error.name, &values)),
+            None => RevertReason::decode(output).to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "serde-json")]
+mod json {
+    use super::*;
+
+    #[derive(serde::Deserialize)]
+    struct Item {
+        #[serde(rename = "type", default)]
+        kind: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        inputs: Vec<JsonParam>,
+        #[serde(default)]
+        outputs: Vec<JsonParam>,
+        #[serde(default)]
+        anonymous: bool,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct JsonParam {
+        #[serde(default)]
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
+        #[serde(default)]
+        components: Vec<JsonParam>,
+        #[serde(default)]
+        indexed: bool,
+    }
+
+    impl JsonParam {
+        fn into_param(self) -> Result<Param, AbiError> {
+            // `tuple`, `tuple[]` and `tuple[2]` are described by the components.
+            let ty = match self.ty.strip_prefix("tuple") {
+                Some(suffix) => {
+                    let components = self
+                        .components
+                        .into_iter()
+                        .map(|c| c.into_param().map(|p| p.ty.to_string()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    AbiType::parse(&format!("({}){suffix}", components.join(",")))?
+                }
+                None => AbiType::parse(&self.ty)?,
+            };
+            Ok(Param {
+                name: self.name,
+                ty,
+                indexed: self.indexed,
+            })
+        }
+    }
+
+    fn params(params: Vec<JsonParam>) -> Result<Vec<Param>, AbiError> {
+        params.into_iter().map(JsonParam::into_param).collect()
+    }
+
+    pub(super) fn parse(json: &str) -> Result<Abi, AbiError> {
+        let items: Vec<Item> =
+            serde_json::from_str(json).map_err(|e| AbiError::InvalidSignature(e.to_string()))?;
+        let mut abi = Abi::default();
+        for item in items {
+            match item.kind.as_str() {
+                "function" | "" => abi.push_function(Function {
+                    name: item.name,
+                    inputs: params(item.inputs)?,
+                    outputs: params(item.outputs)?,
+                }),
+                "error" => abi.push_error(CustomError {
+                    name: item.name,
+                    inputs: params(item.inputs)?,
+                }),
+                "event" => abi.push_event(Event {
+                    name: item.name,
+                    inputs: params(item.inputs)?,
+                    anonymous: item.anonymous,
+                }),
+                _ => {}
+            }
+        }
+        Ok(abi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{address, b256, hex};
+
+    #[test]
+    fn parse_types() {
+        assert_eq!(AbiType::parse("uint").unwrap(), AbiType::Uint(256));
+        assert_eq!(AbiType::parse("bytes4").unwrap(), AbiType::FixedBytes(4));
+        assert_eq!(
+            AbiType::parse("tuple(address,uint8[2])[]").unwrap(),
+            AbiType::Array(Box::new(AbiType::Tuple(std::vec![
+                AbiType::Address,
+                AbiType::FixedArray(Box::new(AbiType::Uint(8)), 2)
+            ])))
+        );
+        assert!(AbiType::parse("uint7").is_err());
+        assert!(AbiType::parse("bytes33").is_err());
+        assert!(AbiType::parse("(uint256").is_err());
+    }
+
+    #[test]
+    fn function_signature() {
+        let function = Function::parse(
+            "function transfer(address to, uint256 amount) external returns (bool)",
+        )
+        .unwrap();
+        assert_eq!(function.signature(), "transfer(address,uint256)");
+        assert_eq!(function.selector(), hex!("a9059cbb"));
+        assert_eq!(function.inputs[0].name, "to");
+        assert_eq!(function.outputs[0].ty, AbiType::Bool);
+
+        let function = Function::parse("f((uint256 a, bytes b)[] calldata items, string)").unwrap();
+        assert_eq!(function.signature(), "f((uint256,bytes)[],string)");
+        assert_eq!(function.inputs[0].name, "items");
+        assert!(Function::parse("f()").unwrap().inputs.is_empty());
+    }
+
+    #[test]
+    fn lookup() {
+        let mut abi = Abi::parse([
+            "function transfer(address to, uint256 amount)",
+            "error E(address)",
+            "event Ev(uint256) anonymous",
+        ])
+        .unwrap();
+        abi.extend(
+            Abi::parse([
+                "function transfer(address, uint256) returns (bool)",
+                "event Ev(uint256)",
+            ])
+            .unwrap(),
+        );
+        let transfer = abi.function(&hex!("a9059cbb")).unwrap();
+        assert_eq!(transfer.inputs[0].name, "to");
+        assert_eq!(abi.functions().len(), 2);
+        let error = &abi.errors()[0];
+        assert_eq!(abi.error(&error.selector()), Some(error));
+        let event = abi.event(&abi.events()[0].topic()).unwrap();
+        assert!(!event.anonymous);
+        assert_eq!(abi.event(&B256::ZERO), None);
+    }
+
+    #[test]
+    fn decode_and_encode_values() {
+        // f(uint256, string, address[], int8)
+        let types = [
+            AbiType::Uint(256),
+            AbiType::String,
+            AbiType::Array(Box::new(AbiType::Address)),
+            AbiType::Int(8),
+        ];
+        let data = hex!(
+            "0000000000000000000000000000000000000000000000000000000000000001"
+            "0000000000000000000000000000000000000000000000000000000000000080"
+            "00000000000000000000000000000000000000000000000000000000000000c0"
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+            "0000000000000000000000000000000000000000000000000000000000000002"
+            "6869000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000001"
+            "000000000000000000000000ffffffffffffffffffffffffffffffffffffffff"
+        );
+        let values = decode(&types, &data).unwrap();
+        assert_eq!(
+            format_call("f", &values),
+            "f(1, \"hi\", [0xFFfFfFffFFfffFFfFFfFFFFFffFFFffffFfFFFfF], -1)"
+        );
+        assert_eq!(
+            values[2],
+            AbiValue::Array(std::vec![AbiValue::Address(address!(
+                "ffffffffffffffffffffffffffffffffffffffff"
+            ))])
+        );
+        assert_eq!(decode(&types, &data[..200]), Err(AbiError::InvalidData));
+        assert_eq!(encode(&values), data);
+    }
+
+    #[test]
+    fn decode_aliased_offsets() {
+        // f(bytes[]) with the three elements at the offset of the same 96 bytes.
+        let word = |value: usize| U256::from(value).to_be_bytes::<32>();
+        let types = [AbiType::Array(Box::new(AbiType::Bytes))];
+        let mut data = [
+            word(0x20),
+            word(3),
+            word(0x60),
+            word(0x60),
+            word(0x60),
+            word(96),
+        ]
+        .concat();
+        data.resize(data.len() + 96, 0xab);
+        assert_eq!(decode(&types, &data), Err(AbiError::InvalidData));
+
+        // Distinct elements of the same size decode.
+        let values = [AbiValue::Array(std::vec![
+            AbiValue::Bytes(Bytes::from(std::vec![0xab; 96]));
+            3
+        ])];
+        assert_eq!(decode(&types, &encode(&values)).unwrap(), values);
+    }
+
+    #[test]
+    fn decode_call_and_error() {
+        let abi = Abi::parse([
+            "function balanceOf(address) returns (uint256)",
+            "error Insufficient(uint256 have, uint256 want)",
+        ])
+        .unwrap();
+
+        let mut input = hex!("70a08231").to_vec();
+        input.extend_from_slice(&[0; 12]);
+        input.extend_from_slice(&[0x11; 20]);
+        let (function, args) = abi.decode_call(&input).unwrap();
+        assert_eq!(function.name, "balanceOf");
+        assert_eq!(args, [AbiValue::Address(Address::repeat_byte(0x11))]);
+        assert!(abi.decode_call(&hex!("12345678")).is_none());
+
+        let mut output = abi.errors()[0].selector().to_vec();
+        output.extend_from_slice(&U256::from(1).to_be_bytes::<32>());
+        output.extend_from_slice(&U256::from(2).to_be_bytes::<32>());
+        let (error, args) = abi.decode_error(&output).unwrap();
+        assert_eq!(format_call(&error.name, &args), "Insufficient(1, 2)");
+        assert_eq!(
+            abi.format_revert(&output),
+            "custom error Insufficient(1, 2)"
+        );
+        assert_eq!(
+            abi.format_revert(&hex!(
+                "4e487b710000000000000000000000000000000000000000000000000000000000000001"
+            )),
+            "panic: assertion failed (0x01)"
+        );
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn json_abi() {
+        let abi = Abi::from_json(
+            r#"[
+                {"type":"constructor","inputs":[]},
+                {"type":"function","name":"f","inputs":[{"name":"p","type":"tuple[]","components":[{"name":"a","type":"uint256"},{"name":"b","type":"bytes"}]}],"outputs":[{"name":"","type":"bool"}]},
+                {"type":"error","name":"E","inputs":[{"name":"x","type":"address"}]},
+                {"type":"event","name":"Ev","inputs":[],"anonymous":false}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(abi.functions().len(), 1);
+        assert_eq!(abi.functions()[0].signature(), "f((uint256,bytes)[])");
+        assert_eq!(abi.functions()[0].outputs[0].ty, AbiType::Bool);
+        assert_eq!(abi.errors()[0].signature(), "E(address)");
+        assert_eq!(abi.events()[0].signature(), "Ev()");
+    }
+
+    #[test]
+    fn decode_event() {
+        let abi = Abi::parse([
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "event Named(string indexed name, bytes data) anonymous",
+        ])
+        .unwrap();
+        let transfer = &abi.events()[0];
+        assert_eq!(
+            transfer.topic(),
+            b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+        );
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let log = LogData::new_unchecked(
+            std::vec![transfer.topic(), from.into_word(), to.into_word()],
+            U256::from(5).to_be_bytes_vec().into(),
+        );
+        let (event, values) = abi.decode_log(&log).unwrap();
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(
+            values,
+            [
+                AbiValue::Address(from),
+                AbiValue::Address(to),
+                AbiValue::Uint(U256::from(5))
+            ]
+        );
+
+        // Missing indexed argument.
+        let log = LogData::new_unchecked(std::vec![transfer.topic(), from.into_word()], log.data);
+        assert!(abi.decode_log(&log).is_none());
+
+        let named = &abi.events()[1];
+        assert!(named.anonymous);
+        let hash = keccak256("name");
+        let data = encode(&[AbiValue::Bytes(hex!("beef").into())]);
+        let log = LogData::new_unchecked(std::vec![hash], data.into());
+        assert!(abi.decode_log(&log).is_none());
+        assert_eq!(
+            named.decode(&log).unwrap(),
+            [
+                AbiValue::FixedBytes(hash, 32),
+                AbiValue::Bytes(hex!("beef").into())
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    inspectors::AccessListInspector,
+    primitives::{AccessListItem, Address, EVMError, ExecutionResult, HashSet, SpecId, TxKind},
+    Database, Evm,
+};
+use std::vec::Vec;
+
+/// Result of [Evm::create_access_list].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessListResult {
+    /// Access list the transaction converged to, sorted by address and storage key.
+    pub access_list: Vec<AccessListItem>,
+    /// Gas used by the transaction with the access list.
+    pub gas_used: u64,
+    /// Gas used by the transaction without an access list.
+    ///
+    /// Access list costs gas too, so it is worth attaching only if `gas_used` is lower.
+    pub gas_used_without_list: u64,
+    /// Result of the execution with the access list.
+    pub result: ExecutionResult,
+}
+
+impl<DB: Database> Evm<'_, AccessListInspector, DB> {
+    /// Create the [EIP-2930] access list of the transaction.
+    ///
+    /// Transaction is executed with the access list collected by the previous
+    /// execution until the list stops changing, as accessed state can depend on
+    /// the gas that is available. Access list of the transaction is used as a starting
+    /// point and is left unchanged. Nothing is committed.
+    ///
+    /// Sender, recipient, precompiles and, since Shanghai, the coinbase are warm anyway
+    /// and are not added to the list. Evm needs to be built with the
+    /// [inspector_handle_register](crate::inspector_handle_register).
+    ///
+    /// [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+    pub fn create_access_list(&mut self) -> Result<AccessListResult, EVMError<DB::Error>> {
+        let access_list = self.tx().access_list.clone();
+        let result = self.create_access_list_inner(&access_list);
+        self.tx_mut().access_list = access_list;
+        result
+    }
+
+    fn create_access_list_inner(
+        &mut self,
+        initial: &[AccessListItem],
+    ) -> Result<AccessListResult, EVMError<DB::Error>> {
+        let excluded = self.warm_addresses()?;
+
+        self.tx_mut().access_list = Vec::new();
+        self.context.external = AccessListInspector::new(initial, excluded.iter().copied());
+        let gas_used_without_list = self.transact()?.result.gas_used();
+        let mut access_list = self.context.external.access_list();
+
+        // Lists only grow as every execution starts with the previous list, so this ends.
+        loop {
+            self.tx_mut().access_list = access_list.clone();
+            self.context.external =
+                AccessListInspector::new(&access_list, excluded.iter().copied());
+            let result = self.transact()?.result;
+            let next = self.context.external.access_list();
+            if next == access_list {
+                return Ok(AccessListResult {
+                    access_list,
+                    gas_used: result.gas_used(),
+                    gas_used_without_list,
+                    result,
+                });
+            }
+            access_list = next;
+        }
+    }
+
+    /// Returns addresses that are warm at the start of the transaction.
+    fn warm_addresses(&mut self) -> Result<HashSet<Address>, EVMError<DB::Error>> {
+        let mut warm = self
+            .handler
+            .pre_execution
+            .load_precompiles()
+            .addresses_set();
+        let caller = self.tx().caller;
+        warm.insert(caller);
+        match self.tx().transact_to {
+            TxKind::Call(to) => {
+                warm.insert(to);
+            }
+            TxKind::Create => {
+                let nonce = match self.tx().nonce {
+                    Some(nonce) => nonce,
+                    None => self
+                        .db_mut()
+                        .basic(caller)
+                        .map_err(EVMError::Database)?
+                        .map(|info| info.nonce)
+                        .unwrap_or_default(),
+                };
+                warm.insert(caller.create(nonce));
+            }
+        }
+        if self.handler.cfg.spec_id.is_enabled_in(SpecId::SHANGHAI) {
+            warm.insert(self.block().coinbase);
+        }
+        Ok(warm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{AccountInfo, Bytecode, B256, U256},
+    };
+
+    #[test]
+    fn access_list() {
+        let caller = Address::repeat_byte(0xca);
+        let contract = Address::repeat_byte(0xc0);
+        let other = Address::repeat_byte(0x0e);
+        let coinbase = Address::repeat_byte(0xcb);
+
+        // PUSH1 1 SLOAD POP
+        let mut code = vec![0x60, 0x01, 0x54, 0x50];
+        // PUSH20 other BALANCE POP
+        code.push(0x73);
+        code.extend_from_slice(other.as_slice());
+        code.extend_from_slice(&[0x31, 0x50]);
+        // COINBASE BALANCE POP CALLER BALANCE POP
+        code.extend_from_slice(&[0x41, 0x31, 0x50, 0x33, 0x31, 0x50]);
+        // STATICCALL(gas, 0x04, 0, 0, 0, 0) POP STOP
+        code.extend_from_slice(&[0x5f, 0x5f, 0x5f, 0x5f, 0x60, 0x04, 0x5a, 0xfa, 0x50, 0x00]);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            contract,
+            AccountInfo::from_bytecode(Bytecode::new_raw(code.into())),
+        );
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(AccessListInspector::default())
+            .append_handler_register(inspector_handle_register)
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TxKind::Call(contract);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+
+        let result = evm.create_access_list().unwrap();
+        assert!(result.result.is_success());
+        assert_eq!(
+            result.access_list,
+            vec![
+                AccessListItem {
+                    address: other,
+                    storage_keys: vec![],
+                },
+                AccessListItem {
+                    address: contract,
+                    storage_keys: vec![B256::from(U256::from(1))],
+                },
+            ]
+        );
+        // accessing `other` and the slot saves 100 gas each, but the recipient is paid for again.
+        assert_eq!(
+            result.gas_used,
+            result.gas_used_without_list + 2400 - 2 * 100
+        );
+        assert!(evm.tx().access_list.is_empty());
+    }
+}
@@ -5,8 +5,9 @@ use super::inner_evm_context::InnerEvmContext;
 use crate::{
     db::Database,
     interpreter::{
-        analysis::validate_eof, return_ok, CallInputs, Contract, CreateInputs, EOFCreateInputs,
-        EOFCreateKind, Gas, InstructionResult, Interpreter, InterpreterResult,
+        analysis::validate_eof, return_ok, CallInputs, CallOutcome, Contract, CreateInputs,
+        CreateOutcome, EOFCreateInputs, EOFCreateKind, Gas, InstructionResult, Interpreter,
+        InterpreterResult,
     },
     primitives::{
         keccak256, Address, Bytecode, Bytes, CreateScheme, EVMError, Env, Eof,
@@ -19,7 +20,7 @@ use core::{
     fmt,
     ops::{Deref, DerefMut},
 };
-use std::{boxed::Box, sync::Arc};
+use std::{boxed::Box, string::String, sync::Arc};
 
 /// EVM context that contains the inner EVM context and precompiles.
 pub struct EvmContext<DB: Database> {
@@ -107,6 +108,87 @@ impl<DB: Database> EvmContext<DB> {
         self.precompiles = precompiles;
     }
 
+    /// Aborts the transaction, `transact` returns [EVMError::Custom] with the `reason`.
+    ///
+    /// Intended for inspectors. All frames are unwound without calling the `*_end` hooks
+    /// and the state is discarded. The first reason is kept if called more than once.
+    ///
+    /// Error is checked when the running frame returns, and before the outcome of a nested
+    /// call is used, so the `*_end` hooks only need to call this. Other hooks also need to stop
+    /// the execution with [EvmContext::abort_interpreter], [EvmContext::abort_call],
+    /// [EvmContext::abort_create] or [EvmContext::abort_eofcreate].
+    #[inline]
+    pub fn abort(&mut self, reason: impl Into<String>) {
+        if self.error.is_ok() {
+            self.error = Err(EVMError::Custom(reason.into()));
+        }
+    }
+
+    /// Returns true if the transaction was aborted or an error occurred.
+    #[inline]
+    pub fn is_aborted(&self) -> bool {
+        self.error.is_err()
+    }
+
+    /// Aborts the transaction and halts the interpreter.
+    ///
+    /// Used from `initialize_interp`, `step`, `step_end` and `log` hooks.
+    #[inline]
+    pub fn abort_interpreter(&mut self, interp: &mut Interpreter, reason: impl Into<String>) {
+        self.abort(reason);
+        interp.instruction_result = InstructionResult::FatalExternalError;
+    }
+
+    /// Aborts the transaction, returns the outcome that the `call` hook returns to skip the call.
+    #[inline]
+    pub fn abort_call(
+        &mut self,
+        inputs: &CallInputs,
+        reason: impl Into<String>,
+    ) -> Option<CallOutcome> {
+        self.abort(reason);
+        Some(CallOutcome::new(
+            Self::aborted_result(inputs.gas_limit),
+            inputs.return_memory_offset.clone(),
+        ))
+    }
+
+    /// Aborts the transaction, returns the outcome that the `create` hook returns to skip the creation.
+    #[inline]
+    pub fn abort_create(
+        &mut self,
+        inputs: &CreateInputs,
+        reason: impl Into<String>,
+    ) -> Option<CreateOutcome> {
+        self.abort(reason);
+        Some(CreateOutcome::new(
+            Self::aborted_result(inputs.gas_limit),
+            None,
+        ))
+    }
+
+    /// Aborts the transaction, returns the outcome that the `eofcreate` hook returns to skip the creation.
+    #[inline]
+    pub fn abort_eofcreate(
+        &mut self,
+        inputs: &EOFCreateInputs,
+        reason: impl Into<String>,
+    ) -> Option<CreateOutcome> {
+        self.abort(reason);
+        Some(CreateOutcome::new(
+            Self::aborted_result(inputs.gas_limit),
+            None,
+        ))
+    }
+
+    fn aborted_result(gas_limit: u64) -> InterpreterResult {
+        InterpreterResult::new(
+            InstructionResult::FatalExternalError,
+            Bytes::new(),
+            Gas::new(gas_limit),
+        )
+    }
+
     /// Call precompile contract
     #[inline]
     fn call_precompile(
@@ -516,6 +598,8 @@ pub(crate) mod test_utils {
                 db,
                 error: Ok(()),
                 valid_authorizations: Vec::new(),
+                #[cfg(feature = "halt_diagnostics")]
+                halt_diagnostics: None,
                 #[cfg(feature = "optimism")]
                 l1_block_info: None,
             },
@@ -532,6 +616,8 @@ pub(crate) mod test_utils {
                 db,
                 error: Ok(()),
                 valid_authorizations: Default::default(),
+                #[cfg(feature = "halt_diagnostics")]
+                halt_diagnostics: None,
                 #[cfg(feature = "optimism")]
                 l1_block_info: None,
             },
@@ -547,6 +633,7 @@ mod tests {
     use crate::{
         db::{CacheDB, EmptyDB},
         primitives::{address, Bytecode},
+        test_fixtures::{inspector_builder, Code, CHILD, TARGET},
         Frame, JournalEntry,
     };
     use std::boxed::Box;
@@ -635,4 +722,94 @@ mod tests {
         };
         assert_eq!(call_frame.return_memory_range, 0..0,);
     }
+
+    /// Where [Aborter] aborts the transaction.
+    #[derive(Clone, Copy, PartialEq)]
+    enum AbortAt {
+        Never,
+        Step(usize),
+        NestedCall,
+        NestedCallEnd,
+    }
+
+    struct Aborter {
+        at: AbortAt,
+        steps: usize,
+        max_depth: u64,
+    }
+
+    impl<DB: Database> crate::Inspector<DB> for Aborter {
+        fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+            self.steps += 1;
+            self.max_depth = self.max_depth.max(context.journaled_state.depth());
+            if self.at == AbortAt::Step(self.steps) {
+                context.abort_interpreter(interp, "step limit");
+            }
+        }
+
+        fn call(
+            &mut self,
+            context: &mut EvmContext<DB>,
+            inputs: &mut CallInputs,
+        ) -> Option<CallOutcome> {
+            if self.at == AbortAt::NestedCall && context.journaled_state.depth() > 0 {
+                return context.abort_call(inputs, "forbidden call");
+            }
+            None
+        }
+
+        fn call_end(
+            &mut self,
+            context: &mut EvmContext<DB>,
+            _inputs: &CallInputs,
+            outcome: CallOutcome,
+        ) -> CallOutcome {
+            if self.at == AbortAt::NestedCallEnd && context.journaled_state.depth() > 0 {
+                context.abort("rejected result");
+            }
+            outcome
+        }
+    }
+
+    fn abort_at(at: AbortAt) -> (Result<(), EVMError<core::convert::Infallible>>, Aborter) {
+        // CALL(GAS, CHILD, 0, 0, 0, 0, 0) STOP
+        let code = Code::new().call(CHILD, 0).op(0x00);
+        // PUSH1 1 PUSH0 SSTORE STOP
+        let child = Code::new().push(1).bytes(&[0x5f, 0x55, 0x00]);
+        let aborter = Aborter {
+            at,
+            steps: 0,
+            max_depth: 0,
+        };
+        let mut evm =
+            inspector_builder(aborter, [(TARGET, code.build()), (CHILD, child.build())]).build();
+        let result = evm
+            .transact()
+            .map(|result| assert!(result.result.is_success()));
+        // journal is cleared, the evm can be used for the next transaction.
+        assert!(evm.context.evm.journaled_state.state.is_empty());
+        assert_eq!(evm.context.evm.journaled_state.depth(), 0);
+        assert!(!evm.context.evm.is_aborted());
+        (result, evm.into_context().external)
+    }
+
+    #[test]
+    fn test_abort_from_inspector() {
+        let (result, inspector) = abort_at(AbortAt::Never);
+        assert_eq!(result, Ok(()));
+        assert_eq!((inspector.steps, inspector.max_depth), (13, 2));
+
+        let (result, inspector) = abort_at(AbortAt::Step(3));
+        assert_eq!(result, Err(EVMError::Custom("step limit".into())));
+        assert_eq!(inspector.steps, 3);
+
+        let (result, inspector) = abort_at(AbortAt::NestedCall);
+        assert_eq!(result, Err(EVMError::Custom("forbidden call".into())));
+        assert_eq!(inspector.max_depth, 1);
+
+        let (result, inspector) = abort_at(AbortAt::NestedCallEnd);
+        assert_eq!(result, Err(EVMError::Custom("rejected result".into())));
+        // `STOP` of the outer frame is not executed.
+        assert_eq!((inspector.steps, inspector.max_depth), (12, 2));
+    }
 }
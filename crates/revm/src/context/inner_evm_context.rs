@@ -29,6 +29,9 @@ pub struct InnerEvmContext<DB: Database> {
     pub error: Result<(), EVMError<DB::Error>>,
     /// EIP-7702 Authorization list of accounts that needs to be cleared.
     pub valid_authorizations: Vec<Address>,
+    /// Location of the failure of the last executed transaction.
+    #[cfg(feature = "halt_diagnostics")]
+    pub halt_diagnostics: Option<crate::primitives::HaltDiagnostics>,
     /// Used as temporary value holder to store L1 block info.
     #[cfg(feature = "optimism")]
     pub l1_block_info: Option<crate::optimism::L1BlockInfo>,
@@ -45,6 +48,8 @@ where
             db: self.db.clone(),
             error: self.error.clone(),
             valid_authorizations: self.valid_authorizations.clone(),
+            #[cfg(feature = "halt_diagnostics")]
+            halt_diagnostics: self.halt_diagnostics,
             #[cfg(feature = "optimism")]
             l1_block_info: self.l1_block_info.clone(),
         }
@@ -59,6 +64,8 @@ impl<DB: Database> InnerEvmContext<DB> {
             db,
             error: Ok(()),
             valid_authorizations: Default::default(),
+            #[cfg(feature = "halt_diagnostics")]
+            halt_diagnostics: None,
             #[cfg(feature = "optimism")]
             l1_block_info: None,
         }
@@ -73,6 +80,8 @@ impl<DB: Database> InnerEvmContext<DB> {
             db,
             error: Ok(()),
             valid_authorizations: Default::default(),
+            #[cfg(feature = "halt_diagnostics")]
+            halt_diagnostics: None,
             #[cfg(feature = "optimism")]
             l1_block_info: None,
         }
@@ -89,6 +98,8 @@ impl<DB: Database> InnerEvmContext<DB> {
             db,
             error: Ok(()),
             valid_authorizations: Default::default(),
+            #[cfg(feature = "halt_diagnostics")]
+            halt_diagnostics: None,
             #[cfg(feature = "optimism")]
             l1_block_info: self.l1_block_info,
         }
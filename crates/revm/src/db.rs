@@ -9,6 +9,7 @@ pub mod emptydb;
 #[cfg(feature = "ethersdb")]
 mod ethersdb;
 pub mod in_memory_db;
+pub mod overrides;
 pub mod states;
 
 pub use crate::primitives::db::*;
@@ -18,8 +19,12 @@ pub use emptydb::{EmptyDB, EmptyDBTyped};
 #[cfg(feature = "ethersdb")]
 pub use ethersdb::EthersDB;
 pub use in_memory_db::*;
+pub use overrides::{
+    apply_precompile_moves, AccountOverride, BlockOverrides, OverrideDB, StateOverride,
+    StateOverrideError,
+};
 pub use states::{
-    AccountRevert, AccountStatus, BundleAccount, BundleState, CacheState, DBBox,
+    AccountRevert, AccountStatus, BundleAccount, BundleState, CacheState, DBBox, DatabaseWrite,
     OriginalValuesKnown, PlainAccount, RevertToSlot, State, StateBuilder, StateDBBox,
     StorageWithOriginalValues, TransitionAccount, TransitionState,
 };
@@ -1,4 +1,4 @@
-use super::{DatabaseCommit, DatabaseRef, EmptyDB};
+use super::{DatabaseCommit, DatabaseRef, DatabaseWrite, EmptyDB};
 use crate::primitives::{
     hash_map::Entry, Account, AccountInfo, Address, Bytecode, HashMap, Log, B256, KECCAK_EMPTY,
     U256,
@@ -165,6 +165,57 @@ impl<ExtDB> DatabaseCommit for CacheDB<ExtDB> {
     }
 }
 
+/// Writes are done only to the cache, the underlying database is never modified.
+///
+/// Wiped storage is marked as cleared so slots are not loaded from the underlying database anymore.
+impl<ExtDB: DatabaseRef> DatabaseWrite for CacheDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    fn set_account(
+        &mut self,
+        address: Address,
+        info: Option<AccountInfo>,
+    ) -> Result<(), Self::Error> {
+        let db_account = self.load_account(address)?;
+        match info {
+            Some(info) => {
+                if matches!(db_account.account_state, AccountState::NotExisting) {
+                    db_account.account_state = AccountState::StorageCleared;
+                }
+                db_account.info = info;
+            }
+            None => {
+                db_account.info = AccountInfo::default();
+                db_account.account_state = AccountState::NotExisting;
+            }
+        }
+        Ok(())
+    }
+
+    fn wipe_storage(&mut self, address: Address) -> Result<(), Self::Error> {
+        let db_account = self.load_account(address)?;
+        db_account.storage.clear();
+        if !matches!(db_account.account_state, AccountState::NotExisting) {
+            db_account.account_state = AccountState::StorageCleared;
+        }
+        Ok(())
+    }
+
+    fn set_storage(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Result<(), Self::Error> {
+        self.insert_account_storage(address, index, value)
+    }
+
+    fn set_contract(&mut self, code_hash: B256, bytecode: Bytecode) -> Result<(), Self::Error> {
+        self.contracts.insert(code_hash, bytecode);
+        Ok(())
+    }
+}
+
 impl<ExtDB: DatabaseRef> Database for CacheDB<ExtDB> {
     type Error = ExtDB::Error;
 
@@ -465,6 +516,118 @@ mod tests {
         assert_eq!(new_state.storage(account, key1), Ok(value1));
     }
 
+    #[test]
+    fn test_apply_changeset_and_reverts() {
+        use crate::db::{
+            states::bundle_state::BundleRetention, states::PlainStateReverts, DatabaseWrite,
+            OriginalValuesKnown, State,
+        };
+        use crate::primitives::{Account, AccountStatus, Bytecode, Bytes, EvmStorageSlot, HashMap};
+        use crate::DatabaseCommit;
+
+        let contract = Address::with_last_byte(1);
+        let wallet = Address::with_last_byte(2);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00, 0x00]));
+        let code_hash = code.hash_slow();
+        let account =
+            |info: AccountInfo, status: AccountStatus, storage: &[(u64, u64, u64)]| Account {
+                info,
+                status: status | AccountStatus::Touched,
+                storage: storage
+                    .iter()
+                    .map(|&(slot, original, present)| {
+                        (
+                            U256::from(slot),
+                            EvmStorageSlot::new_changed(U256::from(original), U256::from(present)),
+                        )
+                    })
+                    .collect(),
+            };
+
+        // execute two blocks and collect bundle.
+        let mut state = State::builder().with_bundle_update().build();
+        state.basic(contract).unwrap();
+        state.basic(wallet).unwrap();
+        state.commit(HashMap::from([
+            (
+                contract,
+                account(
+                    AccountInfo {
+                        balance: U256::from(1),
+                        nonce: 1,
+                        code_hash,
+                        code: Some(code.clone()),
+                    },
+                    AccountStatus::Created,
+                    &[(1, 0, 1)],
+                ),
+            ),
+            (
+                wallet,
+                account(
+                    AccountInfo::from_balance(U256::from(5)),
+                    AccountStatus::default(),
+                    &[],
+                ),
+            ),
+        ]));
+        state.merge_transitions(BundleRetention::Reverts);
+        state.commit(HashMap::from([
+            (
+                contract,
+                account(
+                    AccountInfo {
+                        balance: U256::from(2),
+                        nonce: 1,
+                        code_hash,
+                        code: Some(code.clone()),
+                    },
+                    AccountStatus::default(),
+                    &[(1, 1, 2), (2, 0, 3)],
+                ),
+            ),
+            (
+                wallet,
+                account(
+                    AccountInfo::from_balance(U256::from(5)),
+                    AccountStatus::SelfDestructed,
+                    &[],
+                ),
+            ),
+        ]));
+        state.merge_transitions(BundleRetention::Reverts);
+
+        // persist and read it back.
+        let (changeset, mut reverts) = state
+            .take_bundle()
+            .into_plain_state_and_reverts(OriginalValuesKnown::Yes);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.apply_changeset(changeset).unwrap();
+        let info = db.basic(contract).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(2));
+        assert_eq!(db.code_by_hash(info.code_hash), Ok(code));
+        assert_eq!(db.storage(contract, U256::from(1)), Ok(U256::from(2)));
+        assert_eq!(db.storage(contract, U256::from(2)), Ok(U256::from(3)));
+        assert_eq!(db.basic(wallet), Ok(None));
+
+        // revert second block.
+        let last = PlainStateReverts {
+            accounts: reverts.accounts.split_off(1),
+            storage: reverts.storage.split_off(1),
+        };
+        db.apply_reverts(last).unwrap();
+        assert_eq!(db.basic(contract).unwrap().unwrap().balance, U256::from(1));
+        assert_eq!(db.storage(contract, U256::from(1)), Ok(U256::from(1)));
+        assert_eq!(db.storage(contract, U256::from(2)), Ok(U256::ZERO));
+        assert_eq!(db.basic(wallet).unwrap().unwrap().balance, U256::from(5));
+
+        // revert first block.
+        db.apply_reverts(reverts).unwrap();
+        assert_eq!(db.basic(contract), Ok(None));
+        assert_eq!(db.storage(contract, U256::from(1)), Ok(U256::ZERO));
+        assert_eq!(db.basic(wallet), Ok(None));
+    }
+
     #[cfg(feature = "serde-json")]
     #[test]
     fn test_serialize_deserialize_cachedb() {
@@ -0,0 +1,803 @@
+//! State and block overrides used by `eth_call` style simulations.
+//!
+//! Semantics follow geth `stateOverride` and `blockOverrides` call parameters.
+use crate::{
+    primitives::{
+        db::{Database, DatabaseCommit, DatabaseRef},
+        Account, AccountInfo, AccountStatus, Address, BlobExcessGasAndPrice, BlockEnv, Bytecode,
+        Bytes, EvmStorageSlot, HashMap, B256, KECCAK_EMPTY, U256,
+    },
+    ContextPrecompiles,
+};
+use core::fmt;
+use std::collections::BTreeMap;
+
+/// Overrides of the accounts, keyed by address.
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+/// Override of a single account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct AccountOverride {
+    /// Fake balance to set for the account.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub balance: Option<U256>,
+    /// Fake nonce to set for the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "crate::quantity::option",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub nonce: Option<u64>,
+    /// Fake code to set for the account.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub code: Option<Bytes>,
+    /// Fake storage that replaces the whole account storage.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub state: Option<HashMap<B256, B256>>,
+    /// Fake storage slots that are patched on top of the account storage.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub state_diff: Option<HashMap<B256, B256>>,
+    /// Moves the precompile at this account address to the given address.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "movePrecompileToAddress",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub move_precompile_to: Option<Address>,
+}
+
+/// Overrides of the block environment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct BlockOverrides {
+    /// Overrides the block number.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub number: Option<U256>,
+    /// Overrides the difficulty of the block.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub difficulty: Option<U256>,
+    /// Overrides the timestamp of the block.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub time: Option<U256>,
+    /// Overrides the gas limit of the block.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub gas_limit: Option<U256>,
+    /// Overrides the coinbase of the block.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "coinbase", skip_serializing_if = "Option::is_none")
+    )]
+    pub fee_recipient: Option<Address>,
+    /// Overrides the prevrandao of the block.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "random", skip_serializing_if = "Option::is_none")
+    )]
+    pub prev_randao: Option<B256>,
+    /// Overrides the basefee of the block.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "baseFee", skip_serializing_if = "Option::is_none")
+    )]
+    pub base_fee_per_gas: Option<U256>,
+    /// Overrides the blob gas price of the block.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub blob_base_fee: Option<U256>,
+    /// Overrides the hashes returned by `BLOCKHASH` opcode.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "blockHash", skip_serializing_if = "Option::is_none")
+    )]
+    pub block_hashes: Option<BTreeMap<u64, B256>>,
+}
+
+impl BlockOverrides {
+    /// Apply overrides to the block environment.
+    ///
+    /// Block hashes are not part of [BlockEnv] and are applied by [OverrideDB::with_block_overrides].
+    pub fn apply(&self, block: &mut BlockEnv) {
+        if let Some(number) = self.number {
+            block.number = number;
+        }
+        if let Some(difficulty) = self.difficulty {
+            block.difficulty = difficulty;
+        }
+        if let Some(time) = self.time {
+            block.timestamp = time;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            block.gas_limit = gas_limit;
+        }
+        if let Some(coinbase) = self.fee_recipient {
+            block.coinbase = coinbase;
+        }
+        if let Some(prevrandao) = self.prev_randao {
+            block.prevrandao = Some(prevrandao);
+        }
+        if let Some(basefee) = self.base_fee_per_gas {
+            block.basefee = basefee;
+        }
+        if let Some(blob_base_fee) = self.blob_base_fee {
+            let excess_blob_gas = block
+                .blob_excess_gas_and_price
+                .as_ref()
+                .map(|blob| blob.excess_blob_gas)
+                .unwrap_or_default();
+            block.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice {
+                excess_blob_gas,
+                blob_gasprice: blob_base_fee.saturating_to(),
+            });
+        }
+    }
+}
+
+/// Errors of invalid state overrides.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateOverrideError {
+    /// Both `state` and `stateDiff` are set for the account.
+    StateAndStateDiff(Address),
+    /// Account that should be moved is not a precompile.
+    NotAPrecompile(Address),
+    /// Precompile is moved to an address that already has a precompile moved to it.
+    PrecompileAlreadyMoved(Address),
+    /// Precompile is moved to an address that is overridden or has a precompile that stays.
+    AlreadyOverridden(Address),
+}
+
+impl fmt::Display for StateOverrideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StateAndStateDiff(address) => write!(
+                f,
+                "account {address} has both 'state' and 'stateDiff' overrides"
+            ),
+            Self::NotAPrecompile(address) => write!(f, "account {address} is not a precompile"),
+            Self::PrecompileAlreadyMoved(address) => write!(
+                f,
+                "account {address} has already been overridden by a precompile"
+            ),
+            Self::AlreadyOverridden(address) => {
+                write!(f, "account {address} is already overridden")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateOverrideError {}
+
+/// Move precompiles as requested by [AccountOverride::move_precompile_to].
+///
+/// All moves are resolved against the precompiles before any of them is moved,
+/// so precompiles can swap addresses. A precompile can't be moved to an overridden account or
+/// over a precompile, unless the account is moved away too.
+pub fn apply_precompile_moves<DB: Database>(
+    precompiles: &mut ContextPrecompiles<DB>,
+    overrides: &StateOverride,
+) -> Result<(), StateOverrideError> {
+    let mut moves = BTreeMap::new();
+    for (source, account) in overrides {
+        let Some(target) = account.move_precompile_to else {
+            continue;
+        };
+        if !precompiles.contains(source) {
+            return Err(StateOverrideError::NotAPrecompile(*source));
+        }
+        if moves.insert(target, *source).is_some() {
+            return Err(StateOverrideError::PrecompileAlreadyMoved(target));
+        }
+    }
+    if moves.is_empty() {
+        return Ok(());
+    }
+    for target in moves.keys() {
+        let moved_away = overrides
+            .get(target)
+            .is_some_and(|account| account.move_precompile_to.is_some());
+        if !moved_away && (overrides.contains_key(target) || precompiles.contains(target)) {
+            return Err(StateOverrideError::AlreadyOverridden(*target));
+        }
+    }
+
+    let precompiles = precompiles.to_mut();
+    let moved = moves
+        .into_iter()
+        .map(|(target, source)| (target, precompiles.remove(&source)))
+        .collect::<std::vec::Vec<_>>();
+    for (target, precompile) in moved {
+        if let Some(precompile) = precompile {
+            precompiles.insert(target, precompile);
+        }
+    }
+    Ok(())
+}
+
+/// Database that applies [StateOverride] and block hash overrides on top of the wrapped database.
+///
+/// Reads never modify the wrapped database. Committed changes are written to it and replace the
+/// overrides of the committed accounts, see [DatabaseCommit::commit].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OverrideDB<DB> {
+    /// Wrapped database.
+    pub db: DB,
+    /// Account overrides.
+    pub accounts: StateOverride,
+    /// Block hashes that are returned instead of the ones from the database.
+    pub block_hashes: BTreeMap<u64, B256>,
+    /// Bytecodes of overridden code by code hash.
+    contracts: HashMap<B256, Bytecode>,
+}
+
+impl<DB> OverrideDB<DB> {
+    /// Create new database with the state overrides.
+    ///
+    /// Returns error if both `state` and `stateDiff` are set for an account.
+    pub fn new(db: DB, accounts: StateOverride) -> Result<Self, StateOverrideError> {
+        let mut db = Self {
+            db,
+            accounts: StateOverride::default(),
+            block_hashes: BTreeMap::new(),
+            contracts: HashMap::new(),
+        };
+        db.set_overrides(accounts)?;
+        Ok(db)
+    }
+
+    /// Replace the account overrides.
+    ///
+    /// Returns error if both `state` and `stateDiff` are set for an account, in which case the
+    /// overrides are left unchanged.
+    pub fn set_overrides(&mut self, accounts: StateOverride) -> Result<(), StateOverrideError> {
+        if let Some((address, _)) = accounts
+            .iter()
+            .find(|(_, account)| account.state.is_some() && account.state_diff.is_some())
+        {
+            return Err(StateOverrideError::StateAndStateDiff(*address));
+        }
+        for account in accounts.values() {
+            if let Some(code) = &account.code {
+                let bytecode = Bytecode::new_raw(code.clone());
+                self.contracts.insert(bytecode.hash_slow(), bytecode);
+            }
+        }
+        self.accounts = accounts;
+        Ok(())
+    }
+
+    /// Add block hash overrides.
+    pub fn with_block_overrides(mut self, overrides: &BlockOverrides) -> Self {
+        if let Some(block_hashes) = &overrides.block_hashes {
+            self.block_hashes
+                .extend(block_hashes.iter().map(|(k, v)| (*k, *v)));
+        }
+        self
+    }
+
+    /// Return the wrapped database.
+    pub fn into_inner(self) -> DB {
+        self.db
+    }
+
+    /// Replace the account overrides of the committed accounts.
+    ///
+    /// Committed info already has the overridden fields applied. Storage overrides are kept, as
+    /// they also shadow the slots that were not committed, and are updated with the committed
+    /// slots. Overrides of destroyed or created accounts are dropped with their old storage.
+    fn commit_to_overrides(&mut self, changes: &HashMap<Address, Account>) {
+        for (address, account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            let Some(account_override) = self.accounts.get_mut(address) else {
+                continue;
+            };
+            account_override.balance = None;
+            account_override.nonce = None;
+            account_override.code = None;
+            if account.is_selfdestructed() || account.is_created() {
+                account_override.state = None;
+                account_override.state_diff = None;
+            }
+            if let Some(storage) = account_override
+                .state
+                .as_mut()
+                .or(account_override.state_diff.as_mut())
+            {
+                storage.extend(
+                    account
+                        .storage
+                        .iter()
+                        .map(|(slot, value)| (B256::from(*slot), value.present_value.into())),
+                );
+            }
+            if *account_override == AccountOverride::default() {
+                self.accounts.remove(address);
+            }
+        }
+    }
+
+    fn override_info(&self, address: Address, info: Option<AccountInfo>) -> Option<AccountInfo> {
+        let Some(account) = self.accounts.get(&address) else {
+            return info;
+        };
+        let mut info = info.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce;
+        }
+        if let Some(code) = &account.code {
+            let bytecode = Bytecode::new_raw(code.clone());
+            info.code_hash = if bytecode.is_empty() {
+                KECCAK_EMPTY
+            } else {
+                bytecode.hash_slow()
+            };
+            info.code = Some(bytecode);
+        }
+        Some(info)
+    }
+
+    /// Returns overridden storage value, `None` if it is not overridden.
+    fn override_storage(&self, address: Address, index: U256) -> Option<U256> {
+        let account = self.accounts.get(&address)?;
+        let key = B256::from(index);
+        if let Some(state) = &account.state {
+            return Some(state.get(&key).map(|v| (*v).into()).unwrap_or_default());
+        }
+        account
+            .state_diff
+            .as_ref()
+            .and_then(|diff| diff.get(&key))
+            .map(|v| (*v).into())
+    }
+}
+
+impl<DB: Database> Database for OverrideDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let info = self.db.basic(address)?;
+        Ok(self.override_info(address, info))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(bytecode) = self.contracts.get(&code_hash) {
+            return Ok(bytecode.clone());
+        }
+        self.db.code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.override_storage(address, index) {
+            Some(value) => Ok(value),
+            None => self.db.storage(address, index),
+        }
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.db.block_hash(number),
+        }
+    }
+}
+
+impl<DB: DatabaseRef> DatabaseRef for OverrideDB<DB> {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let info = self.db.basic_ref(address)?;
+        Ok(self.override_info(address, info))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(bytecode) = self.contracts.get(&code_hash) {
+            return Ok(bytecode.clone());
+        }
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.override_storage(address, index) {
+            Some(value) => Ok(value),
+            None => self.db.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.db.block_hash_ref(number),
+        }
+    }
+}
+
+impl<DB: Database + DatabaseCommit> OverrideDB<DB> {
+    /// Commit the account overrides to the wrapped database and clear them.
+    ///
+    /// Accounts with `state` override get their storage replaced, as if they were created.
+    pub fn commit_overrides(&mut self) -> Result<(), DB::Error> {
+        let mut changes = HashMap::with_capacity(self.accounts.len());
+        for (address, account) in &self.accounts {
+            let info = self.db.basic(*address)?;
+            let info = self.override_info(*address, info).unwrap_or_default();
+            let mut status = AccountStatus::Touched;
+            let mut storage = HashMap::new();
+            if let Some(state) = &account.state {
+                status |= AccountStatus::Created;
+                for (slot, value) in state {
+                    storage.insert(
+                        (*slot).into(),
+                        EvmStorageSlot::new_changed(U256::ZERO, (*value).into()),
+                    );
+                }
+            } else if let Some(state_diff) = &account.state_diff {
+                for (slot, value) in state_diff {
+                    let slot = (*slot).into();
+                    let original = self.db.storage(*address, slot)?;
+                    storage.insert(slot, EvmStorageSlot::new_changed(original, (*value).into()));
+                }
+            }
+            changes.insert(
+                *address,
+                Account {
+                    info,
+                    storage,
+                    status,
+                },
+            );
+        }
+        self.accounts.clear();
+        self.db.commit(changes);
+        Ok(())
+    }
+}
+
+/// Changes are committed to the wrapped database and replace the overrides of the committed
+/// accounts, so later reads see the committed state.
+impl<DB: DatabaseCommit> DatabaseCommit for OverrideDB<DB> {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        self.commit_to_overrides(&changes);
+        self.db.commit(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        precompile::PrecompileSpecId,
+        primitives::{address, bytes},
+    };
+
+    #[test]
+    fn account_overrides() {
+        let address = Address::with_last_byte(1);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            address,
+            AccountInfo {
+                balance: U256::from(1),
+                nonce: 5,
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(address, U256::from(1), U256::from(1))
+            .unwrap();
+        db.insert_account_storage(address, U256::from(2), U256::from(2))
+            .unwrap();
+
+        let code = bytes!("6001600055");
+        let mut db = OverrideDB::new(
+            db,
+            HashMap::from([(
+                address,
+                AccountOverride {
+                    balance: Some(U256::from(100)),
+                    code: Some(code.clone()),
+                    state_diff: Some(HashMap::from([(
+                        B256::from(U256::from(2)),
+                        B256::from(U256::from(20)),
+                    )])),
+                    ..Default::default()
+                },
+            )]),
+        )
+        .unwrap();
+
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(100));
+        assert_eq!(info.nonce, 5);
+        assert_eq!(
+            db.code_by_hash(info.code_hash).unwrap().original_bytes(),
+            code
+        );
+        assert_eq!(db.storage(address, U256::from(1)), Ok(U256::from(1)));
+        assert_eq!(db.storage(address, U256::from(2)), Ok(U256::from(20)));
+
+        // full state replaces the storage.
+        db.accounts.get_mut(&address).unwrap().state =
+            db.accounts.get_mut(&address).unwrap().state_diff.take();
+        assert_eq!(db.storage(address, U256::from(1)), Ok(U256::ZERO));
+        assert_eq!(db.storage(address, U256::from(2)), Ok(U256::from(20)));
+
+        // not existing accounts are created.
+        let other = Address::with_last_byte(2);
+        db.accounts.insert(
+            other,
+            AccountOverride {
+                nonce: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(db.basic(other).unwrap().unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn commit_overridden_account() {
+        let address = Address::with_last_byte(1);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(address, U256::from(1), U256::from(1))
+            .unwrap();
+        db.insert_account_storage(address, U256::from(2), U256::from(2))
+            .unwrap();
+        let mut db = OverrideDB::new(
+            db,
+            HashMap::from([(
+                address,
+                AccountOverride {
+                    balance: Some(U256::from(100)),
+                    nonce: Some(5),
+                    state_diff: Some(HashMap::from([
+                        (B256::from(U256::from(1)), B256::from(U256::from(10))),
+                        (B256::from(U256::from(2)), B256::from(U256::from(20))),
+                    ])),
+                    ..Default::default()
+                },
+            )]),
+        )
+        .unwrap();
+
+        let mut info = db.basic(address).unwrap().unwrap();
+        info.balance -= U256::from(1);
+        info.nonce += 1;
+        let mut account = Account::from(info);
+        account.mark_touch();
+        account.storage.insert(
+            U256::from(1),
+            EvmStorageSlot::new_changed(U256::from(10), U256::from(11)),
+        );
+        db.commit(HashMap::from([(address, account)]));
+
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(99));
+        assert_eq!(info.nonce, 6);
+        assert_eq!(db.storage(address, U256::from(1)), Ok(U256::from(11)));
+        // slot that was not committed is still overridden.
+        assert_eq!(db.storage(address, U256::from(2)), Ok(U256::from(20)));
+        assert_eq!(db.db.accounts[&address].info.nonce, 6);
+
+        // destroyed account drops the overrides.
+        let mut account = Account::from(info);
+        account.mark_touch();
+        account.mark_selfdestruct();
+        db.commit(HashMap::from([(address, account)]));
+        assert!(db.accounts.is_empty());
+        assert_eq!(db.storage(address, U256::from(2)), Ok(U256::ZERO));
+    }
+
+    #[test]
+    fn commit_overrides() {
+        let address = Address::with_last_byte(1);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(address, U256::from(1), U256::from(1))
+            .unwrap();
+        let mut db = OverrideDB::new(
+            db,
+            HashMap::from([(
+                address,
+                AccountOverride {
+                    balance: Some(U256::from(100)),
+                    state: Some(HashMap::from([(
+                        B256::from(U256::from(2)),
+                        B256::from(U256::from(20)),
+                    )])),
+                    ..Default::default()
+                },
+            )]),
+        )
+        .unwrap();
+        db.commit_overrides().unwrap();
+        assert!(db.accounts.is_empty());
+        assert_eq!(db.db.accounts[&address].info.balance, U256::from(100));
+        assert_eq!(db.storage(address, U256::from(1)), Ok(U256::ZERO));
+        assert_eq!(db.storage(address, U256::from(2)), Ok(U256::from(20)));
+    }
+
+    #[test]
+    fn state_and_state_diff() {
+        let address = Address::with_last_byte(1);
+        let result = OverrideDB::new(
+            EmptyDB::default(),
+            HashMap::from([(
+                address,
+                AccountOverride {
+                    state: Some(HashMap::new()),
+                    state_diff: Some(HashMap::new()),
+                    ..Default::default()
+                },
+            )]),
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            StateOverrideError::StateAndStateDiff(address)
+        );
+    }
+
+    #[test]
+    fn block_overrides() {
+        let mut db = OverrideDB::new(EmptyDB::default(), HashMap::new())
+            .unwrap()
+            .with_block_overrides(&BlockOverrides {
+                block_hashes: Some(BTreeMap::from([(1, B256::with_last_byte(1))])),
+                ..Default::default()
+            });
+        assert_eq!(db.block_hash(1), Ok(B256::with_last_byte(1)));
+
+        let mut block = BlockEnv::default();
+        BlockOverrides {
+            number: Some(U256::from(10)),
+            time: Some(U256::from(20)),
+            fee_recipient: Some(Address::with_last_byte(3)),
+            blob_base_fee: Some(U256::from(7)),
+            ..Default::default()
+        }
+        .apply(&mut block);
+        assert_eq!(block.number, U256::from(10));
+        assert_eq!(block.timestamp, U256::from(20));
+        assert_eq!(block.coinbase, Address::with_last_byte(3));
+        assert_eq!(block.get_blob_gasprice(), Some(7));
+    }
+
+    #[test]
+    fn move_precompiles() {
+        let ecrecover = address!("0000000000000000000000000000000000000001");
+        let sha256 = address!("0000000000000000000000000000000000000002");
+        let target = Address::with_last_byte(0xff);
+        let mut precompiles = ContextPrecompiles::<EmptyDB>::new(PrecompileSpecId::BERLIN);
+        let count = precompiles.addresses().count();
+        let overrides = HashMap::from([(
+            ecrecover,
+            AccountOverride {
+                move_precompile_to: Some(target),
+                ..Default::default()
+            },
+        )]);
+        apply_precompile_moves(&mut precompiles, &overrides).unwrap();
+        assert!(!precompiles.contains(&ecrecover));
+        assert!(precompiles.contains(&target));
+        assert_eq!(precompiles.addresses().count(), count);
+
+        let overrides = HashMap::from([(
+            ecrecover,
+            AccountOverride {
+                move_precompile_to: Some(sha256),
+                ..Default::default()
+            },
+        )]);
+        assert_eq!(
+            apply_precompile_moves(&mut precompiles, &overrides),
+            Err(StateOverrideError::NotAPrecompile(ecrecover))
+        );
+
+        // Targets that are overridden or keep their precompile are rejected.
+        let mut precompiles = ContextPrecompiles::<EmptyDB>::new(PrecompileSpecId::BERLIN);
+        let move_to = |target| AccountOverride {
+            move_precompile_to: Some(target),
+            ..Default::default()
+        };
+        let overridden = HashMap::from([
+            (ecrecover, move_to(target)),
+            (target, AccountOverride::default()),
+        ]);
+        assert_eq!(
+            apply_precompile_moves(&mut precompiles, &overridden),
+            Err(StateOverrideError::AlreadyOverridden(target))
+        );
+        let onto_precompile = HashMap::from([(ecrecover, move_to(sha256))]);
+        assert_eq!(
+            apply_precompile_moves(&mut precompiles, &onto_precompile),
+            Err(StateOverrideError::AlreadyOverridden(sha256))
+        );
+        assert_eq!(
+            StateOverrideError::AlreadyOverridden(sha256).to_string(),
+            format!("account {sha256} is already overridden")
+        );
+
+        // Precompiles can swap addresses.
+        let swap = HashMap::from([(ecrecover, move_to(sha256)), (sha256, move_to(ecrecover))]);
+        apply_precompile_moves(&mut precompiles, &swap).unwrap();
+        assert!(precompiles.contains(&ecrecover) && precompiles.contains(&sha256));
+        assert_eq!(precompiles.addresses().count(), count);
+    }
+
+    #[test]
+    fn moved_precompile_in_evm() {
+        use crate::{primitives::TxKind, Evm};
+        use std::sync::Arc;
+
+        let identity = Address::with_last_byte(4);
+        let target = Address::with_last_byte(0xff);
+        let overrides = HashMap::from([(
+            identity,
+            AccountOverride {
+                move_precompile_to: Some(target),
+                ..Default::default()
+            },
+        )]);
+        let db = OverrideDB::new(EmptyDB::default(), overrides.clone()).unwrap();
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .append_handler_register_box(Box::new(move |handler| {
+                let mut precompiles = handler.pre_execution.load_precompiles();
+                apply_precompile_moves(&mut precompiles, &overrides).unwrap();
+                handler.pre_execution.load_precompiles = Arc::new(move || precompiles.clone());
+            }))
+            .modify_tx_env(|tx| {
+                tx.transact_to = TxKind::Call(target);
+                tx.data = bytes!("c0ffee");
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        assert_eq!(result.output(), Some(&bytes!("c0ffee")));
+
+        evm.tx_mut().transact_to = TxKind::Call(identity);
+        let result = evm.transact().unwrap().result;
+        assert_eq!(result.output(), Some(&Bytes::new()));
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn deserialize_geth_format() {
+        let overrides: StateOverride = serde_json::from_str(
+            r#"{
+                "0x0000000000000000000000000000000000000001": {
+                    "balance": "0x10",
+                    "nonce": "0x2",
+                    "code": "0x6001",
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                    },
+                    "movePrecompileToAddress": "0x00000000000000000000000000000000000000ff"
+                }
+            }"#,
+        )
+        .unwrap();
+        let account = &overrides[&Address::with_last_byte(1)];
+        assert_eq!(account.balance, Some(U256::from(16)));
+        assert_eq!(account.nonce, Some(2));
+        assert_eq!(account.code, Some(bytes!("6001")));
+        assert_eq!(
+            account.move_precompile_to,
+            Some(Address::with_last_byte(0xff))
+        );
+
+        let block: BlockOverrides =
+            serde_json::from_str(r#"{"number": "0x5", "coinbase": "0x00000000000000000000000000000000000000aa", "baseFee": "0x7"}"#)
+                .unwrap();
+        assert_eq!(block.number, Some(U256::from(5)));
+        assert_eq!(block.fee_recipient, Some(Address::with_last_byte(0xaa)));
+        assert_eq!(block.base_fee_per_gas, Some(U256::from(7)));
+    }
+}
@@ -4,6 +4,9 @@ pub mod bundle_state;
 pub mod cache;
 pub mod cache_account;
 pub mod changes;
+pub mod codec;
+pub mod diff;
+pub mod observer;
 pub mod plain_account;
 pub mod reverts;
 pub mod state;
@@ -17,7 +20,16 @@ pub use bundle_account::BundleAccount;
 pub use bundle_state::{BundleBuilder, BundleState, OriginalValuesKnown};
 pub use cache::CacheState;
 pub use cache_account::CacheAccount;
-pub use changes::{PlainStateReverts, PlainStorageChangeset, PlainStorageRevert, StateChangeset};
+pub use changes::{
+    DatabaseWrite, PlainStateReverts, PlainStorageChangeset, PlainStorageRevert, StateChangeset,
+};
+pub use codec::{BundleDecodeError, BUNDLE_ENCODING_VERSION};
+pub use diff::{AccountDiff, BundleConflict, BundleDiff, Change};
+#[cfg(feature = "std")]
+pub use observer::ChannelObserver;
+pub use observer::{
+    FilteredObserver, StateEvent, StateObserver, StateObservers, TransitionPosition,
+};
 pub use plain_account::{PlainAccount, StorageSlot, StorageWithOriginalValues};
 pub use reverts::{AccountRevert, RevertToSlot};
 pub use state::{DBBox, State, StateDBBox};
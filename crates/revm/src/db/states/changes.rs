@@ -1,4 +1,5 @@
 use super::RevertToSlot;
+use auto_impl::auto_impl;
 use revm_interpreter::primitives::{AccountInfo, Address, Bytecode, B256, U256};
 use std::vec::Vec;
 
@@ -69,3 +70,78 @@ impl PlainStateReverts {
 
 /// Storage reverts
 pub type StorageRevert = Vec<Vec<(Address, bool, Vec<(U256, RevertToSlot)>)>>;
+
+/// Database that can persist plain state created by
+/// [BundleState::into_plain_state](super::BundleState::into_plain_state).
+///
+/// Unlike [DatabaseCommit](crate::DatabaseCommit) that consumes EVM state of a single transaction,
+/// this writes already aggregated changes, the same way a database backend would store them.
+#[auto_impl(&mut, Box)]
+pub trait DatabaseWrite {
+    /// The database error type.
+    type Error;
+
+    /// Set account info, `None` removes the account.
+    ///
+    /// Account info does not contain bytecode, it is written with [DatabaseWrite::set_contract].
+    fn set_account(
+        &mut self,
+        address: Address,
+        info: Option<AccountInfo>,
+    ) -> Result<(), Self::Error>;
+
+    /// Remove all storage of the account.
+    fn wipe_storage(&mut self, address: Address) -> Result<(), Self::Error>;
+
+    /// Set storage slot of the account.
+    fn set_storage(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Result<(), Self::Error>;
+
+    /// Insert contract bytecode by its hash.
+    fn set_contract(&mut self, code_hash: B256, bytecode: Bytecode) -> Result<(), Self::Error>;
+
+    /// Apply plain state changes.
+    ///
+    /// Accounts are written first, then storage and at the end contracts.
+    fn apply_changeset(&mut self, changeset: StateChangeset) -> Result<(), Self::Error> {
+        for (address, info) in changeset.accounts {
+            self.set_account(address, info)?;
+        }
+        for storage in changeset.storage {
+            if storage.wipe_storage {
+                self.wipe_storage(storage.address)?;
+            }
+            for (index, value) in storage.storage {
+                self.set_storage(storage.address, index, value)?;
+            }
+        }
+        for (code_hash, bytecode) in changeset.contracts {
+            self.set_contract(code_hash, bytecode)?;
+        }
+        Ok(())
+    }
+
+    /// Apply reverts on top of the plain state, going from the latest transition to the first one.
+    ///
+    /// After a wipe is reverted, only storage slots recorded in the reverts are restored.
+    fn apply_reverts(&mut self, reverts: PlainStateReverts) -> Result<(), Self::Error> {
+        for (accounts, storage) in reverts.accounts.into_iter().zip(reverts.storage).rev() {
+            for (address, info) in accounts {
+                self.set_account(address, info)?;
+            }
+            for revert in storage {
+                if revert.wiped {
+                    self.wipe_storage(revert.address)?;
+                }
+                for (index, value) in revert.storage_revert {
+                    self.set_storage(revert.address, index, value.to_previous_value())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,672 @@
+//! Compact binary encoding of [BundleState].
+//!
+//! Encoding is deterministic: accounts, storage slots and contracts are written
+//! sorted by their keys, so two equal bundles always produce the same bytes.
+//!
+//! All integers are LEB128 encoded and [U256] values are written as a length
+//! prefix followed by big-endian bytes without leading zeros.
+use super::{
+    reverts::{AccountInfoRevert, Reverts},
+    AccountRevert, AccountStatus, BundleAccount, BundleState, RevertToSlot, StorageSlot,
+};
+use core::fmt;
+use revm_interpreter::primitives::{
+    bitvec::vec::BitVec, eof::EofDecodeError, AccountInfo, Address, Bytecode, Bytes, Eof, HashMap,
+    JumpTable, LegacyAnalyzedBytecode, B256, U256,
+};
+use std::{sync::Arc, vec::Vec};
+
+/// Version of the encoding format, written as the first byte.
+pub const BUNDLE_ENCODING_VERSION: u8 = 1;
+
+/// Error returned when decoding of [BundleState] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BundleDecodeError {
+    /// Input ended before the value was fully read.
+    UnexpectedEnd,
+    /// Encoding version is not supported.
+    UnsupportedVersion(u8),
+    /// Unknown tag byte for the given item.
+    InvalidTag {
+        /// Item that was being decoded.
+        item: &'static str,
+        /// Tag that was found.
+        tag: u8,
+    },
+    /// Integer does not fit into the target type.
+    Overflow,
+    /// EOF bytecode could not be decoded.
+    InvalidEof(EofDecodeError),
+    /// Bytes are left after the bundle was decoded.
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for BundleDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("Unexpected end of input"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported bundle encoding version {version}")
+            }
+            Self::InvalidTag { item, tag } => write!(f, "Invalid tag {tag} for {item}"),
+            Self::Overflow => f.write_str("Integer overflow"),
+            Self::InvalidEof(e) => write!(f, "Invalid EOF bytecode: {e}"),
+            Self::TrailingBytes(len) => write!(f, "{len} trailing bytes after bundle"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BundleDecodeError {}
+
+impl BundleState {
+    /// Encode the bundle into compact binary form.
+    ///
+    /// Bundle can be restored with [BundleState::decode].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 * self.size_hint() + 16);
+        self.encode_to(&mut out);
+        out
+    }
+
+    /// Encode the bundle and append it to `out`.
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(BUNDLE_ENCODING_VERSION);
+
+        let mut state = self.state.iter().collect::<Vec<_>>();
+        state.sort_unstable_by_key(|(address, _)| *address);
+        put_len(out, state.len());
+        for (address, account) in state {
+            out.extend_from_slice(address.as_slice());
+            put_account(out, account);
+        }
+
+        let mut contracts = self.contracts.iter().collect::<Vec<_>>();
+        contracts.sort_unstable_by_key(|(hash, _)| *hash);
+        put_len(out, contracts.len());
+        for (hash, bytecode) in contracts {
+            out.extend_from_slice(hash.as_slice());
+            put_bytecode(out, bytecode);
+        }
+
+        put_len(out, self.reverts.len());
+        for block_reverts in self.reverts.iter() {
+            put_len(out, block_reverts.len());
+            for (address, revert) in block_reverts {
+                out.extend_from_slice(address.as_slice());
+                put_account_revert(out, revert);
+            }
+        }
+
+        put_len(out, self.state_size);
+        put_len(out, self.reverts_size);
+    }
+
+    /// Decode the bundle from bytes created by [BundleState::encode].
+    pub fn decode(bytes: &[u8]) -> Result<Self, BundleDecodeError> {
+        let mut buf = bytes;
+        let bundle = Self::decode_from(&mut buf)?;
+        if !buf.is_empty() {
+            return Err(BundleDecodeError::TrailingBytes(buf.len()));
+        }
+        Ok(bundle)
+    }
+
+    /// Decode the bundle from the start of `buf` and advance it past the read bytes.
+    pub fn decode_from(buf: &mut &[u8]) -> Result<Self, BundleDecodeError> {
+        let mut reader = Reader(buf);
+        let version = reader.u8()?;
+        if version != BUNDLE_ENCODING_VERSION {
+            return Err(BundleDecodeError::UnsupportedVersion(version));
+        }
+
+        let len = reader.len()?;
+        let mut state = HashMap::with_capacity(reader.capacity(len));
+        for _ in 0..len {
+            let address = reader.address()?;
+            state.insert(address, reader.account()?);
+        }
+
+        let len = reader.len()?;
+        let mut contracts = HashMap::with_capacity(reader.capacity(len));
+        for _ in 0..len {
+            let hash = reader.b256()?;
+            contracts.insert(hash, reader.bytecode()?);
+        }
+
+        let len = reader.len()?;
+        let mut reverts = Vec::with_capacity(reader.capacity(len));
+        for _ in 0..len {
+            let len = reader.len()?;
+            let mut block_reverts = Vec::with_capacity(reader.capacity(len));
+            for _ in 0..len {
+                let address = reader.address()?;
+                block_reverts.push((address, reader.account_revert()?));
+            }
+            reverts.push(block_reverts);
+        }
+
+        let state_size = reader.len()?;
+        let reverts_size = reader.len()?;
+        *buf = reader.0;
+
+        Ok(BundleState {
+            state,
+            contracts,
+            reverts: Reverts::new(reverts),
+            state_size,
+            reverts_size,
+        })
+    }
+}
+
+fn put_u64(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_len(out: &mut Vec<u8>, len: usize) {
+    put_u64(out, len as u64)
+}
+
+fn put_u256(out: &mut Vec<u8>, value: &U256) {
+    let bytes = value.to_be_bytes::<32>();
+    let skip = (value.leading_zeros() / 8).min(32);
+    out.push((32 - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_len(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn put_status(out: &mut Vec<u8>, status: AccountStatus) {
+    out.push(match status {
+        AccountStatus::LoadedNotExisting => 0,
+        AccountStatus::Loaded => 1,
+        AccountStatus::LoadedEmptyEIP161 => 2,
+        AccountStatus::InMemoryChange => 3,
+        AccountStatus::Changed => 4,
+        AccountStatus::Destroyed => 5,
+        AccountStatus::DestroyedChanged => 6,
+        AccountStatus::DestroyedAgain => 7,
+    })
+}
+
+fn put_bytecode(out: &mut Vec<u8>, bytecode: &Bytecode) {
+    match bytecode {
+        Bytecode::LegacyRaw(bytes) => {
+            out.push(0);
+            put_bytes(out, bytes);
+        }
+        Bytecode::LegacyAnalyzed(analyzed) => {
+            out.push(1);
+            put_bytes(out, analyzed.bytecode());
+            put_len(out, analyzed.original_len());
+            put_len(out, analyzed.jump_table().0.len());
+            put_bytes(out, analyzed.jump_table().as_slice());
+        }
+        Bytecode::Eof(eof) => {
+            out.push(2);
+            put_bytes(out, eof.raw());
+        }
+    }
+}
+
+fn put_account_info(out: &mut Vec<u8>, info: Option<&AccountInfo>) {
+    let Some(info) = info else {
+        out.push(0);
+        return;
+    };
+    match &info.code {
+        Some(code) => {
+            out.push(2);
+            put_account_info_fields(out, info);
+            put_bytecode(out, code);
+        }
+        None => {
+            out.push(1);
+            put_account_info_fields(out, info);
+        }
+    }
+}
+
+fn put_account_info_fields(out: &mut Vec<u8>, info: &AccountInfo) {
+    put_u256(out, &info.balance);
+    put_u64(out, info.nonce);
+    out.extend_from_slice(info.code_hash.as_slice());
+}
+
+fn put_account(out: &mut Vec<u8>, account: &BundleAccount) {
+    put_status(out, account.status);
+    put_account_info(out, account.info.as_ref());
+    put_account_info(out, account.original_info.as_ref());
+
+    let mut storage = account.storage.iter().collect::<Vec<_>>();
+    storage.sort_unstable_by_key(|(key, _)| *key);
+    put_len(out, storage.len());
+    for (key, slot) in storage {
+        put_u256(out, key);
+        put_u256(out, &slot.previous_or_original_value);
+        put_u256(out, &slot.present_value);
+    }
+}
+
+fn put_account_revert(out: &mut Vec<u8>, revert: &AccountRevert) {
+    match &revert.account {
+        AccountInfoRevert::DoNothing => out.push(0),
+        AccountInfoRevert::DeleteIt => out.push(1),
+        AccountInfoRevert::RevertTo(info) => {
+            out.push(2);
+            put_account_info(out, Some(info));
+        }
+    }
+    put_status(out, revert.previous_status);
+    out.push(revert.wipe_storage as u8);
+
+    let mut storage = revert.storage.iter().collect::<Vec<_>>();
+    storage.sort_unstable_by_key(|(key, _)| *key);
+    put_len(out, storage.len());
+    for (key, slot) in storage {
+        put_u256(out, key);
+        match slot {
+            RevertToSlot::Some(value) => {
+                out.push(0);
+                put_u256(out, value);
+            }
+            RevertToSlot::Destroyed => out.push(1),
+        }
+    }
+}
+
+/// Cursor over the encoded bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BundleDecodeError> {
+        if self.0.len() < len {
+            return Err(BundleDecodeError::UnexpectedEnd);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    /// Capacity to pre-allocate for `len` items. Every item takes at least one byte, so the
+    /// capacity is bounded by the remaining input instead of trusting the decoded length.
+    fn capacity(&self, len: usize) -> usize {
+        len.min(self.0.len())
+    }
+
+    fn u8(&mut self) -> Result<u8, BundleDecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, BundleDecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(BundleDecodeError::InvalidTag { item: "bool", tag }),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, BundleDecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(BundleDecodeError::Overflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BundleDecodeError::Overflow)
+    }
+
+    fn len(&mut self) -> Result<usize, BundleDecodeError> {
+        usize::try_from(self.u64()?).map_err(|_| BundleDecodeError::Overflow)
+    }
+
+    fn u256(&mut self) -> Result<U256, BundleDecodeError> {
+        let len = self.u8()? as usize;
+        if len > 32 {
+            return Err(BundleDecodeError::Overflow);
+        }
+        Ok(U256::from_be_slice(self.take(len)?))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], BundleDecodeError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn address(&mut self) -> Result<Address, BundleDecodeError> {
+        Ok(Address::from_slice(self.take(20)?))
+    }
+
+    fn b256(&mut self) -> Result<B256, BundleDecodeError> {
+        Ok(B256::from_slice(self.take(32)?))
+    }
+
+    fn status(&mut self) -> Result<AccountStatus, BundleDecodeError> {
+        Ok(match self.u8()? {
+            0 => AccountStatus::LoadedNotExisting,
+            1 => AccountStatus::Loaded,
+            2 => AccountStatus::LoadedEmptyEIP161,
+            3 => AccountStatus::InMemoryChange,
+            4 => AccountStatus::Changed,
+            5 => AccountStatus::Destroyed,
+            6 => AccountStatus::DestroyedChanged,
+            7 => AccountStatus::DestroyedAgain,
+            tag => {
+                return Err(BundleDecodeError::InvalidTag {
+                    item: "account status",
+                    tag,
+                })
+            }
+        })
+    }
+
+    fn bytecode(&mut self) -> Result<Bytecode, BundleDecodeError> {
+        match self.u8()? {
+            0 => Ok(Bytecode::LegacyRaw(Bytes::copy_from_slice(self.bytes()?))),
+            1 => {
+                let bytecode = Bytes::copy_from_slice(self.bytes()?);
+                let original_len = self.len()?;
+                let jump_table_len = self.len()?;
+                let mut jump_table = BitVec::from_slice(self.bytes()?);
+                if original_len > bytecode.len() || jump_table_len > jump_table.len() {
+                    return Err(BundleDecodeError::Overflow);
+                }
+                jump_table.truncate(jump_table_len);
+                Ok(Bytecode::LegacyAnalyzed(LegacyAnalyzedBytecode::new(
+                    bytecode,
+                    original_len,
+                    JumpTable(Arc::new(jump_table)),
+                )))
+            }
+            2 => {
+                let raw = Bytes::copy_from_slice(self.bytes()?);
+                let eof = Eof::decode(raw).map_err(BundleDecodeError::InvalidEof)?;
+                Ok(Bytecode::Eof(Arc::new(eof)))
+            }
+            tag => Err(BundleDecodeError::InvalidTag {
+                item: "bytecode",
+                tag,
+            }),
+        }
+    }
+
+    fn account_info_fields(&mut self) -> Result<AccountInfo, BundleDecodeError> {
+        Ok(AccountInfo {
+            balance: self.u256()?,
+            nonce: self.u64()?,
+            code_hash: self.b256()?,
+            code: None,
+        })
+    }
+
+    fn account_info(&mut self) -> Result<Option<AccountInfo>, BundleDecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => self.account_info_fields().map(Some),
+            2 => {
+                let mut info = self.account_info_fields()?;
+                info.code = Some(self.bytecode()?);
+                Ok(Some(info))
+            }
+            tag => Err(BundleDecodeError::InvalidTag {
+                item: "account info",
+                tag,
+            }),
+        }
+    }
+
+    fn account(&mut self) -> Result<BundleAccount, BundleDecodeError> {
+        let status = self.status()?;
+        let info = self.account_info()?;
+        let original_info = self.account_info()?;
+        let len = self.len()?;
+        let mut storage = HashMap::with_capacity(self.capacity(len));
+        for _ in 0..len {
+            let key = self.u256()?;
+            let previous = self.u256()?;
+            let present = self.u256()?;
+            storage.insert(key, StorageSlot::new_changed(previous, present));
+        }
+        Ok(BundleAccount::new(original_info, info, storage, status))
+    }
+
+    fn account_revert(&mut self) -> Result<AccountRevert, BundleDecodeError> {
+        let account = match self.u8()? {
+            0 => AccountInfoRevert::DoNothing,
+            1 => AccountInfoRevert::DeleteIt,
+            2 => match self.account_info()? {
+                Some(info) => AccountInfoRevert::RevertTo(info),
+                None => {
+                    return Err(BundleDecodeError::InvalidTag {
+                        item: "account info",
+                        tag: 0,
+                    })
+                }
+            },
+            tag => {
+                return Err(BundleDecodeError::InvalidTag {
+                    item: "account revert",
+                    tag,
+                })
+            }
+        };
+        let previous_status = self.status()?;
+        let wipe_storage = self.bool()?;
+        let len = self.len()?;
+        let mut storage = HashMap::with_capacity(self.capacity(len));
+        for _ in 0..len {
+            let key = self.u256()?;
+            let slot = match self.u8()? {
+                0 => RevertToSlot::Some(self.u256()?),
+                1 => RevertToSlot::Destroyed,
+                tag => {
+                    return Err(BundleDecodeError::InvalidTag {
+                        item: "revert slot",
+                        tag,
+                    })
+                }
+            };
+            storage.insert(key, slot);
+        }
+        Ok(AccountRevert {
+            account,
+            storage,
+            previous_status,
+            wipe_storage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::states::bundle_state::BundleRetention, interpreter::analysis::to_analysed,
+        primitives::KECCAK_EMPTY, TransitionAccount, TransitionState,
+    };
+
+    fn bundle() -> BundleState {
+        let code = to_analysed(Bytecode::new_raw(Bytes::from_static(&[
+            0x60, 0x01, 0x5b, 0x00,
+        ])));
+        let code_hash = code.hash_slow();
+        BundleState::new(
+            vec![
+                (
+                    Address::with_last_byte(1),
+                    None,
+                    Some(AccountInfo {
+                        balance: U256::from(1000),
+                        nonce: 1,
+                        code_hash,
+                        code: Some(code.clone()),
+                    }),
+                    HashMap::from([
+                        (U256::from(1), (U256::ZERO, U256::from(10))),
+                        (U256::MAX, (U256::from(3), U256::ZERO)),
+                    ]),
+                ),
+                (
+                    Address::with_last_byte(2),
+                    Some(AccountInfo {
+                        balance: U256::from(7),
+                        nonce: u64::MAX,
+                        code_hash: KECCAK_EMPTY,
+                        code: None,
+                    }),
+                    None,
+                    HashMap::new(),
+                ),
+            ],
+            vec![
+                vec![(Address::with_last_byte(1), Some(None), vec![])],
+                vec![(
+                    Address::with_last_byte(2),
+                    Some(Some(AccountInfo::default())),
+                    vec![(U256::from(5), U256::from(6))],
+                )],
+            ],
+            vec![(code_hash, code), (KECCAK_EMPTY, Bytecode::new())],
+        )
+    }
+
+    #[test]
+    fn roundtrip() {
+        let bundle = bundle();
+        let encoded = bundle.encode();
+        let decoded = BundleState::decode(&encoded).unwrap();
+        assert_eq!(decoded, bundle);
+        // `AccountInfo` equality ignores the code, compare it explicitly.
+        for (address, account) in &bundle.state {
+            let decoded = &decoded.state[address];
+            let code = |info: &Option<AccountInfo>| info.as_ref().map(|info| info.code.clone());
+            assert_eq!(code(&decoded.info), code(&account.info));
+            assert_eq!(code(&decoded.original_info), code(&account.original_info));
+        }
+        assert!(decoded.state[&Address::with_last_byte(1)]
+            .info
+            .as_ref()
+            .is_some_and(|info| info.code.is_some()));
+        // encoding is deterministic.
+        assert_eq!(bundle.clone().encode(), encoded);
+    }
+
+    #[test]
+    fn roundtrip_transitions() {
+        let mut bundle = BundleState::default();
+        let address = Address::with_last_byte(9);
+        let mut storage = HashMap::new();
+        storage.insert(
+            U256::from(1),
+            StorageSlot::new_changed(U256::ZERO, U256::from(1)),
+        );
+        bundle.apply_transitions_and_create_reverts(
+            TransitionState::single(
+                address,
+                TransitionAccount {
+                    info: Some(AccountInfo::from_balance(U256::from(1))),
+                    status: AccountStatus::DestroyedChanged,
+                    previous_info: None,
+                    previous_status: AccountStatus::Loaded,
+                    storage,
+                    storage_was_destroyed: true,
+                },
+            ),
+            BundleRetention::Reverts,
+        );
+        let encoded = bundle.encode();
+        assert_eq!(BundleState::decode(&encoded), Ok(bundle));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let encoded = bundle().encode();
+        assert_eq!(
+            BundleState::decode(&encoded[..encoded.len() - 1]),
+            Err(BundleDecodeError::UnexpectedEnd)
+        );
+
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert_eq!(
+            BundleState::decode(&trailing),
+            Err(BundleDecodeError::TrailingBytes(1))
+        );
+
+        let mut version = encoded;
+        version[0] = 0xff;
+        assert_eq!(
+            BundleState::decode(&version),
+            Err(BundleDecodeError::UnsupportedVersion(0xff))
+        );
+    }
+
+    #[test]
+    fn decode_huge_lengths() {
+        // Lengths of 2^60 items must fail on the missing items instead of pre-allocating them.
+        let huge = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x10];
+        let with_prefix = |prefix: &[u8]| [prefix, &huge[..]].concat();
+        for encoded in [
+            // state
+            with_prefix(&[BUNDLE_ENCODING_VERSION]),
+            // contracts
+            with_prefix(&[BUNDLE_ENCODING_VERSION, 0]),
+            // reverts
+            with_prefix(&[BUNDLE_ENCODING_VERSION, 0, 0]),
+            // reverts of a block
+            with_prefix(&[BUNDLE_ENCODING_VERSION, 0, 0, 1]),
+        ] {
+            assert_eq!(
+                BundleState::decode(&encoded),
+                Err(BundleDecodeError::UnexpectedEnd)
+            );
+        }
+
+        // storage of an account: address, `Loaded` status, no infos.
+        let mut encoded = std::vec![BUNDLE_ENCODING_VERSION, 1];
+        encoded.extend_from_slice(&[0; 20]);
+        encoded.extend_from_slice(&[1, 0, 0]);
+        encoded.extend_from_slice(&huge);
+        assert_eq!(
+            BundleState::decode(&encoded),
+            Err(BundleDecodeError::UnexpectedEnd)
+        );
+
+        // storage of an account revert: address, `DoNothing`, `Loaded` status, no wipe.
+        let mut encoded = std::vec![BUNDLE_ENCODING_VERSION, 0, 0, 1, 1];
+        encoded.extend_from_slice(&[0; 20]);
+        encoded.extend_from_slice(&[0, 1, 0]);
+        encoded.extend_from_slice(&huge);
+        assert_eq!(
+            BundleState::decode(&encoded),
+            Err(BundleDecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn decode_from_stream() {
+        let first = bundle();
+        let second = BundleState::default();
+        let mut buf = first.encode();
+        second.encode_to(&mut buf);
+
+        let mut slice = buf.as_slice();
+        assert_eq!(BundleState::decode_from(&mut slice), Ok(first));
+        assert_eq!(BundleState::decode_from(&mut slice), Ok(second));
+        assert!(slice.is_empty());
+    }
+}
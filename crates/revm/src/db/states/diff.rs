@@ -0,0 +1,401 @@
+use super::{AccountStatus, BundleAccount, BundleState};
+use revm_interpreter::primitives::{AccountInfo, Address, Bytecode, B256, U256};
+use std::{collections::BTreeMap, vec::Vec};
+
+/// Difference of a single value between two bundles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change<T> {
+    /// Value is present only in the other bundle.
+    Added(T),
+    /// Value is present only in this bundle.
+    Removed(T),
+    /// Value is present in both bundles but differs.
+    Modified {
+        /// Value in this bundle.
+        old: T,
+        /// Value in the other bundle.
+        new: T,
+    },
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Compare two optional values and return the change, if any.
+    pub fn between(old: Option<T>, new: Option<T>) -> Option<Self> {
+        match (old, new) {
+            (None, None) => None,
+            (None, Some(new)) => Some(Self::Added(new)),
+            (Some(old), None) => Some(Self::Removed(old)),
+            (Some(old), Some(new)) if old == new => None,
+            (Some(old), Some(new)) => Some(Self::Modified { old, new }),
+        }
+    }
+}
+
+/// Difference of one account between two bundles.
+///
+/// Only the fields that differ are set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountDiff {
+    /// Change of the present account info. Bytecode is compared by code hash.
+    pub info: Option<Change<AccountInfo>>,
+    /// Change of the account status.
+    pub status: Option<Change<AccountStatus>>,
+    /// Change of present storage values, by slot.
+    pub storage: BTreeMap<U256, Change<U256>>,
+}
+
+impl AccountDiff {
+    /// Return true if there is no difference.
+    pub fn is_empty(&self) -> bool {
+        self.info.is_none() && self.status.is_none() && self.storage.is_empty()
+    }
+}
+
+/// Structured difference between two [BundleState]s.
+///
+/// Created by [BundleState::diff], where `old` is the bundle the method is
+/// called on and `new` is the argument. Reverts are not compared.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BundleDiff {
+    /// Accounts that differ, sorted by address.
+    pub accounts: BTreeMap<Address, AccountDiff>,
+    /// Contracts that differ, sorted by code hash.
+    pub contracts: BTreeMap<B256, Change<Bytecode>>,
+}
+
+impl BundleDiff {
+    /// Return true if bundles are equal at account, storage and code level.
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.contracts.is_empty()
+    }
+}
+
+/// Conflict found when a bundle is extended with a bundle that was built
+/// on top of a different base state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BundleConflict {
+    /// Original account info of the other bundle does not match present info of this one.
+    AccountInfo {
+        /// Address of the account.
+        address: Address,
+        /// Present info in this bundle.
+        present: Option<AccountInfo>,
+        /// Original info the other bundle was built on.
+        original: Option<AccountInfo>,
+    },
+    /// Original storage value of the other bundle does not match present value of this one.
+    Storage {
+        /// Address of the account.
+        address: Address,
+        /// Storage slot.
+        slot: U256,
+        /// Present value in this bundle.
+        present: U256,
+        /// Original value the other bundle was built on.
+        original: U256,
+    },
+}
+
+impl BundleState {
+    /// Compare this bundle with `other` at account, storage and code level.
+    pub fn diff(&self, other: &BundleState) -> BundleDiff {
+        let mut accounts = BTreeMap::new();
+        for (address, old) in &self.state {
+            let diff = account_diff(Some(old), other.state.get(address));
+            if !diff.is_empty() {
+                accounts.insert(*address, diff);
+            }
+        }
+        for (address, new) in &other.state {
+            if !self.state.contains_key(address) {
+                accounts.insert(*address, account_diff(None, Some(new)));
+            }
+        }
+
+        let mut contracts = BTreeMap::new();
+        for (hash, old) in &self.contracts {
+            let new = other.contracts.get(hash);
+            if new.map(Bytecode::original_byte_slice) != Some(old.original_byte_slice()) {
+                let change = match new {
+                    Some(new) => Change::Modified {
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                    None => Change::Removed(old.clone()),
+                };
+                contracts.insert(*hash, change);
+            }
+        }
+        for (hash, new) in &other.contracts {
+            if !self.contracts.contains_key(hash) {
+                contracts.insert(*hash, Change::Added(new.clone()));
+            }
+        }
+
+        BundleDiff {
+            accounts,
+            contracts,
+        }
+    }
+
+    /// Find conflicts that would arise if `other` is applied on top of this bundle.
+    ///
+    /// `other` is expected to be built on top of the state this bundle ends with,
+    /// so original values that `other` recorded need to be equal to present values
+    /// of this bundle. Storage of accounts that were destroyed in `other` is not checked,
+    /// nor are accounts or slots that this bundle does not know about.
+    pub fn conflicts(&self, other: &BundleState) -> Vec<BundleConflict> {
+        let mut conflicts = Vec::new();
+        let mut addresses = other.state.keys().collect::<Vec<_>>();
+        addresses.sort_unstable();
+        for address in addresses {
+            let Some(this) = self.state.get(address) else {
+                continue;
+            };
+            let other = &other.state[address];
+
+            if this.info != other.original_info {
+                conflicts.push(BundleConflict::AccountInfo {
+                    address: *address,
+                    present: this.info.clone(),
+                    original: other.original_info.clone(),
+                });
+            }
+
+            if other.was_destroyed() {
+                continue;
+            }
+            let mut slots = other.storage.iter().collect::<Vec<_>>();
+            slots.sort_unstable_by_key(|(slot, _)| *slot);
+            for (slot, value) in slots {
+                let Some(present) = this.storage_slot(*slot) else {
+                    continue;
+                };
+                if present != value.previous_or_original_value {
+                    conflicts.push(BundleConflict::Storage {
+                        address: *address,
+                        slot: *slot,
+                        present,
+                        original: value.previous_or_original_value,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Extend the bundle with `other` if there are no [conflicts](Self::conflicts).
+    ///
+    /// On conflict the bundle is left unchanged and `other` is dropped.
+    pub fn try_extend(&mut self, other: Self) -> Result<(), Vec<BundleConflict>> {
+        let conflicts = self.conflicts(&other);
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        self.extend(other);
+        Ok(())
+    }
+}
+
+fn account_diff(old: Option<&BundleAccount>, new: Option<&BundleAccount>) -> AccountDiff {
+    let info = Change::between(
+        old.and_then(|a| a.info.clone()),
+        new.and_then(|a| a.info.clone()),
+    );
+    let status = Change::between(old.map(|a| a.status), new.map(|a| a.status));
+
+    let mut storage = BTreeMap::new();
+    if let Some(old) = old {
+        for (slot, value) in &old.storage {
+            let new_value = new
+                .and_then(|a| a.storage.get(slot))
+                .map(|s| s.present_value);
+            if let Some(change) = Change::between(Some(value.present_value), new_value) {
+                storage.insert(*slot, change);
+            }
+        }
+    }
+    if let Some(new) = new {
+        for (slot, value) in &new.storage {
+            if !old.is_some_and(|a| a.storage.contains_key(slot)) {
+                storage.insert(*slot, Change::Added(value.present_value));
+            }
+        }
+    }
+
+    AccountDiff {
+        info,
+        status,
+        storage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{Bytes, HashMap, KECCAK_EMPTY};
+
+    fn info(nonce: u64, balance: u64) -> AccountInfo {
+        AccountInfo {
+            nonce,
+            balance: U256::from(balance),
+            code_hash: KECCAK_EMPTY,
+            code: None,
+        }
+    }
+
+    type BlockReverts = Vec<(Address, Option<Option<AccountInfo>>, Vec<(U256, U256)>)>;
+
+    fn no_reverts() -> Vec<BlockReverts> {
+        vec![vec![]]
+    }
+
+    fn address(byte: u8) -> Address {
+        Address::with_last_byte(byte)
+    }
+
+    #[test]
+    fn diff_bundles() {
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        let old = BundleState::new(
+            vec![
+                (
+                    address(1),
+                    None,
+                    Some(info(1, 10)),
+                    HashMap::from([
+                        (U256::from(1), (U256::ZERO, U256::from(1))),
+                        (U256::from(2), (U256::ZERO, U256::from(2))),
+                    ]),
+                ),
+                (address(2), None, Some(info(1, 1)), HashMap::new()),
+            ],
+            no_reverts(),
+            vec![(code.hash_slow(), code.clone())],
+        );
+        let new = BundleState::new(
+            vec![
+                (
+                    address(1),
+                    None,
+                    Some(info(2, 10)),
+                    HashMap::from([
+                        (U256::from(1), (U256::ZERO, U256::from(1))),
+                        (U256::from(2), (U256::ZERO, U256::from(3))),
+                        (U256::from(4), (U256::ZERO, U256::from(4))),
+                    ]),
+                ),
+                (address(3), None, Some(info(1, 1)), HashMap::new()),
+            ],
+            no_reverts(),
+            vec![],
+        );
+
+        assert!(old.diff(&old).is_empty());
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.accounts.len(), 3);
+
+        let account1 = &diff.accounts[&address(1)];
+        assert_eq!(
+            account1.info,
+            Some(Change::Modified {
+                old: info(1, 10),
+                new: info(2, 10)
+            })
+        );
+        assert_eq!(account1.status, None);
+        assert_eq!(
+            account1.storage,
+            BTreeMap::from([
+                (
+                    U256::from(2),
+                    Change::Modified {
+                        old: U256::from(2),
+                        new: U256::from(3)
+                    }
+                ),
+                (U256::from(4), Change::Added(U256::from(4))),
+            ])
+        );
+
+        assert_eq!(
+            diff.accounts[&address(2)].info,
+            Some(Change::Removed(info(1, 1)))
+        );
+        assert_eq!(
+            diff.accounts[&address(3)].status,
+            Some(Change::Added(AccountStatus::Changed))
+        );
+        assert_eq!(
+            diff.contracts,
+            BTreeMap::from([(code.hash_slow(), Change::Removed(code))])
+        );
+    }
+
+    #[test]
+    fn extend_conflicts() {
+        let base = BundleState::new(
+            vec![(
+                address(1),
+                None,
+                Some(info(1, 10)),
+                HashMap::from([(U256::from(1), (U256::ZERO, U256::from(1)))]),
+            )],
+            no_reverts(),
+            vec![],
+        );
+
+        // built on top of `base`.
+        let next = BundleState::new(
+            vec![(
+                address(1),
+                Some(info(1, 10)),
+                Some(info(2, 5)),
+                HashMap::from([(U256::from(1), (U256::from(1), U256::from(2)))]),
+            )],
+            no_reverts(),
+            vec![],
+        );
+        assert!(base.conflicts(&next).is_empty());
+        let mut extended = base.clone();
+        assert_eq!(extended.try_extend(next.clone()), Ok(()));
+        let mut expected = base.clone();
+        expected.extend(next);
+        assert_eq!(extended, expected);
+
+        // built on top of a different state.
+        let other = BundleState::new(
+            vec![(
+                address(1),
+                Some(info(1, 20)),
+                Some(info(2, 5)),
+                HashMap::from([(U256::from(1), (U256::from(5), U256::from(2)))]),
+            )],
+            no_reverts(),
+            vec![],
+        );
+        let mut extended = base.clone();
+        assert_eq!(
+            extended.try_extend(other),
+            Err(vec![
+                BundleConflict::AccountInfo {
+                    address: address(1),
+                    present: Some(info(1, 10)),
+                    original: Some(info(1, 20)),
+                },
+                BundleConflict::Storage {
+                    address: address(1),
+                    slot: U256::from(1),
+                    present: U256::from(1),
+                    original: U256::from(5),
+                }
+            ])
+        );
+        assert_eq!(extended, base);
+    }
+}
@@ -0,0 +1,285 @@
+use super::TransitionAccount;
+use core::fmt;
+use dyn_clone::DynClone;
+use revm_interpreter::primitives::{Address, HashSet};
+use std::{boxed::Box, vec::Vec};
+
+/// Position of a transition inside the chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransitionPosition {
+    /// Block number set with [State::set_block_number](super::State::set_block_number).
+    pub block_number: u64,
+    /// Index of the transaction inside the block.
+    ///
+    /// It is `None` for changes that are not done by a transaction,
+    /// as block rewards, withdrawals or balance drains.
+    pub tx_index: Option<u64>,
+}
+
+/// Observer of changes applied to the [State](super::State).
+///
+/// Observers are notified of every [TransitionAccount] that is applied to the state,
+/// in the order they are applied. Transition contains both previous and present
+/// account info and storage values, so there is no need to diff the bundle state.
+pub trait StateObserver: DynClone + Send + Sync {
+    /// Returns true if the observer wants to be notified about changes of `address`.
+    #[inline]
+    fn is_interested(&self, address: &Address) -> bool {
+        let _ = address;
+        true
+    }
+
+    /// Called for each account transition applied to the state.
+    fn on_transition(
+        &mut self,
+        position: TransitionPosition,
+        address: Address,
+        transition: &TransitionAccount,
+    );
+
+    /// Called when transitions of the block are merged into the bundle state
+    /// with [State::merge_transitions](super::State::merge_transitions).
+    #[inline]
+    fn on_merge(&mut self, block_number: u64) {
+        let _ = block_number;
+    }
+}
+
+dyn_clone::clone_trait_object!(StateObserver);
+
+/// Observer that is notified only about changes of the given addresses.
+#[derive(Clone, Debug)]
+pub struct FilteredObserver<O> {
+    addresses: HashSet<Address>,
+    inner: O,
+}
+
+impl<O: StateObserver> FilteredObserver<O> {
+    /// Wrap the observer so it is notified only about the given addresses.
+    pub fn new(addresses: impl IntoIterator<Item = Address>, inner: O) -> Self {
+        Self {
+            addresses: addresses.into_iter().collect(),
+            inner,
+        }
+    }
+
+    /// Returns the wrapped observer.
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+impl<O: StateObserver + Clone> StateObserver for FilteredObserver<O> {
+    fn is_interested(&self, address: &Address) -> bool {
+        self.addresses.contains(address) && self.inner.is_interested(address)
+    }
+
+    fn on_transition(
+        &mut self,
+        position: TransitionPosition,
+        address: Address,
+        transition: &TransitionAccount,
+    ) {
+        self.inner.on_transition(position, address, transition)
+    }
+
+    fn on_merge(&mut self, block_number: u64) {
+        self.inner.on_merge(block_number)
+    }
+}
+
+/// Event sent by [ChannelObserver].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateEvent {
+    /// Account transition was applied.
+    Transition {
+        /// Position of the transition.
+        position: TransitionPosition,
+        /// Address of the changed account.
+        address: Address,
+        /// Applied transition.
+        transition: Box<TransitionAccount>,
+    },
+    /// Transitions of the block were merged into the bundle state.
+    Merged {
+        /// Block number.
+        block_number: u64,
+    },
+}
+
+/// Observer that streams [StateEvent]s to a channel.
+///
+/// Events are dropped silently if the receiving side is disconnected.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct ChannelObserver {
+    sender: std::sync::mpsc::Sender<StateEvent>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelObserver {
+    /// Create new observer that sends events to `sender`.
+    pub fn new(sender: std::sync::mpsc::Sender<StateEvent>) -> Self {
+        Self { sender }
+    }
+
+    /// Create new observer and the receiving side of the channel.
+    pub fn channel() -> (Self, std::sync::mpsc::Receiver<StateEvent>) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        (Self::new(sender), receiver)
+    }
+}
+
+#[cfg(feature = "std")]
+impl StateObserver for ChannelObserver {
+    fn on_transition(
+        &mut self,
+        position: TransitionPosition,
+        address: Address,
+        transition: &TransitionAccount,
+    ) {
+        let _ = self.sender.send(StateEvent::Transition {
+            position,
+            address,
+            transition: Box::new(transition.clone()),
+        });
+    }
+
+    fn on_merge(&mut self, block_number: u64) {
+        let _ = self.sender.send(StateEvent::Merged { block_number });
+    }
+}
+
+/// List of observers registered on the [State](super::State).
+#[derive(Clone, Default)]
+pub struct StateObservers {
+    observers: Vec<Box<dyn StateObserver>>,
+}
+
+impl fmt::Debug for StateObservers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateObservers")
+            .field("len", &self.observers.len())
+            .finish()
+    }
+}
+
+impl StateObservers {
+    /// Add new observer.
+    pub fn push(&mut self, observer: Box<dyn StateObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Returns true if there are no observers.
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Returns number of observers.
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Notify all interested observers about the transitions.
+    pub fn notify_transitions<'a>(
+        &mut self,
+        position: TransitionPosition,
+        transitions: impl IntoIterator<Item = &'a (Address, TransitionAccount)>,
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+        for (address, transition) in transitions {
+            for observer in &mut self.observers {
+                if observer.is_interested(address) {
+                    observer.on_transition(position, *address, transition);
+                }
+            }
+        }
+    }
+
+    /// Notify all observers about the merge of the block.
+    pub fn notify_merge(&mut self, block_number: u64) {
+        for observer in &mut self.observers {
+            observer.on_merge(block_number);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+
+    const ADDRESS1: Address = Address::with_last_byte(1);
+    const ADDRESS2: Address = Address::with_last_byte(2);
+
+    fn position(tx_index: u64) -> TransitionPosition {
+        TransitionPosition {
+            block_number: 1,
+            tx_index: Some(tx_index),
+        }
+    }
+
+    fn addresses(events: &Receiver<StateEvent>) -> Vec<Option<Address>> {
+        events
+            .try_iter()
+            .map(|event| match event {
+                StateEvent::Transition { address, .. } => Some(address),
+                StateEvent::Merged { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn state_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::db::State<crate::db::EmptyDB>>();
+    }
+
+    #[test]
+    fn notify_interested_observers() {
+        let (all, all_events) = ChannelObserver::channel();
+        let (filtered, filtered_events) = ChannelObserver::channel();
+        let mut observers = StateObservers::default();
+        assert!(observers.is_empty());
+        observers.push(Box::new(all));
+        observers.push(Box::new(FilteredObserver::new([ADDRESS2], filtered)));
+        assert_eq!(observers.len(), 2);
+
+        let transitions = [
+            (ADDRESS1, TransitionAccount::default()),
+            (ADDRESS2, TransitionAccount::default()),
+        ];
+        observers.notify_transitions(position(0), &transitions);
+        observers.notify_merge(1);
+
+        assert_eq!(
+            addresses(&all_events),
+            vec![Some(ADDRESS1), Some(ADDRESS2), None]
+        );
+        assert_eq!(addresses(&filtered_events), vec![Some(ADDRESS2), None]);
+    }
+
+    #[test]
+    fn cloned_observers() {
+        let (observer, events) = ChannelObserver::channel();
+        let mut observers = StateObservers::default();
+        observers.push(Box::new(FilteredObserver::new([ADDRESS1], observer)));
+        let mut cloned = observers.clone();
+
+        let transitions = [(ADDRESS1, TransitionAccount::default())];
+        observers.notify_transitions(position(0), &transitions);
+        cloned.notify_transitions(position(1), &transitions);
+
+        let positions = events
+            .try_iter()
+            .map(|event| match event {
+                StateEvent::Transition { position, .. } => position.tx_index,
+                StateEvent::Merged { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![Some(0), Some(1)]);
+    }
+}
@@ -1,6 +1,7 @@
 use super::{
     bundle_state::BundleRetention, cache::CacheState, plain_account::PlainStorage, BundleState,
-    CacheAccount, StateBuilder, TransitionAccount, TransitionState,
+    CacheAccount, StateBuilder, StateObserver, StateObservers, TransitionAccount,
+    TransitionPosition, TransitionState,
 };
 use crate::db::EmptyDB;
 use revm_interpreter::primitives::{
@@ -56,6 +57,12 @@ pub struct State<DB> {
     /// This map can be used to give different values for block hashes if in case
     /// The fork block is different or some blocks are not saved inside database.
     pub block_hashes: BTreeMap<u64, B256>,
+    /// Observers that are notified of every transition applied to the state.
+    pub observers: StateObservers,
+    /// Block number reported to observers.
+    pub block_number: u64,
+    /// Index of the next committed transaction inside the block, reported to observers.
+    pub next_tx_index: u64,
 }
 
 // Have ability to call State::builder without having to specify the type.
@@ -100,9 +107,7 @@ impl<DB: Database> State<DB> {
             ))
         }
         // append transition
-        if let Some(s) = self.transition_state.as_mut() {
-            s.add_transitions(transitions)
-        }
+        self.apply_transition(transitions);
         Ok(())
     }
 
@@ -123,9 +128,7 @@ impl<DB: Database> State<DB> {
             transitions.push((address, transition))
         }
         // append transition
-        if let Some(s) = self.transition_state.as_mut() {
-            s.add_transitions(transitions)
-        }
+        self.apply_transition(transitions);
         Ok(balances)
     }
 
@@ -152,8 +155,34 @@ impl<DB: Database> State<DB> {
             .insert_account_with_storage(address, info, storage)
     }
 
+    /// Set block number that is reported to observers and reset the transaction index.
+    pub fn set_block_number(&mut self, block_number: u64) {
+        self.block_number = block_number;
+        self.next_tx_index = 0;
+    }
+
+    /// Add observer that is notified of every transition applied to the state.
+    pub fn add_observer(&mut self, observer: impl StateObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
     /// Apply evm transitions to transition state.
+    ///
+    /// Transitions are reported to observers without transaction index.
     pub fn apply_transition(&mut self, transitions: Vec<(Address, TransitionAccount)>) {
+        let position = TransitionPosition {
+            block_number: self.block_number,
+            tx_index: None,
+        };
+        self.apply_transition_at(position, transitions);
+    }
+
+    fn apply_transition_at(
+        &mut self,
+        position: TransitionPosition,
+        transitions: Vec<(Address, TransitionAccount)>,
+    ) {
+        self.observers.notify_transitions(position, &transitions);
         // add transition to transition state.
         if let Some(s) = self.transition_state.as_mut() {
             s.add_transitions(transitions)
@@ -168,6 +197,7 @@ impl<DB: Database> State<DB> {
         if let Some(transition_state) = self.transition_state.as_mut().map(TransitionState::take) {
             self.bundle_state
                 .apply_transitions_and_create_reverts(transition_state, retention);
+            self.observers.notify_merge(self.block_number);
         }
     }
 
@@ -293,7 +323,12 @@ impl<DB: Database> Database for State<DB> {
 impl<DB: Database> DatabaseCommit for State<DB> {
     fn commit(&mut self, evm_state: HashMap<Address, Account>) {
         let transitions = self.cache.apply_evm_state(evm_state);
-        self.apply_transition(transitions);
+        let position = TransitionPosition {
+            block_number: self.block_number,
+            tx_index: Some(self.next_tx_index),
+        };
+        self.next_tx_index += 1;
+        self.apply_transition_at(position, transitions);
     }
 }
 
@@ -789,4 +824,72 @@ mod tests {
             )])])
         )
     }
+
+    #[test]
+    fn observers_receive_transitions() {
+        use crate::db::states::{ChannelObserver, FilteredObserver, StateEvent};
+        use revm_interpreter::primitives::AccountStatus as EvmAccountStatus;
+
+        let address1 = Address::with_last_byte(1);
+        let address2 = Address::with_last_byte(2);
+        let (all, all_events) = ChannelObserver::channel();
+        let (filtered, filtered_events) = ChannelObserver::channel();
+        let mut state = State::builder()
+            .with_bundle_update()
+            .with_observer(all)
+            .with_observer(FilteredObserver::new([address2], filtered))
+            .build();
+        state.set_block_number(10);
+
+        let touched = |balance: u64| Account {
+            info: AccountInfo::from_balance(U256::from(balance)),
+            storage: HashMap::default(),
+            status: EvmAccountStatus::Touched,
+        };
+        for (address, balance) in [(address1, 1), (address2, 2)] {
+            state.basic(address).unwrap();
+            state.commit(HashMap::from([(address, touched(balance))]));
+        }
+        state.increment_balances([(address2, 5)]).unwrap();
+        state.merge_transitions(BundleRetention::Reverts);
+
+        let positions = all_events
+            .try_iter()
+            .map(|event| match event {
+                StateEvent::Transition {
+                    position, address, ..
+                } => (Some(position.tx_index), address),
+                StateEvent::Merged { block_number } => {
+                    assert_eq!(block_number, 10);
+                    (None, Address::ZERO)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                (Some(Some(0)), address1),
+                (Some(Some(1)), address2),
+                (Some(None), address2),
+                (None, Address::ZERO),
+            ]
+        );
+
+        let filtered = filtered_events.try_iter().collect::<Vec<_>>();
+        assert_eq!(filtered.len(), 3);
+        match &filtered[1] {
+            StateEvent::Transition {
+                position,
+                address,
+                transition,
+            } => {
+                assert_eq!(position.block_number, 10);
+                assert_eq!(position.tx_index, None);
+                assert_eq!(*address, address2);
+                assert_eq!(transition.previous_balance(), U256::from(2));
+                assert_eq!(transition.current_balance(), U256::from(7));
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
 }
@@ -1,13 +1,16 @@
-use super::{cache::CacheState, state::DBBox, BundleState, State, TransitionState};
+use super::{
+    cache::CacheState, state::DBBox, BundleState, State, StateObserver, StateObservers,
+    TransitionState,
+};
 use crate::db::EmptyDB;
 use revm_interpreter::primitives::{
     db::{Database, DatabaseRef, WrapDatabaseRef},
     B256,
 };
-use std::collections::BTreeMap;
+use std::{boxed::Box, collections::BTreeMap};
 
 /// Allows building of State and initializing it with different options.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct StateBuilder<DB> {
     /// Database that we use to fetch data from.
     database: DB,
@@ -28,8 +31,26 @@ pub struct StateBuilder<DB> {
     with_background_transition_merge: bool,
     /// If we want to set different block hashes
     with_block_hashes: BTreeMap<u64, B256>,
+    /// Observers notified about applied transitions.
+    with_observers: StateObservers,
+}
+
+/// Observers are trait objects that can't be compared, builders are equal if all other options
+/// are.
+impl<DB: PartialEq> PartialEq for StateBuilder<DB> {
+    fn eq(&self, other: &Self) -> bool {
+        self.database == other.database
+            && self.with_state_clear == other.with_state_clear
+            && self.with_bundle_prestate == other.with_bundle_prestate
+            && self.with_cache_prestate == other.with_cache_prestate
+            && self.with_bundle_update == other.with_bundle_update
+            && self.with_background_transition_merge == other.with_background_transition_merge
+            && self.with_block_hashes == other.with_block_hashes
+    }
 }
 
+impl<DB: Eq> Eq for StateBuilder<DB> {}
+
 impl StateBuilder<EmptyDB> {
     /// Create a new builder with an empty database.
     ///
@@ -57,6 +78,7 @@ impl<DB: Database> StateBuilder<DB> {
             with_bundle_update: false,
             with_background_transition_merge: false,
             with_block_hashes: BTreeMap::new(),
+            with_observers: StateObservers::default(),
         }
     }
 
@@ -72,6 +94,7 @@ impl<DB: Database> StateBuilder<DB> {
             with_bundle_update: self.with_bundle_update,
             with_background_transition_merge: self.with_background_transition_merge,
             with_block_hashes: self.with_block_hashes,
+            with_observers: self.with_observers,
         }
     }
 
@@ -150,6 +173,14 @@ impl<DB: Database> StateBuilder<DB> {
         }
     }
 
+    /// Add observer that is notified of every transition applied to the state.
+    ///
+    /// Use [FilteredObserver](super::FilteredObserver) to observe only some addresses.
+    pub fn with_observer(mut self, observer: impl StateObserver + 'static) -> Self {
+        self.with_observers.push(Box::new(observer));
+        self
+    }
+
     pub fn build(mut self) -> State<DB> {
         let use_preloaded_bundle = if self.with_cache_prestate.is_some() {
             self.with_bundle_prestate = None;
@@ -166,6 +197,9 @@ impl<DB: Database> StateBuilder<DB> {
             bundle_state: self.with_bundle_prestate.unwrap_or_default(),
             use_preloaded_bundle,
             block_hashes: self.with_block_hashes,
+            observers: self.with_observers,
+            block_number: 0,
+            next_tx_index: 0,
         }
     }
 }
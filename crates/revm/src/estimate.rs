@@ -0,0 +1,476 @@
+use crate::{
+    primitives::{
+        Account, AccountStatus, Bytes, EVMError, EvmState, EvmStorageSlot, ExecutionResult,
+        HaltReason, InvalidTransaction, ResultAndState, RevertReason,
+    },
+    Database, Evm,
+};
+use core::fmt;
+use std::string::String;
+
+/// Gas stipend given to the callee of a call that transfers value.
+const CALL_STIPEND: u64 = 2300;
+
+/// Result of [Evm::estimate_gas].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasEstimate {
+    /// Lowest gas limit with which the transaction succeeds.
+    pub gas_limit: u64,
+    /// Gas used by the transaction when executed with the highest allowed gas limit.
+    pub gas_used: u64,
+    /// Number of times the transaction was executed.
+    pub iterations: u64,
+}
+
+/// Errors of [Evm::estimate_gas].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EstimateGasError<DBError> {
+    /// Transaction is invalid or database returned an error.
+    Evm(EVMError<DBError>),
+    /// Caller can't pay for the transferred value.
+    InsufficientFunds,
+    /// Transaction does not succeed with the highest allowed gas limit.
+    ///
+    /// Allowance is capped by the transaction and block gas limits and by the caller's balance.
+    GasRequiredExceedsAllowance {
+        /// Highest allowed gas limit.
+        allowance: u64,
+    },
+    /// Transaction reverted with the highest allowed gas limit.
+    Reverted {
+        /// Revert output.
+        output: Bytes,
+    },
+    /// Transaction halted with the highest allowed gas limit.
+    Halted {
+        /// Halt reason.
+        reason: HaltReason,
+    },
+}
+
+impl<DBError> EstimateGasError<DBError> {
+    /// Returns the revert reason if the transaction reverted with `Error(string)`.
+    pub fn revert_reason(&self) -> Option<String> {
+        match self {
+            Self::Reverted { output } => match RevertReason::decode(output) {
+                RevertReason::Error(message) => Some(message),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<DBError> From<EVMError<DBError>> for EstimateGasError<DBError> {
+    fn from(value: EVMError<DBError>) -> Self {
+        Self::Evm(value)
+    }
+}
+
+impl<DBError: fmt::Display> fmt::Display for EstimateGasError<DBError> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Evm(e) => e.fmt(f),
+            Self::InsufficientFunds => f.write_str("insufficient funds for transfer"),
+            Self::GasRequiredExceedsAllowance { allowance } => {
+                write!(f, "gas required exceeds allowance ({allowance})")
+            }
+            Self::Reverted { output } => match RevertReason::decode(output) {
+                RevertReason::Error(reason) => write!(f, "execution reverted: {reason}"),
+                _ => write!(f, "execution reverted: {output}"),
+            },
+            Self::Halted { reason } => write!(f, "execution halted: {reason:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<DBError: fmt::Debug + fmt::Display> std::error::Error for EstimateGasError<DBError> {}
+
+impl<EXT, DB: Database> Evm<'_, EXT, DB> {
+    /// Estimate the lowest gas limit with which the transaction succeeds.
+    ///
+    /// Upper bound is the transaction gas limit, capped by the block gas limit and by the
+    /// gas the caller can pay for. Transaction is executed with the upper bound first and
+    /// the estimate is then found with a binary search. Gas that is not used because of
+    /// the 63/64 rule is accounted for as every candidate is executed.
+    ///
+    /// Accounts and storage loaded by the first execution are reused by the following
+    /// ones, so the database is not queried for them again. Nothing is committed and
+    /// the transaction environment is left unchanged.
+    pub fn estimate_gas(&mut self) -> Result<GasEstimate, EstimateGasError<DB::Error>> {
+        let gas_limit = self.tx().gas_limit;
+        let estimate = self.estimate_gas_inner();
+        self.tx_mut().gas_limit = gas_limit;
+        self.context.evm.journaled_state.state.clear();
+        estimate
+    }
+
+    fn estimate_gas_inner(&mut self) -> Result<GasEstimate, EstimateGasError<DB::Error>> {
+        let mut hi = self
+            .tx()
+            .gas_limit
+            .min(self.block().gas_limit.saturating_to());
+        if !self.tx().gas_price.is_zero() {
+            let caller = self.tx().caller;
+            let balance = self
+                .db_mut()
+                .basic(caller)
+                .map_err(EVMError::Database)?
+                .map(|info| info.balance)
+                .unwrap_or_default();
+            let available = balance
+                .checked_sub(self.tx().value)
+                .ok_or(EstimateGasError::InsufficientFunds)?;
+            hi = hi.min((available / self.tx().gas_price).saturating_to());
+        }
+
+        self.tx_mut().gas_limit = hi;
+        let ResultAndState { result, state, .. } = match self.transact() {
+            Ok(result) => result,
+            Err(EVMError::Transaction(InvalidTransaction::CallGasCostMoreThanGasLimit)) => {
+                return Err(EstimateGasError::GasRequiredExceedsAllowance { allowance: hi })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (gas_used, gas_refunded) = match result {
+            ExecutionResult::Success {
+                gas_used,
+                gas_refunded,
+                ..
+            } => (gas_used, gas_refunded),
+            ExecutionResult::Revert { output, .. } => {
+                return Err(EstimateGasError::Reverted { output })
+            }
+            ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(_),
+                ..
+            } => return Err(EstimateGasError::GasRequiredExceedsAllowance { allowance: hi }),
+            ExecutionResult::Halt { reason, .. } => {
+                return Err(EstimateGasError::Halted { reason })
+            }
+        };
+
+        let prestate = self.prestate(state)?;
+        let mut estimate = GasEstimate {
+            gas_limit: hi,
+            gas_used,
+            iterations: 1,
+        };
+
+        // Transaction needs at least the gas it used, most often it needs just a bit more.
+        let mut lo = gas_used.saturating_sub(1);
+        let optimistic = optimistic_gas_limit(gas_used, gas_refunded, hi);
+        if optimistic < hi {
+            estimate.iterations += 1;
+            if self.succeeds_with(optimistic, &prestate)? {
+                hi = optimistic;
+            } else {
+                lo = optimistic;
+            }
+        }
+
+        while lo + 1 < hi {
+            // Most transactions need little more than `lo`, so do not go too far above it.
+            let mid = (lo + (hi - lo) / 2).min(lo.saturating_mul(2));
+            estimate.iterations += 1;
+            if self.succeeds_with(mid, &prestate)? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        estimate.gas_limit = hi;
+        Ok(estimate)
+    }
+
+    /// Execute the transaction with `gas_limit` on top of the `prestate`.
+    fn succeeds_with(
+        &mut self,
+        gas_limit: u64,
+        prestate: &EvmState,
+    ) -> Result<bool, EstimateGasError<DB::Error>> {
+        self.tx_mut().gas_limit = gas_limit;
+        self.context.evm.journaled_state.state = prestate.clone();
+        match self.transact() {
+            Ok(ResultAndState { result, .. }) => Ok(result.is_success()),
+            Err(EVMError::Transaction(InvalidTransaction::CallGasCostMoreThanGasLimit)) => {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Build the state as it was before the transaction from the state it ended with.
+    ///
+    /// Storage original values are kept by the state; only accounts that were changed
+    /// are loaded from the database again. Accounts and slots are marked cold unless they
+    /// are warm at the start of the transaction.
+    fn prestate(&mut self, state: EvmState) -> Result<EvmState, EVMError<DB::Error>> {
+        let mut prestate = EvmState::with_capacity(state.len());
+        for (address, account) in state {
+            let mut status = AccountStatus::Loaded;
+            let info = if account.is_touched() {
+                self.db_mut()
+                    .basic(address)
+                    .map_err(EVMError::Database)?
+                    .map(|mut info| {
+                        // keep the code that was loaded, if it was not changed.
+                        if info.code.is_none() && info.code_hash == account.info.code_hash {
+                            info.code = account.info.code;
+                        }
+                        info
+                    })
+            } else if account.is_loaded_as_not_existing() {
+                None
+            } else {
+                Some(account.info)
+            };
+            let info = match info {
+                Some(info) => info,
+                None => {
+                    status |= AccountStatus::LoadedAsNotExisting;
+                    Default::default()
+                }
+            };
+
+            let access_list = self
+                .tx()
+                .access_list
+                .iter()
+                .find(|item| item.address == address);
+            let is_warm = access_list.is_some()
+                || self
+                    .context
+                    .evm
+                    .journaled_state
+                    .warm_preloaded_addresses
+                    .contains(&address);
+            if !is_warm {
+                status |= AccountStatus::Cold;
+            }
+            let storage = account
+                .storage
+                .into_iter()
+                .map(|(key, slot)| {
+                    let mut slot = EvmStorageSlot::new(slot.original_value);
+                    let is_warm =
+                        access_list.is_some_and(|item| item.storage_keys.contains(&key.into()));
+                    if !is_warm {
+                        slot.mark_cold();
+                    }
+                    (key, slot)
+                })
+                .collect();
+            prestate.insert(
+                address,
+                Account {
+                    info,
+                    storage,
+                    status,
+                },
+            );
+        }
+        Ok(prestate)
+    }
+}
+
+/// Returns the gas limit with which most transactions succeed: the gas used before the refund
+/// with the stipend of a call, and the 1/64 of the gas that calls retain, at most `cap`.
+fn optimistic_gas_limit(gas_used: u64, gas_refunded: u64, cap: u64) -> u64 {
+    let limit =
+        (u128::from(gas_used) + u128::from(gas_refunded) + u128::from(CALL_STIPEND)) * 64 / 63;
+    limit.min(u128::from(cap)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        primitives::{AccountInfo, Address, Bytecode, TxKind, B256, U256},
+        DatabaseRef,
+    };
+    use std::vec::Vec;
+
+    /// Database that counts the queries.
+    #[derive(Debug, Default)]
+    struct CountingDB {
+        db: CacheDB<EmptyDB>,
+        basic: usize,
+        storage: usize,
+    }
+
+    impl Database for CountingDB {
+        type Error = core::convert::Infallible;
+
+        fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.basic += 1;
+            self.db.basic_ref(address)
+        }
+
+        fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+            self.db.code_by_hash_ref(code_hash)
+        }
+
+        fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+            self.storage += 1;
+            self.db.storage_ref(address, index)
+        }
+
+        fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+            self.db.block_hash_ref(number)
+        }
+    }
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const CONTRACT: Address = Address::repeat_byte(0xc0);
+    const CALLEE: Address = Address::repeat_byte(0xce);
+
+    fn evm(contracts: &[(Address, Vec<u8>)]) -> Evm<'static, (), CountingDB> {
+        let mut db = CountingDB::default();
+        db.db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balance: U256::from(1_000_000_000_000_000_000u64),
+                ..Default::default()
+            },
+        );
+        for (address, code) in contracts {
+            db.db.insert_account_info(
+                *address,
+                AccountInfo::from_bytecode(Bytecode::new_raw(code.clone().into())),
+            );
+        }
+        Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(CONTRACT);
+            })
+            .build()
+    }
+
+    /// Executes the transaction with the given gas limit.
+    fn succeeds(evm: &mut Evm<'_, (), CountingDB>, gas_limit: u64) -> bool {
+        let previous = core::mem::replace(&mut evm.tx_mut().gas_limit, gas_limit);
+        let success = evm.transact().unwrap().result.is_success();
+        evm.tx_mut().gas_limit = previous;
+        success
+    }
+
+    /// Code that calls `CALLEE` with all available gas and reverts if the call failed.
+    fn call_and_check() -> Vec<u8> {
+        let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x73];
+        code.extend_from_slice(CALLEE.as_slice());
+        // GAS CALL ISZERO PUSH1 0x21 JUMPI STOP JUMPDEST PUSH0 PUSH0 REVERT
+        code.extend_from_slice(&[
+            0x5a, 0xf1, 0x15, 0x60, 0x21, 0x57, 0x00, 0x5b, 0x5f, 0x5f, 0xfd,
+        ]);
+        code
+    }
+
+    /// Code that reverts with `data`.
+    fn revert_with(data: &[u8]) -> Vec<u8> {
+        let mut code = Vec::new();
+        for (i, chunk) in data.chunks(32).enumerate() {
+            let mut word = [0u8; 32];
+            word[..chunk.len()].copy_from_slice(chunk);
+            code.push(0x7f);
+            code.extend_from_slice(&word);
+            code.extend_from_slice(&[0x60, (i * 32) as u8, 0x52]);
+        }
+        code.extend_from_slice(&[0x60, data.len() as u8, 0x5f, 0xfd]);
+        code
+    }
+
+    #[test]
+    fn transfer() {
+        let mut evm = evm(&[]);
+        evm.tx_mut().transact_to = TxKind::Call(CALLEE);
+        evm.tx_mut().value = U256::from(1);
+        let estimate = evm.estimate_gas().unwrap();
+        assert_eq!(estimate.gas_limit, 21_000);
+        assert_eq!(estimate.gas_used, 21_000);
+        assert_eq!(evm.tx().gas_limit, u64::MAX);
+    }
+
+    #[test]
+    fn all_but_one_64th() {
+        // PUSH1 1 PUSH0 SSTORE STOP
+        let mut evm = evm(&[
+            (CONTRACT, call_and_check()),
+            (CALLEE, vec![0x60, 0x01, 0x5f, 0x55, 0x00]),
+        ]);
+        let estimate = evm.estimate_gas().unwrap();
+
+        // gas used is not enough, as only 63/64 of the gas is passed to the callee.
+        assert!(estimate.gas_limit > estimate.gas_used);
+        assert!(!succeeds(&mut evm, estimate.gas_used));
+        assert!(succeeds(&mut evm, estimate.gas_limit));
+        assert!(!succeeds(&mut evm, estimate.gas_limit - 1));
+    }
+
+    #[test]
+    fn state_is_reused() {
+        let mut evm = evm(&[
+            (CONTRACT, call_and_check()),
+            (CALLEE, vec![0x60, 0x01, 0x5f, 0x55, 0x00]),
+        ]);
+        let estimate = evm.estimate_gas().unwrap();
+        assert!(estimate.iterations > 2);
+
+        // caller, contract, callee and coinbase are loaded by the first execution
+        // and once more to restore them, as all of them are touched.
+        let db = evm.db();
+        assert_eq!(db.basic, 2 * 4);
+        assert_eq!(db.storage, 1);
+
+        let estimate_again = evm.estimate_gas().unwrap();
+        assert_eq!(estimate, estimate_again);
+    }
+
+    #[test]
+    fn revert_reason() {
+        // Error("nope")
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend_from_slice(&U256::from(32).to_be_bytes::<32>());
+        data.extend_from_slice(&U256::from(4).to_be_bytes::<32>());
+        let mut reason = [0u8; 32];
+        reason[..4].copy_from_slice(b"nope");
+        data.extend_from_slice(&reason);
+
+        let mut evm = evm(&[(CONTRACT, revert_with(&data))]);
+        let error = evm.estimate_gas().unwrap_err();
+        assert_eq!(
+            error,
+            EstimateGasError::Reverted {
+                output: data.into()
+            }
+        );
+        assert_eq!(error.revert_reason().as_deref(), Some("nope"));
+        assert_eq!(error.to_string(), "execution reverted: nope");
+    }
+
+    #[test]
+    fn balance_cap() {
+        // PUSH1 1 PUSH0 SSTORE STOP
+        let mut evm = evm(&[(CONTRACT, vec![0x60, 0x01, 0x5f, 0x55, 0x00])]);
+        // caller can pay for 30_000 gas only.
+        evm.tx_mut().gas_price = U256::from(1_000_000_000_000_000_000u64 / 30_000);
+        assert_eq!(
+            evm.estimate_gas(),
+            Err(EstimateGasError::GasRequiredExceedsAllowance { allowance: 30_000 })
+        );
+
+        evm.tx_mut().value = U256::from(2_000_000_000_000_000_000u64);
+        assert_eq!(evm.estimate_gas(), Err(EstimateGasError::InsufficientFunds));
+    }
+
+    #[test]
+    fn optimistic_limit() {
+        assert_eq!(optimistic_gas_limit(61_000, 0, 100_000), 64_304);
+        assert_eq!(optimistic_gas_limit(61_000, 0, 50_000), 50_000);
+        assert_eq!(optimistic_gas_limit(u64::MAX, u64::MAX, u64::MAX), u64::MAX);
+    }
+}
@@ -14,6 +14,14 @@ use crate::{
 use core::fmt;
 use std::{boxed::Box, vec::Vec};
 
+#[cfg(feature = "execution_limits")]
+use crate::interpreter::ExecutionLimits;
+#[cfg(feature = "halt_diagnostics")]
+use crate::{
+    interpreter::{return_ok, InstructionResult, Interpreter, InterpreterResult},
+    primitives::{Bytes, HaltDiagnostics, B256},
+};
+
 /// EVM call stack limit.
 pub const CALL_STACK_LIMIT: u64 = 1024;
 
@@ -43,7 +51,7 @@ where
 impl<EXT, DB: Database + DatabaseCommit> Evm<'_, EXT, DB> {
     /// Commit the changes to the database.
     pub fn transact_commit(&mut self) -> Result<ExecutionResult, EVMError<DB::Error>> {
-        let ResultAndState { result, state } = self.transact()?;
+        let ResultAndState { result, state, .. } = self.transact()?;
         self.context.evm.db.commit(state);
         Ok(result)
     }
@@ -86,20 +94,40 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
 
         shared_memory.new_context();
 
+        // Instruction and time budget shared by all frames.
+        #[cfg(feature = "execution_limits")]
+        let mut execution_limits = ExecutionLimits::new(&self.context.evm.env.cfg);
+
+        // Innermost failed frame with its revert data.
+        #[cfg(feature = "halt_diagnostics")]
+        let mut failure: Option<(HaltDiagnostics, Bytes)> = None;
+
         // Peek the last stack frame.
         let mut stack_frame = call_stack.last_mut().unwrap();
 
         loop {
+            #[cfg(feature = "execution_limits")]
+            {
+                stack_frame.interpreter_mut().execution_limits = execution_limits;
+            }
+
             // Execute the frame.
             let next_action =
                 self.handler
                     .execute_frame(stack_frame, &mut shared_memory, &mut self.context)?;
 
+            #[cfg(feature = "execution_limits")]
+            {
+                execution_limits = stack_frame.interpreter().execution_limits;
+            }
+
             // Take error and break the loop, if any.
             // This error can be set in the Interpreter when it interacts with the context.
             self.context.evm.take_error()?;
 
             let exec = &mut self.handler.execution;
+            #[cfg(feature = "halt_diagnostics")]
+            let returned = matches!(next_action, InterpreterAction::Return { .. });
             let frame_or_result = match next_action {
                 InterpreterAction::Call { inputs } => exec.call(&mut self.context, inputs)?,
                 InterpreterAction::Create { inputs } => exec.create(&mut self.context, inputs)?,
@@ -107,6 +135,12 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
                     exec.eofcreate(&mut self.context, inputs)?
                 }
                 InterpreterAction::Return { result } => {
+                    #[cfg(feature = "halt_diagnostics")]
+                    let (mut location, forwarded) = (
+                        halt_location(stack_frame.interpreter(), &result),
+                        stack_frame.interpreter().return_data_copied,
+                    );
+
                     // free memory context.
                     shared_memory.free_context();
 
@@ -116,7 +150,7 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
                         .expect("We just returned from Interpreter frame");
 
                     let ctx = &mut self.context;
-                    FrameOrResult::Result(match returned_frame {
+                    let frame_result = match returned_frame {
                         Frame::Call(frame) => {
                             // return_call
                             FrameResult::Call(exec.call_return(ctx, frame, result)?)
@@ -129,10 +163,29 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
                             // return_eofcreate
                             FrameResult::EOFCreate(exec.eofcreate_return(ctx, frame, result)?)
                         }
-                    })
+                    };
+
+                    #[cfg(feature = "halt_diagnostics")]
+                    {
+                        location.depth = call_stack.len();
+                        let result = frame_result.interpreter_result();
+                        record_failure(&mut failure, location, result, forwarded);
+                    }
+
+                    FrameOrResult::Result(frame_result)
                 }
                 InterpreterAction::None => unreachable!("InterpreterAction::None is not expected"),
             };
+            // A call or creation that ends without running code is reported at the calling
+            // instruction.
+            #[cfg(feature = "halt_diagnostics")]
+            if let (false, FrameOrResult::Result(frame_result)) = (returned, &frame_or_result) {
+                let result = frame_result.interpreter_result();
+                let caller = call_stack.last().expect("the caller frame is on the stack");
+                let mut location = halt_location(caller.interpreter(), result);
+                location.depth = call_stack.len() - 1;
+                record_failure(&mut failure, location, result, false);
+            }
             // handle result
             match frame_or_result {
                 FrameOrResult::Frame(frame) => {
@@ -142,6 +195,11 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
                 }
                 FrameOrResult::Result(result) => {
                     let Some(top_frame) = call_stack.last_mut() else {
+                        #[cfg(feature = "halt_diagnostics")]
+                        {
+                            self.context.evm.inner.halt_diagnostics =
+                                failure.map(|(location, _)| location);
+                        }
                         // Break the loop if there are no more frames.
                         return Ok(result);
                     };
@@ -168,6 +226,53 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
     }
 }
 
+/// Returns the location of the instruction that ended the frame, depth is set by the caller.
+#[cfg(feature = "halt_diagnostics")]
+fn halt_location(interp: &Interpreter, result: &InterpreterResult) -> HaltDiagnostics {
+    // The instruction pointer is advanced before the instruction is executed, except when the
+    // execution limits stop the frame before the next instruction.
+    let pc = match result.result {
+        InstructionResult::InstructionLimitReached | InstructionResult::DeadlineExceeded => {
+            interp.program_counter()
+        }
+        _ => interp.program_counter().saturating_sub(1),
+    };
+    // `CREATE` frames have a zero init code hash.
+    let code_hash = match interp.contract.hash {
+        Some(hash) if hash != B256::ZERO => hash,
+        _ => interp.contract.bytecode.hash_slow(),
+    };
+    HaltDiagnostics {
+        address: interp.contract.target_address,
+        code_hash,
+        pc,
+        opcode: interp.bytecode.get(pc).copied().unwrap_or_default(),
+        depth: 0,
+    }
+}
+
+/// Records the failure of a frame, or clears it if the frame succeeded.
+///
+/// A revert of a frame that copied the return data of the last failure and reverts with the
+/// same data is attributed to that failure, as it was bubbled up from a nested call.
+#[cfg(feature = "halt_diagnostics")]
+fn record_failure(
+    failure: &mut Option<(HaltDiagnostics, Bytes)>,
+    location: HaltDiagnostics,
+    result: &InterpreterResult,
+    forwarded: bool,
+) {
+    match result.result {
+        return_ok!() => *failure = None,
+        InstructionResult::Revert
+            if forwarded
+                && failure
+                    .as_ref()
+                    .is_some_and(|(_, output)| *output == result.output) => {}
+        _ => *failure = Some((location, result.output.clone())),
+    }
+}
+
 impl<EXT, DB: Database> Evm<'_, EXT, DB> {
     /// Returns specification (hardfork) that the EVM is instanced with.
     ///
@@ -326,6 +431,10 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
     fn transact_preverified_inner(&mut self, initial_gas_spend: u64) -> EVMResult<DB::Error> {
         let spec_id = self.spec_id();
         let ctx = &mut self.context;
+        #[cfg(feature = "halt_diagnostics")]
+        {
+            ctx.evm.inner.halt_diagnostics = None;
+        }
         let pre_exec = self.handler.pre_execution();
 
         // load access list and beneficiary if needed.
@@ -388,3 +497,142 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         post_exec.output(ctx, result)
     }
 }
+
+#[cfg(all(test, feature = "execution_limits"))]
+mod tests {
+    use crate::{
+        primitives::{Bytes, ExecutionResult, HaltReason},
+        test_fixtures::{builder, Code, CHILD, TARGET},
+    };
+
+    /// `JUMPDEST PUSH1 0 JUMP`, loops until it runs out of gas.
+    const INFINITE_LOOP: &[u8] = &[0x5b, 0x60, 0x00, 0x56];
+
+    fn run(code: Bytes, instruction_limit: Option<u64>) -> ExecutionResult {
+        let mut evm = builder([(TARGET, code), (CHILD, INFINITE_LOOP.into())])
+            .modify_tx_env(|tx| tx.gas_limit = 30_000_000)
+            .modify_cfg_env(|cfg| cfg.instruction_limit = instruction_limit)
+            .build();
+        evm.transact().unwrap().result
+    }
+
+    #[test]
+    fn instruction_limit() {
+        // PUSH1 1 PUSH1 2 ADD STOP
+        let code = Code::new().push(1).push(2).op(0x01).op(0x00).build();
+        assert!(run(code.clone(), Some(4)).is_success());
+        assert_eq!(
+            run(code, Some(3)),
+            ExecutionResult::Halt {
+                reason: HaltReason::InstructionLimitReached,
+                gas_used: 30_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn instruction_limit_is_shared_by_frames() {
+        let code = Code::new().call(CHILD, 0).op(0x00).build();
+        let ExecutionResult::Halt { reason, .. } = run(code, Some(10_000)) else {
+            panic!("expected halt");
+        };
+        assert_eq!(reason, HaltReason::InstructionLimitReached);
+    }
+
+    #[test]
+    fn deadline() {
+        let mut evm = builder([(TARGET, INFINITE_LOOP.into())])
+            .modify_tx_env(|tx| tx.gas_limit = u64::MAX / 2)
+            .modify_cfg_env(|cfg| {
+                cfg.execution_deadline =
+                    Some(std::time::Instant::now() + std::time::Duration::from_millis(10))
+            })
+            .build();
+
+        let ExecutionResult::Halt { reason, .. } = evm.transact().unwrap().result else {
+            panic!("expected halt");
+        };
+        assert_eq!(reason, HaltReason::DeadlineExceeded);
+    }
+}
+
+#[cfg(all(test, feature = "halt_diagnostics"))]
+mod halt_diagnostics_tests {
+    use crate::{
+        primitives::{keccak256, Address, Bytes, HaltDiagnostics, ResultAndState},
+        test_fixtures::{builder, Code, CHILD, REVERT, TARGET},
+    };
+
+    /// `PUSH1 3 JUMP`, jumps to a destination that is not a `JUMPDEST`.
+    const INVALID_JUMP: &[u8] = &[0x60, 0x03, 0x56];
+
+    fn run(code: impl Into<Bytes>, child: &'static [u8]) -> ResultAndState {
+        let mut evm = builder([(TARGET, code.into()), (CHILD, child.into())]).build();
+        evm.transact().unwrap()
+    }
+
+    fn location(address: Address, code: &[u8], pc: usize, depth: usize) -> HaltDiagnostics {
+        HaltDiagnostics {
+            address,
+            code_hash: keccak256(code),
+            pc,
+            opcode: code[pc],
+            depth,
+        }
+    }
+
+    #[test]
+    fn halt() {
+        let result = run(INVALID_JUMP, REVERT);
+        assert!(result.result.is_halt());
+        assert_eq!(
+            result.diagnostics,
+            Some(location(TARGET, INVALID_JUMP, 2, 0))
+        );
+    }
+
+    #[test]
+    fn bubbled_failure() {
+        let code = Code::new().call(CHILD, 0).forward_revert().op(0x00);
+        let result = run(code.clone(), REVERT);
+        assert!(!result.result.is_success());
+        assert_eq!(result.diagnostics, Some(location(CHILD, REVERT, 4, 1)));
+
+        // The caller reverts with the empty output of the halted child.
+        let diagnostics = run(code, INVALID_JUMP).diagnostics.unwrap();
+        assert_eq!((diagnostics.address, diagnostics.opcode), (CHILD, 0x56));
+    }
+
+    #[test]
+    fn handled_failure() {
+        let result = run(Code::new().call(CHILD, 0).op(0x00), REVERT);
+        assert!(result.result.is_success());
+        assert_eq!(result.diagnostics, None);
+
+        // The caller ignores the failure and reverts with its own empty data.
+        let code = Code::new().call(CHILD, 0).op(0x50).revert().build();
+        let result = run(code.clone(), REVERT);
+        assert!(!result.result.is_success());
+        assert_eq!(
+            result.diagnostics,
+            Some(location(TARGET, &code, code.len() - 1, 0))
+        );
+    }
+
+    #[test]
+    fn failure_without_frame() {
+        // CALL(1, SHA256, 0, 0, 0, 0, 0) runs out of gas in the precompile.
+        let call = Code::new()
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(2)
+            .push(1);
+        let code = call.op(0xf1).forward_revert().build();
+        let result = run(code.clone(), REVERT);
+        assert!(!result.result.is_success());
+        assert_eq!(result.diagnostics, Some(location(TARGET, &code, 14, 0)));
+    }
+}
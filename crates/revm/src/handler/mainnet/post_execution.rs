@@ -132,5 +132,10 @@ pub fn output<EXT, DB: Database>(
         }
     };
 
-    Ok(ResultAndState { result, state })
+    Ok(ResultAndState {
+        result,
+        state,
+        #[cfg(feature = "halt_diagnostics")]
+        diagnostics: context.evm.inner.halt_diagnostics.take(),
+    })
 }
@@ -1,10 +1,26 @@
+mod access_list;
+mod call_tracer;
+mod cheatcodes;
+mod console_log;
+mod coverage;
 #[cfg(feature = "std")]
 mod customprinter;
 #[cfg(all(feature = "std", feature = "serde-json"))]
 mod eip3155;
 mod gas;
+mod gas_profiler;
 mod handler_register;
+mod log_stream;
+mod multi;
 mod noop;
+mod parity_tracer;
+mod prestate_tracer;
+mod revert_tracer;
+mod security;
+mod struct_logger;
+mod token_flow;
+mod transfer;
+mod user_op_validation;
 
 pub use handler_register::{inspector_handle_register, GetInspector};
 
@@ -19,12 +35,50 @@ use auto_impl::auto_impl;
 
 /// [Inspector] implementations.
 pub mod inspectors {
+    pub use super::access_list::AccessListInspector;
+    pub use super::call_tracer::{CallFrame, CallKind, CallLog, CallTracer, CallTracerConfig};
+    pub use super::cheatcodes::{
+        cheatcodes_handle_register, Cheatcodes, StorageAccesses, HEVM_ADDRESS,
+    };
+    pub use super::console_log::{ConsoleLogInspector, ConsoleMessage, CONSOLE_ADDRESS};
+    pub use super::coverage::{
+        parse_source_map, ArtifactBytecode, BranchHits, CodeCoverage, ContractArtifact,
+        CoverageInspector, CoverageReport, FileCoverage, ImmutableReference, Jump, SourceElement,
+        SourceFile, SourceMapError,
+    };
     #[cfg(feature = "std")]
-    pub use super::customprinter::CustomPrintTracer;
+    pub use super::customprinter::{CustomPrintTracer, PrintVerbosity};
     #[cfg(all(feature = "std", feature = "serde-json"))]
-    pub use super::eip3155::TracerEip3155;
+    pub use super::eip3155::{Eip3155Step, Eip3155StepError, TracerEip3155};
     pub use super::gas::GasInspector;
+    pub use super::gas_profiler::{
+        GasProfiler, ProfileEntry, ProfileGroup, ProfileRow, ProfileSort,
+    };
+    pub use super::log_stream::{DecodedEvent, LogStreamInspector, StreamedLog};
+    pub use super::multi::{InspectorList, MultiInspector, OverridePolicy};
     pub use super::noop::NoOpInspector;
+    pub use super::parity_tracer::{
+        AccountDiff, Action, CallAction, CallOutput, CallType, ChangedType, CreateAction,
+        CreateOutput, CreationMethod, Delta, MemoryDelta, ParityTraceTypes, ParityTracer,
+        SelfdestructAction, StateDiff, StorageDelta, TraceOutput, TraceResults, TransactionTrace,
+        VmExecutedOperation, VmInstruction, VmTrace,
+    };
+    pub use super::prestate_tracer::{
+        AccountState, PrestateFrame, PrestateTracer, PrestateTracerConfig,
+    };
+    pub use super::revert_tracer::{RevertLocation, RevertTrace, RevertTracer};
+    pub use super::security::{SecurityFinding, SecurityInspector, SecurityIssue};
+    pub use super::struct_logger::{
+        StructLog, StructLogger, StructLoggerConfig, StructLoggerResult,
+    };
+    pub use super::token_flow::{
+        Asset, TokenFlowInspector, TokenTransfer, TRANSFER_BATCH_EVENT_TOPIC,
+        TRANSFER_SINGLE_EVENT_TOPIC,
+    };
+    pub use super::transfer::{TransferInspector, TRANSFER_EVENT_TOPIC, TRANSFER_LOG_ADDRESS};
+    pub use super::user_op_validation::{
+        Entity, RuleViolation, StorageRule, UserOpValidationTracer, ValidationPhase, ValidationRule,
+    };
 }
 
 /// EVM [Interpreter] callbacks.
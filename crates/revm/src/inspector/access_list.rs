@@ -0,0 +1,109 @@
+//! AccessListInspector. Collects addresses and storage slots accessed by the transaction.
+
+use crate::{
+    interpreter::{opcode, Interpreter},
+    primitives::{db::Database, AccessListItem, Address, HashSet, B256},
+    EvmContext, Inspector,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+/// [Inspector] that collects the [EIP-2930] access list of the transaction.
+///
+/// Every address and storage slot touched by the executed opcodes is collected,
+/// except for the excluded addresses that are warm anyway, as the sender,
+/// the recipient and the precompiles. Created by [crate::Evm::create_access_list]
+/// or directly with [AccessListInspector::new].
+///
+/// [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessListInspector {
+    /// Addresses that are not added to the access list.
+    excluded: HashSet<Address>,
+    /// Collected addresses and slots, sorted.
+    access_list: BTreeMap<Address, BTreeSet<B256>>,
+}
+
+impl AccessListInspector {
+    /// Create new inspector that starts with the `access_list` and never adds `excluded` addresses.
+    pub fn new(
+        access_list: &[AccessListItem],
+        excluded: impl IntoIterator<Item = Address>,
+    ) -> Self {
+        let mut inspector = Self {
+            excluded: excluded.into_iter().collect(),
+            access_list: BTreeMap::new(),
+        };
+        for item in access_list {
+            if inspector.excluded.contains(&item.address) {
+                continue;
+            }
+            inspector
+                .access_list
+                .entry(item.address)
+                .or_default()
+                .extend(item.storage_keys.iter().copied());
+        }
+        inspector
+    }
+
+    /// Returns excluded addresses.
+    pub fn excluded(&self) -> &HashSet<Address> {
+        &self.excluded
+    }
+
+    /// Returns the collected access list, sorted by address and storage key.
+    pub fn access_list(&self) -> Vec<AccessListItem> {
+        self.access_list
+            .iter()
+            .map(|(address, slots)| AccessListItem {
+                address: *address,
+                storage_keys: slots.iter().copied().collect(),
+            })
+            .collect()
+    }
+
+    fn add_address(&mut self, address: Address) {
+        if !self.excluded.contains(&address) {
+            self.access_list.entry(address).or_default();
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListInspector {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let stack = interp.stack();
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = stack.peek(0) {
+                    let address = interp.contract.target_address;
+                    // storage of excluded accounts is not warm.
+                    self.access_list
+                        .entry(address)
+                        .or_default()
+                        .insert(B256::from(slot));
+                }
+            }
+            opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::EXTCODESIZE
+            | opcode::BALANCE
+            | opcode::SELFDESTRUCT
+            | opcode::EXTCALL
+            | opcode::EXTDELEGATECALL
+            | opcode::EXTSTATICCALL => {
+                if let Ok(address) = stack.peek(0) {
+                    self.add_address(Address::from_word(B256::from(address)));
+                }
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                if let Ok(address) = stack.peek(1) {
+                    self.add_address(Address::from_word(B256::from(address)));
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,615 @@
+//! CallTracer. Builds the call tree in the format of geth `callTracer`.
+
+use crate::{
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, EOFCreateInputs,
+        InstructionResult, Interpreter, SuccessOrHalt,
+    },
+    primitives::{
+        db::Database, Address, Bytes, CreateScheme, HaltReason, Log, RevertReason, SpecId, B256,
+        U256,
+    },
+    EvmContext, Inspector,
+};
+use std::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Options of the [CallTracer], named as in geth `callTracer` config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct CallTracerConfig {
+    /// Trace only the top call, without the nested ones.
+    pub only_top_call: bool,
+    /// Include logs emitted by the calls.
+    pub with_log: bool,
+}
+
+/// Type of the call frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+pub enum CallKind {
+    /// `CALL` or `EXTCALL`.
+    #[default]
+    Call,
+    /// `CALLCODE`.
+    CallCode,
+    /// `DELEGATECALL` or `EXTDELEGATECALL`.
+    DelegateCall,
+    /// `STATICCALL` or `EXTSTATICCALL`.
+    StaticCall,
+    /// `CREATE`.
+    Create,
+    /// `CREATE2`.
+    Create2,
+    /// `EOFCREATE` or EOF creation transaction.
+    EofCreate,
+}
+
+impl From<CallScheme> for CallKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call | CallScheme::ExtCall => Self::Call,
+            CallScheme::CallCode => Self::CallCode,
+            CallScheme::DelegateCall | CallScheme::ExtDelegateCall => Self::DelegateCall,
+            CallScheme::StaticCall | CallScheme::ExtStaticCall => Self::StaticCall,
+        }
+    }
+}
+
+impl From<CreateScheme> for CallKind {
+    fn from(scheme: CreateScheme) -> Self {
+        match scheme {
+            CreateScheme::Create => Self::Create,
+            CreateScheme::Create2 { .. } => Self::Create2,
+        }
+    }
+}
+
+/// Log emitted by a call frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallLog {
+    /// Address of the emitter.
+    pub address: Address,
+    /// Topics of the log.
+    pub topics: Vec<B256>,
+    /// Data of the log.
+    pub data: Bytes,
+    /// Number of nested calls of the frame made before the log was emitted.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub position: u64,
+}
+
+/// Call frame, fields are ordered and named as in geth `callTracer` output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CallFrame {
+    /// Caller of the frame.
+    pub from: Address,
+    /// Gas available to the frame; gas limit of the transaction for the top call.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas: u64,
+    /// Gas used by the frame; gas used by the transaction for the top call.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas_used: u64,
+    /// Called or created address. Not set for failed creations.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub to: Option<Address>,
+    /// Call data or init code.
+    pub input: Bytes,
+    /// Returned data, deployed code for creations.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "<[u8]>::is_empty")
+    )]
+    pub output: Bytes,
+    /// Error if the frame did not succeed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<String>,
+    /// Decoded `Error(string)` revert reason.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub revert_reason: Option<String>,
+    /// Nested frames.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub calls: Vec<CallFrame>,
+    /// Logs emitted by the frame, if enabled with [CallTracerConfig::with_log].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub logs: Vec<CallLog>,
+    /// Transferred value. Not set for static and delegate calls.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub value: Option<U256>,
+    /// Type of the frame.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub kind: CallKind,
+}
+
+impl CallFrame {
+    /// Returns true if the frame did not succeed.
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Remove logs of this frame and of all nested frames.
+    fn clear_logs(&mut self) {
+        self.logs.clear();
+        for call in &mut self.calls {
+            call.clear_logs();
+        }
+    }
+}
+
+/// [Inspector] that builds the call tree of the transaction as geth `callTracer` does.
+///
+/// Logs of the frames that failed are removed, together with logs of their nested frames.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallTracer {
+    config: CallTracerConfig,
+    /// Frames that are being executed.
+    stack: Vec<CallFrame>,
+    /// Depth of the frames that are not traced because of [CallTracerConfig::only_top_call].
+    skipped: usize,
+    /// Finished top frame.
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// Create new tracer with the given options.
+    pub fn new(config: CallTracerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options of the tracer.
+    pub fn config(&self) -> &CallTracerConfig {
+        &self.config
+    }
+
+    /// Returns the top frame if the transaction was executed.
+    pub fn root(&self) -> Option<&CallFrame> {
+        self.root.as_ref()
+    }
+
+    /// Takes the top frame, resetting the tracer for the next transaction.
+    pub fn take_root(&mut self) -> Option<CallFrame> {
+        self.stack.clear();
+        self.skipped = 0;
+        self.root.take()
+    }
+
+    fn start_frame(&mut self, frame: CallFrame) {
+        if self.config.only_top_call && !self.stack.is_empty() {
+            self.skipped += 1;
+            return;
+        }
+        if self.stack.is_empty() {
+            self.root = None;
+        }
+        self.stack.push(frame);
+    }
+
+    fn end_frame<DB: Database>(
+        &mut self,
+        context: &EvmContext<DB>,
+        result: InstructionResult,
+        gas: &crate::interpreter::Gas,
+        output: &Bytes,
+        address: Option<Address>,
+    ) {
+        if self.skipped > 0 {
+            self.skipped -= 1;
+            return;
+        }
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = frame.gas - gas.remaining();
+        if let Some(address) = address {
+            frame.to = Some(address);
+        }
+        frame.output = output.clone();
+        match SuccessOrHalt::from(result) {
+            SuccessOrHalt::Success(_) => {}
+            SuccessOrHalt::Revert => {
+                frame.error = Some("execution reverted".to_string());
+                frame.revert_reason = RevertReason::decode(output).message().map(String::from);
+            }
+            SuccessOrHalt::Halt(reason) => {
+                frame.error = Some(halt_error(reason));
+                frame.output = Bytes::new();
+            }
+            other => frame.error = Some(format!("{other:?}")),
+        }
+        if frame.is_error() {
+            frame.clear_logs();
+            if matches!(frame.kind, CallKind::Create | CallKind::Create2) {
+                frame.to = None;
+            }
+        }
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => {
+                // top call accounts for the whole transaction, as in geth.
+                let tx = &context.env.tx;
+                let spent = tx.gas_limit - gas.remaining();
+                let refunded = if result.is_ok() {
+                    let quotient = if context.spec_id().is_enabled_in(SpecId::LONDON) {
+                        5
+                    } else {
+                        2
+                    };
+                    (gas.refunded() as u64).min(spent / quotient)
+                } else {
+                    0
+                };
+                frame.gas = tx.gas_limit;
+                frame.gas_used = spent - refunded;
+                self.root = Some(frame);
+            }
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn log(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>, log: &Log) {
+        if !self.config.with_log || self.skipped > 0 {
+            return;
+        }
+        if let Some(frame) = self.stack.last_mut() {
+            frame.logs.push(CallLog {
+                address: log.address,
+                topics: log.topics().to_vec(),
+                data: log.data.data.clone(),
+                position: frame.calls.len() as u64,
+            });
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let kind = CallKind::from(inputs.scheme);
+        let value = match kind {
+            CallKind::DelegateCall | CallKind::StaticCall => None,
+            _ => Some(inputs.call_value()),
+        };
+        self.start_frame(CallFrame {
+            from: inputs.caller,
+            gas: inputs.gas_limit,
+            to: Some(inputs.target_address),
+            input: inputs.input.clone(),
+            value,
+            kind,
+            ..Default::default()
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.end_frame(
+            context,
+            outcome.result.result,
+            &outcome.result.gas,
+            &outcome.result.output,
+            None,
+        );
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.start_frame(CallFrame {
+            from: inputs.caller,
+            gas: inputs.gas_limit,
+            input: inputs.init_code.clone(),
+            value: Some(inputs.value),
+            kind: inputs.scheme.into(),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(
+            context,
+            outcome.result.result,
+            &outcome.result.gas,
+            &outcome.result.output,
+            outcome.address,
+        );
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        let input = match &inputs.kind {
+            crate::interpreter::EOFCreateKind::Tx { initdata } => initdata.clone(),
+            crate::interpreter::EOFCreateKind::Opcode {
+                initcode, input, ..
+            } => [initcode.raw.as_ref(), input.as_ref()].concat().into(),
+        };
+        self.start_frame(CallFrame {
+            from: inputs.caller,
+            gas: inputs.gas_limit,
+            to: inputs.kind.created_address().copied(),
+            input,
+            value: Some(inputs.value),
+            kind: CallKind::EofCreate,
+            ..Default::default()
+        });
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(
+            context,
+            outcome.result.result,
+            &outcome.result.gas,
+            &outcome.result.output,
+            outcome.address,
+        );
+        outcome
+    }
+}
+
+/// Returns the geth error message of the halt.
+pub(crate) fn halt_error(reason: HaltReason) -> String {
+    let message = match reason {
+        HaltReason::OutOfGas(_) => "out of gas",
+        HaltReason::OpcodeNotFound | HaltReason::InvalidFEOpcode | HaltReason::NotActivated => {
+            "invalid opcode"
+        }
+        HaltReason::InvalidJump => "invalid jump destination",
+        HaltReason::StackUnderflow => "stack underflow",
+        HaltReason::StackOverflow => "stack limit reached 1024",
+        HaltReason::OutOfOffset => "return data out of bounds",
+        HaltReason::CreateCollision => "contract address collision",
+        HaltReason::PrecompileError => "precompiled contract failed",
+        HaltReason::NonceOverflow => "nonce uint64 overflow",
+        HaltReason::CreateContractSizeLimit => "max code size exceeded",
+        HaltReason::CreateContractStartingWithEF => "invalid code: must not begin with 0xef",
+        HaltReason::CreateInitCodeSizeLimit => "max initcode size exceeded",
+        HaltReason::OverflowPayment => "gas uint64 overflow",
+        HaltReason::StateChangeDuringStaticCall | HaltReason::CallNotAllowedInsideStatic => {
+            "write protection"
+        }
+        HaltReason::OutOfFunds => "insufficient balance for transfer",
+        HaltReason::CallTooDeep => "max call depth exceeded",
+        other => return format!("{other:?}"),
+    };
+    message.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{AccountInfo, Bytecode, TxKind},
+        Evm,
+    };
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const OUTER: Address = Address::repeat_byte(0x0a);
+    const INNER: Address = Address::repeat_byte(0x0b);
+
+    /// Outer contract logs, calls the inner contract with 1 wei and returns 32 bytes.
+    /// Inner contract logs and reverts with `Error("nope")`.
+    fn evm(config: CallTracerConfig) -> Evm<'static, CallTracer, CacheDB<EmptyDB>> {
+        // PUSH1 0xaa PUSH0 PUSH0 LOG1 CALL(gas, INNER, 1, 0, 0, 0, 0) PUSH0 MSTORE PUSH1 32 PUSH0 RETURN
+        let mut outer = vec![
+            0x60, 0xaa, 0x5f, 0x5f, 0xa1, 0x5f, 0x5f, 0x5f, 0x5f, 0x60, 0x01, 0x73,
+        ];
+        outer.extend_from_slice(INNER.as_slice());
+        outer.extend_from_slice(&[0x5a, 0xf1, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3]);
+
+        // PUSH1 0xbb PUSH0 PUSH0 LOG1, then revert with Error("nope").
+        let mut inner = vec![0x60, 0xbb, 0x5f, 0x5f, 0xa1];
+        let mut revert = vec![0x08, 0xc3, 0x79, 0xa0];
+        revert.extend_from_slice(&U256::from(32).to_be_bytes::<32>());
+        revert.extend_from_slice(&U256::from(4).to_be_bytes::<32>());
+        revert.extend_from_slice(b"nope");
+        revert.resize(100, 0);
+        for (i, chunk) in revert.chunks(32).enumerate() {
+            let mut word = [0u8; 32];
+            word[..chunk.len()].copy_from_slice(chunk);
+            inner.push(0x7f);
+            inner.extend_from_slice(&word);
+            inner.extend_from_slice(&[0x60, (i * 32) as u8, 0x52]);
+        }
+        inner.extend_from_slice(&[0x60, 100, 0x5f, 0xfd]);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            OUTER,
+            AccountInfo {
+                balance: U256::from(10),
+                ..AccountInfo::from_bytecode(Bytecode::new_raw(outer.into()))
+            },
+        );
+        db.insert_account_info(
+            INNER,
+            AccountInfo::from_bytecode(Bytecode::new_raw(inner.into())),
+        );
+        Evm::builder()
+            .with_db(db)
+            .with_external_context(CallTracer::new(config))
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(OUTER);
+                tx.gas_limit = 100_000;
+                tx.data = Bytes::from_static(&[0x12, 0x34]);
+            })
+            .build()
+    }
+
+    #[test]
+    fn call_tree() {
+        let mut evm = evm(CallTracerConfig {
+            only_top_call: false,
+            with_log: true,
+        });
+        let result = evm.transact().unwrap().result;
+        let root = evm.context.external.take_root().unwrap();
+
+        assert_eq!(root.kind, CallKind::Call);
+        assert_eq!(root.from, CALLER);
+        assert_eq!(root.to, Some(OUTER));
+        assert_eq!(root.gas, 100_000);
+        assert_eq!(root.gas_used, result.gas_used());
+        assert_eq!(root.input, Bytes::from_static(&[0x12, 0x34]));
+        assert_eq!(root.output, Bytes::from(U256::ZERO.to_be_bytes_vec()));
+        assert_eq!(root.value, Some(U256::ZERO));
+        assert_eq!(root.error, None);
+        assert_eq!(root.logs.len(), 1);
+        assert_eq!(root.logs[0].position, 0);
+
+        assert_eq!(root.calls.len(), 1);
+        let inner = &root.calls[0];
+        assert_eq!(inner.from, OUTER);
+        assert_eq!(inner.to, Some(INNER));
+        assert_eq!(inner.value, Some(U256::from(1)));
+        assert_eq!(inner.error.as_deref(), Some("execution reverted"));
+        assert_eq!(inner.revert_reason.as_deref(), Some("nope"));
+        assert!(inner.gas_used > 0 && inner.gas_used < inner.gas);
+        // logs of the reverted call are dropped.
+        assert!(inner.logs.is_empty());
+    }
+
+    #[test]
+    fn only_top_call_without_logs() {
+        let mut without_logs = evm(CallTracerConfig::default());
+        without_logs.transact().unwrap();
+        let root = without_logs.context.external.take_root().unwrap();
+        assert!(root.logs.is_empty());
+        assert_eq!(root.calls.len(), 1);
+
+        let mut only_top_call = evm(CallTracerConfig {
+            only_top_call: true,
+            with_log: true,
+        });
+        only_top_call.transact().unwrap();
+        let root = only_top_call.context.external.take_root().unwrap();
+        assert_eq!(root.logs.len(), 1);
+        assert!(root.calls.is_empty());
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn geth_format() {
+        let mut evm = evm(CallTracerConfig {
+            only_top_call: false,
+            with_log: true,
+        });
+        evm.transact().unwrap();
+        let mut root = evm.context.external.take_root().unwrap();
+        root.gas_used = 0x5208;
+        root.calls[0].gas = 0x100;
+        root.calls[0].gas_used = 0x10;
+
+        let json = serde_json::to_value(&root).unwrap();
+        let expected = serde_json::json!({
+            "from": "0xcacacacacacacacacacacacacacacacacacacaca",
+            "gas": "0x186a0",
+            "gasUsed": "0x5208",
+            "to": "0x0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a",
+            "input": "0x1234",
+            "output": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "calls": [{
+                "from": "0x0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a",
+                "gas": "0x100",
+                "gasUsed": "0x10",
+                "to": "0x0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+                "input": "0x",
+                "output": root.calls[0].output,
+                "error": "execution reverted",
+                "revertReason": "nope",
+                "value": "0x1",
+                "type": "CALL"
+            }],
+            "logs": [{
+                "address": "0x0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a",
+                "topics": ["0x00000000000000000000000000000000000000000000000000000000000000aa"],
+                "data": "0x",
+                "position": "0x0"
+            }],
+            "value": "0x0",
+            "type": "CALL"
+        });
+        assert_eq!(json, expected);
+
+        // keys are in the same order as in geth.
+        let text = serde_json::to_string(&root.calls[0]).unwrap();
+        let keys = [
+            "from",
+            "gas",
+            "gasUsed",
+            "to",
+            "input",
+            "output",
+            "error",
+            "revertReason",
+            "value",
+            "type",
+        ];
+        let positions = keys
+            .iter()
+            .map(|key| text.find(&format!("\"{key}\":")).unwrap())
+            .collect::<Vec<_>>();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+        let decoded: CallFrame = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, root);
+    }
+}
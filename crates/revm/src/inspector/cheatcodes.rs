@@ -0,0 +1,975 @@
+//! Cheatcodes. Foundry compatible testing cheatcodes at [HEVM_ADDRESS].
+
+use crate::{
+    abi::{self, Abi, AbiValue},
+    handler::register::EvmHandler,
+    inspector_handle_register,
+    interpreter::{
+        analysis::to_analysed, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome,
+        Gas, InstructionResult, Interpreter, InterpreterResult,
+    },
+    primitives::{
+        address, db::Database, Address, BlockEnv, Bytecode, Bytes, Log, RevertReason, B256,
+        ERROR_SELECTOR, U256,
+    },
+    EvmContext, Inspector, JournalCheckpoint,
+};
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+/// Address of the cheatcodes, `address(bytes20(uint160(uint256(keccak256("hevm cheat code")))))`.
+pub const HEVM_ADDRESS: Address = address!("7109709ECfa91a80626fF3989D68f67F5b1DD12D");
+
+/// Supported cheatcodes, with the signatures of Foundry `Vm`.
+const CHEATCODES: &[&str] = &[
+    "function warp(uint256)",
+    "function roll(uint256)",
+    "function deal(address,uint256)",
+    "function etch(address,bytes)",
+    "function store(address,bytes32,bytes32)",
+    "function load(address,bytes32) returns (bytes32)",
+    "function prank(address)",
+    "function prank(address,address)",
+    "function startPrank(address)",
+    "function startPrank(address,address)",
+    "function stopPrank()",
+    "function expectRevert()",
+    "function expectRevert(bytes)",
+    "function expectRevert(bytes4)",
+    "function expectEmit()",
+    "function expectEmit(address)",
+    "function expectEmit(bool,bool,bool,bool)",
+    "function expectEmit(bool,bool,bool,bool,address)",
+    "function record()",
+    "function accesses(address) returns (bytes32[],bytes32[])",
+    "function snapshot() returns (uint256)",
+    "function revertTo(uint256) returns (bool)",
+    "function label(address,string)",
+];
+
+/// Storage slots read and written by a contract while recording.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageAccesses {
+    /// Slots read by `SLOAD` or `SSTORE`.
+    pub reads: Vec<B256>,
+    /// Slots written by `SSTORE`.
+    pub writes: Vec<B256>,
+}
+
+/// Caller, and optionally origin, of the calls of a frame.
+#[derive(Clone, Copy, Debug)]
+struct Prank {
+    sender: Address,
+    origin: Option<Address>,
+    /// Depth of the frame that set the prank, only its calls are pranked.
+    depth: u64,
+    /// Whether the prank applies until `stopPrank` or only to the next call.
+    persistent: bool,
+}
+
+/// Revert expected from the next call of a frame.
+#[derive(Clone, Debug)]
+struct ExpectedRevert {
+    /// Expected revert data, any data matches if `None`.
+    data: Option<Bytes>,
+    /// Whether the data is a selector that only has to prefix the revert data.
+    selector_only: bool,
+    depth: u64,
+    /// Whether the call the revert is expected from has started.
+    active: bool,
+    /// Whether the call reverted as expected, it succeeds once its journal is reverted.
+    matched: bool,
+}
+
+/// Log expected to be emitted by the next call of a frame.
+#[derive(Clone, Debug)]
+struct ExpectedEmit {
+    /// Whether topics 1 to 3 and the data are compared.
+    checks: [bool; 4],
+    address: Option<Address>,
+    depth: u64,
+    /// Expected log, the next log emitted by the frame.
+    log: Option<Log>,
+    found: bool,
+}
+
+impl ExpectedEmit {
+    fn matches(&self, log: &Log) -> bool {
+        let Some(expected) = &self.log else {
+            return false;
+        };
+        let (topics, expected_topics) = (log.topics(), expected.topics());
+        self.address.is_none_or(|address| address == log.address)
+            && topics.len() == expected_topics.len()
+            && topics.first() == expected_topics.first()
+            && (1..topics.len()).all(|i| !self.checks[i - 1] || topics[i] == expected_topics[i])
+            && (!self.checks[3] || log.data.data == expected.data.data)
+    }
+}
+
+/// State to restore on `revertTo`.
+#[derive(Clone, Debug)]
+struct Snapshot {
+    checkpoint: JournalCheckpoint,
+    /// Index of the journal entry set the snapshot starts.
+    journal_i: usize,
+    block: BlockEnv,
+}
+
+/// [Inspector] that implements Foundry compatible cheatcodes called at [HEVM_ADDRESS].
+///
+/// State changes made by cheatcodes are journaled, so they are reverted together with the
+/// frame that made them. `warp` and `roll` change the block environment, which is only restored
+/// by `revertTo`.
+///
+/// Snapshots are valid until the transaction ends or until a frame that was executing when the
+/// snapshot was taken reverts. `revertTo` returns `false` for invalid snapshots.
+///
+/// A failed expectation makes the call it applies to revert with `Error(string)`, together with
+/// its state changes. Expectations are checked before the journal of the call is committed, so
+/// the inspector has to be registered with [cheatcodes_handle_register].
+#[derive(Clone, Debug)]
+pub struct Cheatcodes {
+    abi: Abi,
+    prank: Option<Prank>,
+    /// Origins to restore when pranked calls end, with the depth of the calls.
+    origins: Vec<(u64, Address)>,
+    expected_revert: Option<ExpectedRevert>,
+    expected_emits: Vec<ExpectedEmit>,
+    recording: bool,
+    accesses: BTreeMap<Address, StorageAccesses>,
+    /// Snapshots by id, `None` once invalidated.
+    snapshots: Vec<Option<Snapshot>>,
+    /// Number of journal entry sets when each active frame started.
+    frames: Vec<usize>,
+    labels: BTreeMap<Address, String>,
+}
+
+impl Default for Cheatcodes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cheatcodes {
+    /// Creates a new inspector.
+    pub fn new() -> Self {
+        Self {
+            abi: Abi::parse(CHEATCODES.iter().copied()).expect("valid signatures"),
+            prank: None,
+            origins: Vec::new(),
+            expected_revert: None,
+            expected_emits: Vec::new(),
+            recording: false,
+            accesses: BTreeMap::new(),
+            snapshots: Vec::new(),
+            frames: Vec::new(),
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the labels set by `label`.
+    pub fn labels(&self) -> &BTreeMap<Address, String> {
+        &self.labels
+    }
+
+    /// Returns the label of the address.
+    pub fn label(&self, address: &Address) -> Option<&str> {
+        self.labels.get(address).map(String::as_str)
+    }
+
+    /// Returns the storage accesses recorded since `record`.
+    pub fn accesses(&self) -> &BTreeMap<Address, StorageAccesses> {
+        &self.accesses
+    }
+
+    /// Executes the cheatcode and returns its output, or the revert message.
+    fn apply<DB: Database>(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        input: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let Some((function, values)) = self.abi.decode_call(input) else {
+            return Err("unknown cheatcode".to_string());
+        };
+        let depth = context.journaled_state.depth();
+        match (function.name.as_str(), values.as_slice()) {
+            ("warp", [AbiValue::Uint(timestamp)]) => context.env.block.timestamp = *timestamp,
+            ("roll", [AbiValue::Uint(number)]) => context.env.block.number = *number,
+            ("deal", [AbiValue::Address(address), AbiValue::Uint(balance)]) => {
+                load_account(context, *address)?;
+                context.journaled_state.set_balance(*address, *balance);
+            }
+            ("etch", [AbiValue::Address(address), AbiValue::Bytes(code)]) => {
+                let code = Bytecode::new_raw_checked(code.clone())
+                    .map_err(|_| "invalid EOF code".to_string())?;
+                load_account(context, *address)?;
+                context
+                    .journaled_state
+                    .replace_code(*address, to_analysed(code));
+            }
+            (
+                "store",
+                [AbiValue::Address(address), AbiValue::FixedBytes(slot, _), AbiValue::FixedBytes(value, _)],
+            ) => {
+                load_account(context, *address)?;
+                let inner = &mut context.inner;
+                let result = inner.journaled_state.sstore(
+                    *address,
+                    (*slot).into(),
+                    (*value).into(),
+                    &mut inner.db,
+                );
+                result.map_err(|e| database_error(context, e))?;
+            }
+            ("load", [AbiValue::Address(address), AbiValue::FixedBytes(slot, _)]) => {
+                load_account(context, *address)?;
+                let inner = &mut context.inner;
+                let result = inner
+                    .journaled_state
+                    .sload(*address, (*slot).into(), &mut inner.db);
+                let (value, _) = result.map_err(|e| database_error(context, e))?;
+                return Ok(abi::encode(&[AbiValue::FixedBytes(value.into(), 32)]));
+            }
+            ("prank" | "startPrank", [AbiValue::Address(sender), rest @ ..]) => {
+                self.prank = Some(Prank {
+                    sender: *sender,
+                    origin: rest.first().and_then(AbiValue::as_address),
+                    depth,
+                    persistent: function.name == "startPrank",
+                });
+            }
+            ("stopPrank", []) => self.prank = None,
+            ("expectRevert", data) => {
+                self.expected_revert = Some(ExpectedRevert {
+                    data: match data.first() {
+                        Some(AbiValue::Bytes(data)) => Some(data.clone()),
+                        Some(AbiValue::FixedBytes(selector, 4)) => {
+                            Some(Bytes::copy_from_slice(&selector[..4]))
+                        }
+                        _ => None,
+                    },
+                    selector_only: matches!(data.first(), Some(AbiValue::FixedBytes(..))),
+                    depth,
+                    active: false,
+                    matched: false,
+                });
+            }
+            ("expectEmit", args) => {
+                let mut checks = [true; 4];
+                let mut address = None;
+                for (i, arg) in args.iter().enumerate() {
+                    match arg {
+                        AbiValue::Bool(check) => checks[i] = *check,
+                        AbiValue::Address(emitter) => address = Some(*emitter),
+                        _ => {}
+                    }
+                }
+                self.expected_emits.push(ExpectedEmit {
+                    checks,
+                    address,
+                    depth,
+                    log: None,
+                    found: false,
+                });
+            }
+            ("record", []) => {
+                self.recording = true;
+                self.accesses.clear();
+            }
+            ("accesses", [AbiValue::Address(address)]) => {
+                let accesses = self.accesses.get(address).cloned().unwrap_or_default();
+                let slots = |slots: Vec<B256>| {
+                    AbiValue::Array(
+                        slots
+                            .into_iter()
+                            .map(|slot| AbiValue::FixedBytes(slot, 32))
+                            .collect(),
+                    )
+                };
+                return Ok(abi::encode(&[
+                    slots(accesses.reads),
+                    slots(accesses.writes),
+                ]));
+            }
+            ("snapshot", []) => {
+                let journal_i = context.journaled_state.journal.len();
+                self.snapshots.push(Some(Snapshot {
+                    checkpoint: context.journaled_state.snapshot(),
+                    journal_i,
+                    block: context.env.block.clone(),
+                }));
+                return Ok(abi::encode(&[AbiValue::Uint(U256::from(
+                    self.snapshots.len() - 1,
+                ))]));
+            }
+            ("revertTo", [AbiValue::Uint(id)]) => {
+                let snapshot = usize::try_from(*id)
+                    .ok()
+                    .and_then(|id| self.snapshots.get(id))
+                    .and_then(Option::as_ref);
+                let Some(snapshot) = snapshot else {
+                    return Ok(abi::encode(&[AbiValue::Bool(false)]));
+                };
+                context
+                    .journaled_state
+                    .revert_to_snapshot(snapshot.checkpoint);
+                context.env.block = snapshot.block.clone();
+                return Ok(abi::encode(&[AbiValue::Bool(true)]));
+            }
+            ("label", [AbiValue::Address(address), AbiValue::String(label)]) => {
+                self.labels.insert(*address, label.clone());
+            }
+            _ => return Err(format!("invalid arguments for {}", function.signature())),
+        }
+        Ok(Vec::new())
+    }
+
+    /// Records the start of a frame.
+    fn frame_start<DB: Database>(&mut self, context: &EvmContext<DB>) {
+        self.frames.push(context.journaled_state.journal.len());
+    }
+
+    /// Invalidates the snapshots taken in the frame if it failed, or all of them if the
+    /// transaction ended.
+    fn frame_end(&mut self, success: bool) {
+        let journal_i = self.frames.pop().unwrap_or_default();
+        let invalid = |snapshot: &Snapshot| {
+            self.frames.is_empty() || (!success && snapshot.journal_i >= journal_i)
+        };
+        for snapshot in &mut self.snapshots {
+            if snapshot.as_ref().is_some_and(invalid) {
+                *snapshot = None;
+            }
+        }
+    }
+
+    /// Applies the prank to a call or creation of the frame at `depth`.
+    fn prank<DB: Database>(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        depth: u64,
+        caller: &mut Address,
+    ) {
+        let Some(prank) = self.prank.filter(|prank| prank.depth == depth) else {
+            return;
+        };
+        *caller = prank.sender;
+        if let Some(origin) = prank.origin {
+            self.origins.push((depth, context.env.tx.caller));
+            context.env.tx.caller = origin;
+        }
+        if !prank.persistent {
+            self.prank = None;
+        }
+    }
+
+    /// Checks the expectations of the call or creation of the frame at `depth` that ended.
+    ///
+    /// A call that reverted as expected keeps its result until its journal is reverted.
+    fn check_expectations(&mut self, depth: u64, result: &mut InterpreterResult) {
+        if let Some(expected) = self
+            .expected_revert
+            .as_mut()
+            .filter(|expected| expected.active && !expected.matched && expected.depth == depth)
+        {
+            if result.is_ok() {
+                self.expected_revert = None;
+                return fail(result, "call did not revert as expected");
+            }
+            let matches = match &expected.data {
+                None => true,
+                Some(data) if expected.selector_only => result.output.starts_with(data),
+                Some(data) => result.output == *data,
+            };
+            if !matches {
+                let message = format!(
+                    "{} != expected {}",
+                    RevertReason::decode(&result.output),
+                    RevertReason::decode(expected.data.as_ref().map_or(&[][..], |data| data))
+                );
+                self.expected_revert = None;
+                return fail(result, &message);
+            }
+            expected.matched = true;
+            return;
+        }
+
+        let (checked, pending) = core::mem::take(&mut self.expected_emits)
+            .into_iter()
+            .partition::<Vec<_>, _>(|expected| expected.depth == depth && expected.log.is_some());
+        self.expected_emits = pending;
+        if result.is_ok() && checked.iter().any(|expected| !expected.found) {
+            fail(result, "log != expected log");
+        }
+    }
+
+    /// Restores the origin of the call or creation that ended, and makes it succeed if it
+    /// reverted as expected.
+    fn end<DB: Database>(&mut self, context: &mut EvmContext<DB>, result: &mut InterpreterResult) {
+        let depth = context.journaled_state.depth();
+        if let Some(&(_, origin)) = self.origins.last().filter(|(d, _)| *d == depth) {
+            context.env.tx.caller = origin;
+            self.origins.pop();
+        }
+
+        // Results without a frame are not checked by the handler register.
+        self.check_expectations(depth, result);
+        if self
+            .expected_revert
+            .as_ref()
+            .is_some_and(|expected| expected.matched && expected.depth == depth)
+        {
+            self.expected_revert = None;
+            result.result = InstructionResult::Return;
+            result.output = Bytes::new();
+        }
+    }
+}
+
+/// Registers the [Cheatcodes] inspector.
+///
+/// Same as [inspector_handle_register], and checks the expectations of the cheatcodes before the
+/// journal of the frame they apply to is committed.
+pub fn cheatcodes_handle_register<DB: Database>(handler: &mut EvmHandler<'_, Cheatcodes, DB>) {
+    inspector_handle_register(handler);
+
+    // Journal depth still includes the returning frame.
+    let prev_handle = handler.execution.call_return.clone();
+    handler.execution.call_return = Arc::new(move |ctx, frame, mut result| {
+        let depth = ctx.evm.journaled_state.depth().saturating_sub(1);
+        ctx.external.check_expectations(depth, &mut result);
+        prev_handle(ctx, frame, result)
+    });
+
+    let prev_handle = handler.execution.create_return.clone();
+    handler.execution.create_return = Arc::new(move |ctx, frame, mut result| {
+        let depth = ctx.evm.journaled_state.depth().saturating_sub(1);
+        ctx.external.check_expectations(depth, &mut result);
+        prev_handle(ctx, frame, result)
+    });
+}
+
+/// Makes the result a revert with `Error(message)`.
+fn fail(result: &mut InterpreterResult, message: &str) {
+    result.result = InstructionResult::Revert;
+    result.output = revert_data(message);
+}
+
+fn revert_data(message: &str) -> Bytes {
+    let mut data = ERROR_SELECTOR.to_vec();
+    data.extend_from_slice(&abi::encode(&[AbiValue::String(message.to_string())]));
+    data.into()
+}
+
+fn load_account<DB: Database>(
+    context: &mut EvmContext<DB>,
+    address: Address,
+) -> Result<(), String> {
+    let inner = &mut context.inner;
+    match inner.journaled_state.load_account(address, &mut inner.db) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(database_error(context, e)),
+    }
+}
+
+/// Stores the database error in the context, it is returned when the frame ends.
+fn database_error<DB: Database>(
+    context: &mut EvmContext<DB>,
+    error: crate::primitives::EVMError<DB::Error>,
+) -> String {
+    context.error = Err(error);
+    "database error".to_string()
+}
+
+impl<DB: Database> Inspector<DB> for Cheatcodes {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if !self.recording {
+            return;
+        }
+        let is_write = match interp.current_opcode() {
+            0x54 => false,
+            0x55 => true,
+            _ => return,
+        };
+        let Ok(slot) = interp.stack.peek(0) else {
+            return;
+        };
+        let accesses = self
+            .accesses
+            .entry(interp.contract.target_address)
+            .or_default();
+        accesses.reads.push(slot.into());
+        if is_write {
+            accesses.writes.push(slot.into());
+        }
+    }
+
+    fn log(&mut self, _interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &Log) {
+        let depth = context.journaled_state.depth();
+        if let Some(expected) = self
+            .expected_emits
+            .iter_mut()
+            .find(|expected| expected.log.is_none() && expected.depth == depth)
+        {
+            expected.log = Some(log.clone());
+        } else if let Some(expected) = self
+            .expected_emits
+            .iter_mut()
+            .find(|expected| expected.log.is_some() && !expected.found)
+        {
+            expected.found = expected.matches(log);
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if inputs.target_address == HEVM_ADDRESS {
+            let (result, output) = match self.apply(context, &inputs.input) {
+                Ok(output) => (InstructionResult::Return, output.into()),
+                Err(message) => (InstructionResult::Revert, revert_data(&message)),
+            };
+            return Some(CallOutcome::new(
+                InterpreterResult {
+                    result,
+                    output,
+                    gas: Gas::new(inputs.gas_limit),
+                },
+                inputs.return_memory_offset.clone(),
+            ));
+        }
+
+        self.frame_start(context);
+        let depth = context.journaled_state.depth();
+        if matches!(
+            inputs.scheme,
+            CallScheme::Call
+                | CallScheme::StaticCall
+                | CallScheme::ExtCall
+                | CallScheme::ExtStaticCall
+        ) {
+            self.prank(context, depth, &mut inputs.caller);
+        }
+        if let Some(expected) = &mut self.expected_revert {
+            expected.active |= expected.depth == depth;
+        }
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        mut outcome: CallOutcome,
+    ) -> CallOutcome {
+        if inputs.target_address != HEVM_ADDRESS {
+            // Journal of the frame is already reverted or committed by its checked result.
+            self.frame_end(outcome.result.is_ok());
+            self.end(context, &mut outcome.result);
+        }
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start(context);
+        let depth = context.journaled_state.depth();
+        self.prank(context, depth, &mut inputs.caller);
+        if let Some(expected) = &mut self.expected_revert {
+            expected.active |= expected.depth == depth;
+        }
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        mut outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frame_end(outcome.result.is_ok());
+        self.end(context, &mut outcome.result);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{hex, ResultAndState, TxKind},
+        test_fixtures::{builder, Code, CALLER},
+        Evm, InMemoryDB,
+    };
+
+    /// Executes the calls encoded in the call data: 20 bytes target, 2 bytes length and the
+    /// call data. Calls to the zero address emit `LOG0` with the data instead. Reverts with the
+    /// data of the first failed call and returns the data of the last call.
+    const SCRIPT: &[u8] = &hex!(
+        "60005b36811015604f57803560601c816014013560f01c80836016016000378115604757"
+        "600060008260006000865af1603d573d600060003e3d6000fd5b905001601601600256"
+        "5b806000a0603d565b3d600060003e3d6000f3"
+    );
+
+    const SCRIPT_ADDRESS: Address = Address::repeat_byte(0x5c);
+    const ECHO_CALLER: Address = Address::repeat_byte(0xc1);
+    const ECHO_ORIGIN: Address = Address::repeat_byte(0xc2);
+    const REVERTER: Address = Address::repeat_byte(0xc3);
+    const EMITTER: Address = Address::repeat_byte(0xc4);
+    const STORAGE: Address = Address::repeat_byte(0xc5);
+    const ALICE: Address = Address::repeat_byte(0xa1);
+    const BOB: Address = Address::repeat_byte(0xb0);
+
+    fn cheat(signature: &str, args: &[AbiValue]) -> (Address, Vec<u8>) {
+        let mut input = abi::selector(signature).to_vec();
+        input.extend_from_slice(&abi::encode(args));
+        (HEVM_ADDRESS, input)
+    }
+
+    fn call(target: Address) -> (Address, Vec<u8>) {
+        (target, Vec::new())
+    }
+
+    fn script(steps: &[(Address, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (target, input) in steps {
+            data.extend_from_slice(target.as_slice());
+            data.extend_from_slice(&(input.len() as u16).to_be_bytes());
+            data.extend_from_slice(input);
+        }
+        data
+    }
+
+    fn run(steps: &[(Address, Vec<u8>)]) -> (ResultAndState, Evm<'static, Cheatcodes, InMemoryDB>) {
+        let data = script(steps);
+        let contracts: [(Address, &[u8]); 6] = [
+            (SCRIPT_ADDRESS, SCRIPT),
+            // MSTORE(0, CALLER) RETURN(0, 32)
+            (ECHO_CALLER, &hex!("3360005260206000f3")),
+            // MSTORE(0, ORIGIN) RETURN(0, 32)
+            (ECHO_ORIGIN, &hex!("3260005260206000f3")),
+            // REVERT with `Error("no")`
+            (
+                REVERTER,
+                &hex!("6308c379a060e01b60005260206004526002602452616e6f60f01b60445260646000fd"),
+            ),
+            // LOG0 with data 0xaa
+            (EMITTER, &hex!("60aa60005360016000a000")),
+            // SLOAD(1) SSTORE(2, 5)
+            (STORAGE, &hex!("60015450600560025500")),
+        ];
+        let contracts = contracts.map(|(address, code)| (address, Bytes::from_static(code)));
+        let mut evm = builder(contracts)
+            .with_external_context(Cheatcodes::new())
+            .append_handler_register(cheatcodes_handle_register)
+            .modify_tx_env(|tx| {
+                tx.transact_to = TxKind::Call(SCRIPT_ADDRESS);
+                tx.data = data.into();
+                tx.gas_limit = 10_000_000;
+            })
+            .build();
+        let result = evm.transact().unwrap();
+        (result, evm)
+    }
+
+    fn output(result: &ResultAndState) -> &[u8] {
+        result.result.output().unwrap()
+    }
+
+    fn word(value: u64) -> AbiValue {
+        AbiValue::FixedBytes(B256::from(U256::from(value)), 32)
+    }
+
+    #[test]
+    fn environment_and_state() {
+        let (result, evm) = run(&[
+            cheat("warp(uint256)", &[AbiValue::Uint(U256::from(100))]),
+            cheat("roll(uint256)", &[AbiValue::Uint(U256::from(7))]),
+            cheat(
+                "deal(address,uint256)",
+                &[AbiValue::Address(ALICE), AbiValue::Uint(U256::from(5))],
+            ),
+            cheat(
+                "etch(address,bytes)",
+                &[AbiValue::Address(BOB), AbiValue::Bytes(hex!("00").into())],
+            ),
+            cheat(
+                "store(address,bytes32,bytes32)",
+                &[AbiValue::Address(BOB), word(1), word(2)],
+            ),
+            cheat("load(address,bytes32)", &[AbiValue::Address(BOB), word(1)]),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+        assert_eq!(output(&result), B256::from(U256::from(2)).as_slice());
+        assert_eq!(result.state[&ALICE].info.balance, U256::from(5));
+        assert_eq!(
+            result.state[&BOB]
+                .info
+                .code
+                .as_ref()
+                .unwrap()
+                .original_byte_slice(),
+            [0]
+        );
+        assert_eq!(
+            result.state[&BOB].storage[&U256::from(1)].present_value,
+            U256::from(2)
+        );
+        assert_eq!(evm.context.evm.env.block.timestamp, U256::from(100));
+        assert_eq!(evm.context.evm.env.block.number, U256::from(7));
+
+        // Changes are reverted together with the frame.
+        let (result, _) = run(&[
+            cheat(
+                "deal(address,uint256)",
+                &[AbiValue::Address(ALICE), AbiValue::Uint(U256::from(5))],
+            ),
+            cheat(
+                "etch(address,bytes)",
+                &[
+                    AbiValue::Address(REVERTER),
+                    AbiValue::Bytes(hex!("00").into()),
+                ],
+            ),
+            call(ECHO_CALLER),
+            cheat("unknown()", &[]),
+        ]);
+        assert_eq!(
+            result.result.revert_reason(),
+            Some(RevertReason::Error("unknown cheatcode".into()))
+        );
+        assert_eq!(result.state[&ALICE].info.balance, U256::ZERO);
+        assert_ne!(result.state[&REVERTER].info.code.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn prank() {
+        let prank = cheat("prank(address)", &[AbiValue::Address(ALICE)]);
+        let (result, _) = run(&[prank.clone(), call(ECHO_CALLER)]);
+        assert_eq!(output(&result), ALICE.into_word().as_slice());
+
+        let (result, _) = run(&[prank, call(ECHO_CALLER), call(ECHO_CALLER)]);
+        assert_eq!(output(&result), SCRIPT_ADDRESS.into_word().as_slice());
+
+        let start_prank = cheat(
+            "startPrank(address,address)",
+            &[AbiValue::Address(ALICE), AbiValue::Address(BOB)],
+        );
+        let (result, evm) = run(&[start_prank.clone(), call(ECHO_CALLER), call(ECHO_ORIGIN)]);
+        assert_eq!(output(&result), BOB.into_word().as_slice());
+        assert_eq!(evm.context.evm.env.tx.caller, CALLER);
+
+        let (result, _) = run(&[
+            start_prank,
+            call(ECHO_CALLER),
+            cheat("stopPrank()", &[]),
+            call(ECHO_CALLER),
+        ]);
+        assert_eq!(output(&result), SCRIPT_ADDRESS.into_word().as_slice());
+    }
+
+    #[test]
+    fn expect_revert() {
+        let (result, _) = run(&[
+            cheat(
+                "expectRevert(bytes4)",
+                &[AbiValue::FixedBytes(
+                    B256::right_padding_from(&ERROR_SELECTOR),
+                    4,
+                )],
+            ),
+            call(REVERTER),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+
+        let (result, _) = run(&[cheat("expectRevert()", &[]), call(ECHO_CALLER)]);
+        assert_eq!(
+            result.result.revert_reason(),
+            Some(RevertReason::Error(
+                "call did not revert as expected".into()
+            ))
+        );
+
+        let (result, _) = run(&[
+            cheat(
+                "expectRevert(bytes)",
+                &[AbiValue::Bytes(revert_data("yes"))],
+            ),
+            call(REVERTER),
+        ]);
+        assert_eq!(
+            result.result.revert_reason(),
+            Some(RevertReason::Error(
+                "revert: no != expected revert: yes".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn caught_expectation_failure() {
+        // CALL(GAS, HEVM_ADDRESS, 0, 28, 4, 0, 0) with `expectRevert()`, then calls STORAGE and
+        // ignores the failure.
+        let catcher = Code::new()
+            .op(0x63)
+            .bytes(&abi::selector("expectRevert()"))
+            .push(0)
+            .op(0x52)
+            .push(0)
+            .push(0)
+            .push(4)
+            .push(28)
+            .push(0)
+            .push_address(HEVM_ADDRESS)
+            .bytes(&[0x5a, 0xf1, 0x50])
+            .call(STORAGE, 0)
+            .op(0x50)
+            .op(0x00);
+        let contracts = [
+            (SCRIPT_ADDRESS, catcher.build()),
+            (STORAGE, Bytes::from_static(&hex!("60015450600560025500"))),
+        ];
+        let mut evm = builder(contracts)
+            .with_external_context(Cheatcodes::new())
+            .append_handler_register(cheatcodes_handle_register)
+            .modify_tx_env(|tx| tx.transact_to = TxKind::Call(SCRIPT_ADDRESS))
+            .build();
+        let result = evm.transact().unwrap();
+        assert!(result.result.is_success(), "{:?}", result.result);
+
+        // Write of the call that did not revert as expected is reverted.
+        let slot = result.state[&STORAGE].storage.get(&U256::from(2));
+        assert!(slot.is_none_or(|slot| slot.present_value.is_zero()));
+    }
+
+    #[test]
+    fn expect_emit() {
+        let expect_emit = cheat("expectEmit()", &[]);
+        let (result, _) = run(&[
+            expect_emit.clone(),
+            (Address::ZERO, vec![0xaa]),
+            call(EMITTER),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+
+        let (result, _) = run(&[expect_emit, (Address::ZERO, vec![0xbb]), call(EMITTER)]);
+        assert_eq!(
+            result.result.revert_reason(),
+            Some(RevertReason::Error("log != expected log".into()))
+        );
+
+        // The data is not compared.
+        let (result, _) = run(&[
+            cheat(
+                "expectEmit(bool,bool,bool,bool,address)",
+                &[
+                    AbiValue::Bool(true),
+                    AbiValue::Bool(true),
+                    AbiValue::Bool(true),
+                    AbiValue::Bool(false),
+                    AbiValue::Address(EMITTER),
+                ],
+            ),
+            (Address::ZERO, vec![0xbb]),
+            call(EMITTER),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+    }
+
+    #[test]
+    fn record_accesses() {
+        let (result, evm) = run(&[
+            cheat("record()", &[]),
+            call(STORAGE),
+            cheat("accesses(address)", &[AbiValue::Address(STORAGE)]),
+        ]);
+        let expected = StorageAccesses {
+            reads: vec![B256::from(U256::from(1)), B256::from(U256::from(2))],
+            writes: vec![B256::from(U256::from(2))],
+        };
+        assert_eq!(evm.context.external.accesses()[&STORAGE], expected);
+        let slots = |slots: &[B256]| {
+            AbiValue::Array(slots.iter().map(|s| AbiValue::FixedBytes(*s, 32)).collect())
+        };
+        assert_eq!(
+            output(&result),
+            abi::encode(&[slots(&expected.reads), slots(&expected.writes)])
+        );
+    }
+
+    #[test]
+    fn snapshot_and_label() {
+        let deal = |balance: u64| {
+            cheat(
+                "deal(address,uint256)",
+                &[
+                    AbiValue::Address(ALICE),
+                    AbiValue::Uint(U256::from(balance)),
+                ],
+            )
+        };
+        let (result, evm) = run(&[
+            deal(5),
+            cheat("snapshot()", &[]),
+            deal(9),
+            call(STORAGE),
+            cheat("warp(uint256)", &[AbiValue::Uint(U256::from(50))]),
+            cheat("revertTo(uint256)", &[AbiValue::Uint(U256::ZERO)]),
+            cheat(
+                "label(address,string)",
+                &[AbiValue::Address(ALICE), AbiValue::String("alice".into())],
+            ),
+            cheat("revertTo(uint256)", &[AbiValue::Uint(U256::from(1))]),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+        assert_eq!(output(&result), abi::encode(&[AbiValue::Bool(false)]));
+        assert_eq!(result.state[&ALICE].info.balance, U256::from(5));
+        assert_eq!(
+            result.state[&STORAGE].storage[&U256::from(2)].present_value,
+            U256::ZERO
+        );
+        assert_eq!(evm.context.evm.env.block.timestamp, U256::from(1));
+        assert_eq!(evm.context.external.label(&ALICE), Some("alice"));
+    }
+
+    #[test]
+    fn invalidated_snapshots() {
+        let revert_to = cheat("revertTo(uint256)", &[AbiValue::Uint(U256::ZERO)]);
+        // Snapshot taken in a reverted frame.
+        let (result, _) = run(&[
+            cheat("expectRevert()", &[]),
+            (
+                SCRIPT_ADDRESS,
+                script(&[cheat("snapshot()", &[]), call(REVERTER)]),
+            ),
+            revert_to.clone(),
+        ]);
+        assert!(result.result.is_success(), "{:?}", result.result);
+        assert_eq!(output(&result), abi::encode(&[AbiValue::Bool(false)]));
+
+        // Snapshot taken in a frame that returned stays valid.
+        let (result, _) = run(&[
+            (SCRIPT_ADDRESS, script(&[cheat("snapshot()", &[])])),
+            revert_to.clone(),
+        ]);
+        assert_eq!(output(&result), abi::encode(&[AbiValue::Bool(true)]));
+
+        // Snapshot of the previous transaction.
+        let (result, mut evm) = run(&[cheat("snapshot()", &[])]);
+        assert_eq!(output(&result), abi::encode(&[AbiValue::Uint(U256::ZERO)]));
+        evm.tx_mut().data = script(&[revert_to]).into();
+        let result = evm.transact().unwrap();
+        assert!(result.result.is_success(), "{:?}", result.result);
+        assert_eq!(output(&result), abi::encode(&[AbiValue::Bool(false)]));
+    }
+}
@@ -0,0 +1,278 @@
+//! ConsoleLogInspector. Collects messages of Hardhat and Foundry `console.log`.
+
+use crate::{
+    abi::{self, AbiType, AbiValue},
+    interpreter::{CallInputs, CallOutcome, Gas, InstructionResult, InterpreterResult},
+    primitives::{address, db::Database, Address, Bytes},
+    EvmContext, Inspector,
+};
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Address called by `console.sol`.
+pub const CONSOLE_ADDRESS: Address = address!("000000000000000000636F6e736F6c652e6c6f67");
+
+/// Types of the `log` overloads, all combinations of up to four of them exist.
+const LOG_TYPES: [&str; 5] = ["uint256", "int256", "string", "bool", "address"];
+
+/// Message logged by `console.log`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsoleMessage {
+    /// Contract that logged the message.
+    pub caller: Address,
+    /// Formatted message.
+    pub message: String,
+}
+
+/// [Inspector] that intercepts calls to [CONSOLE_ADDRESS] and collects the logged messages.
+///
+/// The calls succeed without executing code or loading the address, so the state is the same
+/// as without the logs. Messages of reverted frames are kept.
+#[derive(Clone, Debug)]
+pub struct ConsoleLogInspector {
+    /// Argument types of the overloads by selector.
+    overloads: BTreeMap<[u8; 4], Vec<AbiType>>,
+    messages: Vec<ConsoleMessage>,
+}
+
+impl Default for ConsoleLogInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsoleLogInspector {
+    /// Creates a new inspector.
+    pub fn new() -> Self {
+        let mut overloads = BTreeMap::new();
+        let mut add = |name: &str, types: &[&str]| {
+            let signature = format!("{name}({})", types.join(","));
+            let types: Vec<_> = types.iter().map(|ty| AbiType::parse(ty).unwrap()).collect();
+            // Older versions of Hardhat `console.sol` use `uint` and `int` in the signatures.
+            let legacy = signature.replace("int256", "int");
+            if legacy != signature {
+                overloads.insert(abi::selector(&legacy), types.clone());
+            }
+            overloads.insert(abi::selector(&signature), types);
+        };
+
+        let mut combinations: Vec<Vec<&str>> = vec![Vec::new()];
+        for _ in 0..4 {
+            combinations = combinations
+                .iter()
+                .flat_map(|types| {
+                    LOG_TYPES.iter().map(move |ty| {
+                        let mut types = types.clone();
+                        types.push(ty);
+                        types
+                    })
+                })
+                .collect();
+            for types in &combinations {
+                add("log", types);
+            }
+        }
+        add("log", &[]);
+        for (name, ty) in [
+            ("logUint", "uint256"),
+            ("logInt", "int256"),
+            ("logString", "string"),
+            ("logBool", "bool"),
+            ("logAddress", "address"),
+            ("logBytes", "bytes"),
+        ] {
+            add(name, &[ty]);
+        }
+        for size in 1..=32 {
+            add(&format!("logBytes{size}"), &[&format!("bytes{size}")]);
+        }
+
+        Self {
+            overloads,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Returns the collected messages.
+    pub fn messages(&self) -> &[ConsoleMessage] {
+        &self.messages
+    }
+
+    /// Takes the collected messages.
+    pub fn take_messages(&mut self) -> Vec<ConsoleMessage> {
+        core::mem::take(&mut self.messages)
+    }
+
+    /// Decodes and formats the arguments of a `console.log` call.
+    pub fn decode(&self, input: &[u8]) -> Option<String> {
+        let (selector, data) = input.split_first_chunk::<4>()?;
+        let values = abi::decode(self.overloads.get(selector)?, data).ok()?;
+        Some(format_message(&values))
+    }
+}
+
+/// Formats the values like `console.log` in Hardhat and Foundry.
+///
+/// If the first value is a string, `%s`, `%d`, `%i`, `%o` and `%x` in it are replaced with the
+/// following values, `%%` is a literal `%`. Values not consumed by the format are appended,
+/// separated by spaces.
+fn format_message(values: &[AbiValue]) -> String {
+    let mut values = values.iter();
+    let mut message = match values.as_slice().first() {
+        Some(AbiValue::String(format)) => {
+            values.next();
+            let mut message = String::new();
+            let mut chars = format.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    message.push(c);
+                    continue;
+                }
+                match chars.clone().next() {
+                    Some('%') => {
+                        chars.next();
+                        message.push('%');
+                    }
+                    Some(spec @ ('s' | 'd' | 'i' | 'o' | 'x')) => match values.next() {
+                        Some(value) => {
+                            chars.next();
+                            message.push_str(&format_value(value, spec == 'x'));
+                        }
+                        None => message.push('%'),
+                    },
+                    _ => message.push('%'),
+                }
+            }
+            message
+        }
+        _ => String::new(),
+    };
+    for value in values {
+        if !message.is_empty() {
+            message.push(' ');
+        }
+        message.push_str(&format_value(value, false));
+    }
+    message
+}
+
+fn format_value(value: &AbiValue, hex: bool) -> String {
+    match value {
+        AbiValue::String(value) => value.clone(),
+        AbiValue::Uint(value) if hex => format!("{value:#x}"),
+        AbiValue::Int(value) if hex => format!("{:#x}", value.into_raw()),
+        value => value.to_string(),
+    }
+}
+
+impl<DB: Database> Inspector<DB> for ConsoleLogInspector {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if inputs.target_address != CONSOLE_ADDRESS {
+            return None;
+        }
+        if let Some(message) = self.decode(&inputs.input) {
+            self.messages.push(ConsoleMessage {
+                caller: inputs.caller,
+                message,
+            });
+        }
+        Some(CallOutcome::new(
+            InterpreterResult {
+                result: InstructionResult::Stop,
+                output: Bytes::new(),
+                gas: Gas::new(inputs.gas_limit),
+            },
+            inputs.return_memory_offset.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{hex, U256},
+        test_fixtures::{inspector_builder, TARGET},
+    };
+
+    fn word(value: usize) -> [u8; 32] {
+        U256::from(value).to_be_bytes()
+    }
+
+    /// Encodes `log(string,uint256)`.
+    fn log_string_uint(format: &str, value: usize) -> Vec<u8> {
+        let mut input = abi::selector("log(string,uint256)").to_vec();
+        input.extend_from_slice(&word(0x40));
+        input.extend_from_slice(&word(value));
+        input.extend_from_slice(&word(format.len()));
+        let mut data = format.as_bytes().to_vec();
+        data.resize(format.len().div_ceil(32) * 32, 0);
+        input.extend_from_slice(&data);
+        input
+    }
+
+    #[test]
+    fn decode_overloads() {
+        let inspector = ConsoleLogInspector::new();
+        assert_eq!(
+            inspector.decode(&log_string_uint("value %d, %x%%", 255)),
+            Some("value 255, %x%".into())
+        );
+        assert_eq!(
+            inspector.decode(&log_string_uint("value", 255)),
+            Some("value 255".into())
+        );
+
+        let mut input = abi::selector("log(uint)").to_vec();
+        input.extend_from_slice(&word(7));
+        assert_eq!(inspector.decode(&input), Some("7".into()));
+
+        let mut input = abi::selector("log(address,bool)").to_vec();
+        input.extend_from_slice(&[0; 12]);
+        input.extend_from_slice(TARGET.as_slice());
+        input.extend_from_slice(&word(1));
+        assert_eq!(inspector.decode(&input), Some(format!("{TARGET} true")));
+
+        let mut input = abi::selector("logBytes2(bytes2)").to_vec();
+        input.extend_from_slice(&hex!("beef"));
+        input.extend_from_slice(&[0; 30]);
+        assert_eq!(inspector.decode(&input), Some("0xbeef".into()));
+
+        assert_eq!(inspector.decode(&hex!("12345678")), None);
+    }
+
+    #[test]
+    fn intercepts_calls() {
+        // CALLDATACOPY(0, 0, CALLDATASIZE)
+        // STATICCALL(GAS, CONSOLE_ADDRESS, 0, CALLDATASIZE, 0, 0)
+        // MSTORE(0, success) RETURN(0, 32)
+        let mut code = hex!("36600060003760006000366000").to_vec();
+        code.push(0x73);
+        code.extend_from_slice(CONSOLE_ADDRESS.as_slice());
+        code.extend_from_slice(&hex!("5afa60005260206000f3"));
+
+        let mut evm = inspector_builder(ConsoleLogInspector::new(), [(TARGET, code.into())])
+            .modify_tx_env(|tx| tx.data = log_string_uint("gas %d", 21000).into())
+            .build();
+        let result = evm.transact().unwrap();
+
+        assert_eq!(result.result.output().unwrap()[..], word(1));
+        // The account is loaded by `STATICCALL` to charge the access, but never touched.
+        assert!(!result.state[&CONSOLE_ADDRESS].is_touched());
+        assert_eq!(
+            evm.context.external.messages(),
+            [ConsoleMessage {
+                caller: TARGET,
+                message: "gas 21000".into()
+            }]
+        );
+    }
+}
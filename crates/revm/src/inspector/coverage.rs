@@ -0,0 +1,699 @@
+//! CoverageInspector. Records executed instructions and maps them to source lines for LCOV.
+
+use crate::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+        OpCode,
+    },
+    primitives::{db::Database, Bytecode, Bytes, Eof, B256, U256},
+    EvmContext, Inspector,
+};
+use core::fmt::{self, Write};
+use std::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+/// Number of times a `JUMPI` jumped and fell through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BranchHits {
+    /// Number of times the condition was true.
+    pub taken: u64,
+    /// Number of times the condition was false.
+    pub not_taken: u64,
+}
+
+impl BranchHits {
+    /// Returns true if the branch was executed at least once.
+    pub fn is_executed(&self) -> bool {
+        self.taken != 0 || self.not_taken != 0
+    }
+
+    fn add(&mut self, other: &BranchHits) {
+        self.taken += other.taken;
+        self.not_taken += other.not_taken;
+    }
+}
+
+/// Executed instructions of one bytecode.
+///
+/// Instructions are identified by the code section index and the program counter in that
+/// section. Legacy bytecode has a single section.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodeCoverage {
+    /// Executed bytecode.
+    pub bytecode: Bytecode,
+    /// Number of times each instruction was executed.
+    pub hits: BTreeMap<(usize, usize), u64>,
+    /// Outcomes of the executed `JUMPI` instructions.
+    pub branches: BTreeMap<(usize, usize), BranchHits>,
+}
+
+/// Inspector that records executed instructions per code hash.
+///
+/// Use [CoverageInspector::report] to map them to source lines through the solc source maps.
+/// Coverage is accumulated over all inspected transactions.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageInspector {
+    coverage: BTreeMap<B256, CodeCoverage>,
+    /// Code hash of every active frame, `None` until its interpreter is initialized.
+    frames: Vec<Option<B256>>,
+}
+
+impl CoverageInspector {
+    /// Creates a new inspector without coverage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the coverage by code hash.
+    pub fn coverage(&self) -> &BTreeMap<B256, CodeCoverage> {
+        &self.coverage
+    }
+
+    /// Clears the recorded coverage.
+    pub fn clear(&mut self) {
+        self.coverage.clear();
+    }
+
+    /// Maps the recorded coverage through the artifacts to the source files.
+    ///
+    /// Every instruction of the artifacts that has a source location is instrumented, so
+    /// artifacts that were never executed are reported with zero hits.
+    pub fn report(
+        &self,
+        artifacts: &[ContractArtifact],
+        sources: &[SourceFile],
+    ) -> Result<CoverageReport, SourceMapError> {
+        let mut report = CoverageReport::default();
+        let bytecodes = artifacts.iter().flat_map(|artifact| {
+            [
+                (artifact.bytecode.as_ref(), true),
+                (artifact.deployed_bytecode.as_ref(), false),
+            ]
+        });
+        for (artifact, is_creation) in bytecodes {
+            let Some(artifact) = artifact else { continue };
+            let source_map = parse_source_map(&artifact.source_map)?;
+            let instructions = Instructions::new(&artifact.object);
+
+            let mut hits = vec![0u64; instructions.opcodes.len()];
+            let mut branches = vec![BranchHits::default(); instructions.opcodes.len()];
+            for code in self
+                .coverage
+                .values()
+                .filter(|code| artifact.matches(&code.bytecode, is_creation))
+            {
+                for (&(section, pc), count) in &code.hits {
+                    if let Some(index) = instructions.index(section, pc) {
+                        hits[index] += count;
+                    }
+                }
+                for (&(section, pc), branch) in &code.branches {
+                    if let Some(index) = instructions.index(section, pc) {
+                        branches[index].add(branch);
+                    }
+                }
+            }
+
+            for (index, element) in source_map.iter().enumerate() {
+                let Some(&opcode) = instructions.opcodes.get(index) else {
+                    break;
+                };
+                let Some(source) = element
+                    .index
+                    .and_then(|id| sources.iter().find(|source| source.id == id))
+                else {
+                    continue;
+                };
+                let line = source.line(element.offset as usize);
+                let file = report.files.entry(source.path.clone()).or_default();
+                let line_hits = file.lines.entry(line).or_default();
+                *line_hits = (*line_hits).max(hits[index]);
+                if opcode == opcode::JUMPI {
+                    file.branches.push((line, branches[index]));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    fn current(&mut self) -> Option<&mut CodeCoverage> {
+        let hash = self.frames.last().copied().flatten()?;
+        self.coverage.get_mut(&hash)
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CoverageInspector {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let hash = code_hash(interp);
+        self.coverage.entry(hash).or_insert_with(|| CodeCoverage {
+            bytecode: interp.contract.bytecode.clone(),
+            ..Default::default()
+        });
+        if let Some(frame) = self.frames.last_mut() {
+            *frame = Some(hash);
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let section = interp.function_stack.current_code_idx;
+        let pc = interp.program_counter();
+        let opcode = interp.current_opcode();
+        let condition = interp.stack.peek(1).ok();
+        let Some(code) = self.current() else { return };
+
+        *code.hits.entry((section, pc)).or_default() += 1;
+        if let (opcode::JUMPI, Some(condition)) = (opcode, condition) {
+            let branch = code.branches.entry((section, pc)).or_default();
+            if condition != U256::ZERO {
+                branch.taken += 1;
+            } else {
+                branch.not_taken += 1;
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.frames.push(None);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frames.pop();
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frames.push(None);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frames.pop();
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frames.push(None);
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frames.pop();
+        outcome
+    }
+}
+
+/// Returns the hash of the code executed by the interpreter.
+pub(crate) fn code_hash(interp: &Interpreter) -> B256 {
+    // `CREATE` frames have a zero init code hash.
+    match interp.contract.hash {
+        Some(hash) if hash != B256::ZERO => hash,
+        _ => interp.contract.bytecode.hash_slow(),
+    }
+}
+
+/// Instructions of a bytecode, in the order of the source map entries.
+#[derive(Clone, Debug)]
+pub(crate) struct Instructions {
+    /// Index of the first instruction of every code section and the instruction index of every
+    /// byte of the section.
+    sections: Vec<(usize, Vec<usize>)>,
+    /// Opcode of every instruction.
+    pub(crate) opcodes: Vec<u8>,
+}
+
+impl Instructions {
+    pub(crate) fn new(object: &Bytes) -> Self {
+        let mut instructions = Self {
+            sections: Vec::new(),
+            opcodes: Vec::new(),
+        };
+        match Eof::decode_dangling(object.clone()) {
+            Ok((eof, _)) => {
+                for code in &eof.body.code_section {
+                    instructions.push_section(code, OpCode::eof_instruction_indices(code));
+                }
+            }
+            Err(_) => instructions.push_section(object, OpCode::legacy_instruction_indices(object)),
+        }
+        instructions
+    }
+
+    fn push_section(&mut self, code: &[u8], indices: Vec<usize>) {
+        let first = self.opcodes.len();
+        for (pc, index) in indices.iter().enumerate() {
+            if pc == 0 || indices[pc - 1] != *index {
+                self.opcodes.push(code[pc]);
+            }
+        }
+        self.sections.push((first, indices));
+    }
+
+    pub(crate) fn index(&self, section: usize, pc: usize) -> Option<usize> {
+        let (first, indices) = self.sections.get(section)?;
+        indices.get(pc).map(|index| first + index)
+    }
+}
+
+/// Jump type of a source map entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Jump {
+    /// Jump into a function.
+    In,
+    /// Return from a function.
+    Out,
+    /// Regular jump or no jump.
+    #[default]
+    Regular,
+}
+
+/// Source location of one instruction, as encoded in solc source maps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceElement {
+    /// Byte offset of the range in the source file.
+    pub offset: u32,
+    /// Length of the range in bytes.
+    pub length: u32,
+    /// Source file id, `None` for compiler generated code.
+    pub index: Option<u32>,
+    /// Jump type of the instruction.
+    pub jump: Jump,
+    /// Depth of the modifier the instruction is in.
+    pub modifier_depth: u32,
+}
+
+/// Error returned when a source map cannot be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceMapError {
+    /// Index of the invalid entry.
+    pub entry: usize,
+}
+
+impl fmt::Display for SourceMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid source map entry {}", self.entry)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SourceMapError {}
+
+/// Parses a compressed solc source map (`s:l:f:j:m` entries separated by `;`).
+///
+/// Empty fields take the value of the previous entry.
+pub fn parse_source_map(source_map: &str) -> Result<Vec<SourceElement>, SourceMapError> {
+    let mut elements = Vec::new();
+    if source_map.is_empty() {
+        return Ok(elements);
+    }
+    let mut element = SourceElement::default();
+    for (entry, fields) in source_map.split(';').enumerate() {
+        let error = SourceMapError { entry };
+        for (field, value) in fields.split(':').enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            match field {
+                0 => element.offset = value.parse().map_err(|_| error)?,
+                1 => element.length = value.parse().map_err(|_| error)?,
+                2 => {
+                    let index: i64 = value.parse().map_err(|_| error)?;
+                    element.index = u32::try_from(index).ok();
+                }
+                3 => {
+                    element.jump = match value {
+                        "i" => Jump::In,
+                        "o" => Jump::Out,
+                        "-" => Jump::Regular,
+                        _ => return Err(error),
+                    }
+                }
+                4 => element.modifier_depth = value.parse().map_err(|_| error)?,
+                _ => return Err(error),
+            }
+        }
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Byte range of an immutable reference in the deployed bytecode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImmutableReference {
+    /// Offset of the value in the bytecode.
+    pub start: usize,
+    /// Length of the value.
+    pub length: usize,
+}
+
+/// Compiled bytecode with its source map, shaped as solc `evm.bytecode` and
+/// `evm.deployedBytecode` output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ArtifactBytecode {
+    /// Compiled bytecode.
+    pub object: Bytes,
+    /// Compressed source map.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_map: String,
+    /// Ranges of the immutables, which are zero in `object` and filled at deployment, by the id
+    /// of the AST node.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub immutable_references: BTreeMap<String, Vec<ImmutableReference>>,
+}
+
+impl ArtifactBytecode {
+    /// Returns true if the executed bytecode was compiled from this artifact.
+    ///
+    /// Immutables are ignored and the creation code may be followed by constructor arguments.
+    /// EOF containers are compared by their code sections, as data is appended at deployment.
+    pub fn matches(&self, bytecode: &Bytecode, is_creation: bool) -> bool {
+        if let Bytecode::Eof(eof) = bytecode {
+            return Eof::decode_dangling(self.object.clone())
+                .is_ok_and(|(artifact, _)| artifact.body.code_section == eof.body.code_section);
+        }
+        let code = bytecode.original_byte_slice();
+        let len = self.object.len();
+        if code.len() < len || (!is_creation && code.len() != len) {
+            return false;
+        }
+        let mut masked = self.object.to_vec();
+        for reference in self.immutable_references.values().flatten() {
+            let range = reference.start..reference.start + reference.length;
+            if let (Some(masked), Some(code)) = (masked.get_mut(range.clone()), code.get(range)) {
+                masked.copy_from_slice(code);
+            }
+        }
+        masked == code[..len]
+    }
+}
+
+/// Creation and deployed bytecode of a contract, shaped as solc `evm` output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ContractArtifact {
+    /// Creation bytecode (`srcmap`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bytecode: Option<ArtifactBytecode>,
+    /// Deployed bytecode (`srcmap-runtime`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub deployed_bytecode: Option<ArtifactBytecode>,
+}
+
+/// Source file referenced by source maps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Source id assigned by solc.
+    pub id: u32,
+    /// Path of the file in the LCOV report.
+    pub path: String,
+    /// Content of the file.
+    pub content: String,
+}
+
+impl SourceFile {
+    /// Creates a new source file.
+    pub fn new(id: u32, path: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            id,
+            path: path.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Returns the 1-based line of the byte offset.
+    pub fn line(&self, offset: usize) -> u32 {
+        let end = offset.min(self.content.len());
+        1 + self.content.as_bytes()[..end]
+            .iter()
+            .filter(|byte| **byte == b'\n')
+            .count() as u32
+    }
+
+    /// Returns the content of the 1-based line.
+    pub fn line_content(&self, line: u32) -> Option<&str> {
+        self.content.lines().nth(line.checked_sub(1)? as usize)
+    }
+}
+
+/// Coverage of one source file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Hits by line, the highest hit count of the instructions on the line.
+    pub lines: BTreeMap<u32, u64>,
+    /// Outcomes of the `JUMPI` instructions and their lines.
+    pub branches: Vec<(u32, BranchHits)>,
+}
+
+/// Line and branch coverage of source files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// Coverage by source file path.
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+impl CoverageReport {
+    /// Formats the report as LCOV tracefile.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, file) in &self.files {
+            let _ = writeln!(lcov, "TN:\nSF:{path}");
+            for (line, hits) in &file.lines {
+                let _ = writeln!(lcov, "DA:{line},{hits}");
+            }
+            let hit = file.lines.values().filter(|hits| **hits != 0).count();
+            let _ = writeln!(lcov, "LF:{}\nLH:{hit}", file.lines.len());
+
+            let mut hit = 0;
+            for (block, (line, branch)) in file.branches.iter().enumerate() {
+                for (id, taken) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+                    if !branch.is_executed() {
+                        let _ = writeln!(lcov, "BRDA:{line},{block},{id},-");
+                        continue;
+                    }
+                    hit += (taken != 0) as usize;
+                    let _ = writeln!(lcov, "BRDA:{line},{block},{id},{taken}");
+                }
+            }
+            let _ = writeln!(lcov, "BRF:{}\nBRH:{hit}", file.branches.len() * 2);
+            lcov.push_str("end_of_record\n");
+        }
+        lcov
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{inspector_builder, TARGET};
+    use std::sync::Arc;
+
+    const SOURCE: &str = "aaaa\nbbbb\ncccc\n";
+
+    /// `if (calldata[0] != 0) { line 3 } else { line 2 }`
+    fn artifact() -> ArtifactBytecode {
+        ArtifactBytecode {
+            // PUSH1 0 CALLDATALOAD PUSH1 7 JUMPI STOP JUMPDEST STOP
+            object: Bytes::from_static(&[0x60, 0x00, 0x35, 0x60, 0x07, 0x57, 0x00, 0x5b, 0x00]),
+            source_map: "0:4:0:-:0;;;;5:4;10:4;".into(),
+            immutable_references: BTreeMap::new(),
+        }
+    }
+
+    fn run(inspector: &mut CoverageInspector, code: Bytes, input: Bytes) {
+        let mut evm = inspector_builder(inspector, [(TARGET, code)])
+            .modify_tx_env(|tx| tx.data = input)
+            .build();
+        evm.transact().unwrap();
+    }
+
+    #[test]
+    fn source_map() {
+        let elements = parse_source_map("1:2:0:i:1;:3;;4::-1:o;").unwrap();
+        assert_eq!(elements.len(), 5);
+        assert_eq!(
+            elements[1],
+            SourceElement {
+                offset: 1,
+                length: 3,
+                index: Some(0),
+                jump: Jump::In,
+                modifier_depth: 1,
+            }
+        );
+        assert_eq!(elements[2], elements[1]);
+        assert_eq!(elements[3].offset, 4);
+        assert_eq!(elements[3].index, None);
+        assert_eq!(elements[3].jump, Jump::Out);
+        assert_eq!(elements[4], elements[3]);
+
+        assert_eq!(parse_source_map(""), Ok(Vec::new()));
+        assert_eq!(parse_source_map("1:2;x"), Err(SourceMapError { entry: 1 }));
+    }
+
+    #[test]
+    fn lines_and_branches() {
+        let artifacts = [ContractArtifact {
+            bytecode: None,
+            deployed_bytecode: Some(artifact()),
+        }];
+        let sources = [SourceFile::new(0, "src/A.sol", SOURCE)];
+        let mut inspector = CoverageInspector::new();
+
+        run(&mut inspector, artifact().object, Bytes::new());
+        let report = inspector.report(&artifacts, &sources).unwrap();
+        let file = &report.files["src/A.sol"];
+        assert_eq!(file.lines, BTreeMap::from([(1, 1), (2, 1), (3, 0)]));
+        assert_eq!(
+            file.branches,
+            [(
+                1,
+                BranchHits {
+                    taken: 0,
+                    not_taken: 1
+                }
+            )]
+        );
+
+        run(
+            &mut inspector,
+            artifact().object,
+            Bytes::from(U256::from(1).to_be_bytes_vec()),
+        );
+        let report = inspector.report(&artifacts, &sources).unwrap();
+        assert_eq!(
+            report.to_lcov(),
+            "TN:\nSF:src/A.sol\nDA:1,2\nDA:2,1\nDA:3,1\nLF:3\nLH:3\n\
+             BRDA:1,0,0,1\nBRDA:1,0,1,1\nBRF:2\nBRH:2\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn not_executed() {
+        let artifacts = [ContractArtifact {
+            bytecode: None,
+            deployed_bytecode: Some(artifact()),
+        }];
+        let sources = [SourceFile::new(0, "src/A.sol", SOURCE)];
+        let report = CoverageInspector::new()
+            .report(&artifacts, &sources)
+            .unwrap();
+        assert_eq!(
+            report.to_lcov(),
+            "TN:\nSF:src/A.sol\nDA:1,0\nDA:2,0\nDA:3,0\nLF:3\nLH:0\n\
+             BRDA:1,0,0,-\nBRDA:1,0,1,-\nBRF:2\nBRH:0\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn immutables_and_constructor_arguments() {
+        // PUSH32 <immutable> POP STOP
+        let mut object = vec![0x7f];
+        object.extend([0; 32]);
+        object.extend([0x50, 0x00]);
+        let artifact = ArtifactBytecode {
+            object: object.clone().into(),
+            source_map: String::new(),
+            immutable_references: BTreeMap::from([(
+                "3".into(),
+                vec![ImmutableReference {
+                    start: 1,
+                    length: 32,
+                }],
+            )]),
+        };
+
+        let mut deployed = object.clone();
+        deployed[1..33].copy_from_slice(&[0x11; 32]);
+        assert!(artifact.matches(&Bytecode::new_raw(deployed.clone().into()), false));
+        deployed[33] = 0x00;
+        assert!(!artifact.matches(&Bytecode::new_raw(deployed.into()), false));
+
+        let mut creation = object;
+        creation.extend([0x22; 32]);
+        assert!(artifact.matches(&Bytecode::new_raw(creation.clone().into()), true));
+        assert!(!artifact.matches(&Bytecode::new_raw(creation.into()), false));
+    }
+
+    #[test]
+    fn eof_sections() {
+        let mut eof = Eof::default();
+        // PUSH1 1 PUSH1 2 ADD STOP, PUSH2 0x0102 RETF
+        eof.body.code_section = vec![
+            Bytes::from_static(&[0x60, 0x01, 0x60, 0x02, 0x01, 0x00]),
+            Bytes::from_static(&[0x61, 0x01, 0x02, 0xe4]),
+        ];
+        eof.body.types_section.push(Default::default());
+        let eof = eof.body.into_eof();
+
+        let instructions = Instructions::new(eof.raw());
+        assert_eq!(instructions.opcodes, [0x60, 0x60, 0x01, 0x00, 0x61, 0xe4]);
+        assert_eq!(instructions.index(0, 3), Some(1));
+        assert_eq!(instructions.index(1, 0), Some(4));
+        assert_eq!(instructions.index(1, 3), Some(5));
+
+        let artifact = ArtifactBytecode {
+            object: eof.raw().clone(),
+            ..Default::default()
+        };
+        assert!(artifact.matches(&Bytecode::Eof(Arc::new(eof)), false));
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn solc_artifact() {
+        let artifact: ContractArtifact = serde_json::from_str(
+            r#"{
+                "bytecode": { "object": "6000", "sourceMap": "0:4:0:-:0" },
+                "deployedBytecode": {
+                    "object": "7f00",
+                    "sourceMap": "0:4:0:-:0",
+                    "immutableReferences": { "3": [{ "start": 1, "length": 1 }] }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            artifact.bytecode.unwrap().object,
+            Bytes::from_static(&[0x60, 0x00])
+        );
+        let deployed = artifact.deployed_bytecode.unwrap();
+        assert_eq!(
+            deployed.immutable_references["3"],
+            [ImmutableReference {
+                start: 1,
+                length: 1
+            }]
+        );
+    }
+}
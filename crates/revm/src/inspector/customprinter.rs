@@ -5,51 +5,359 @@ use revm_interpreter::CallOutcome;
 use revm_interpreter::CreateOutcome;
 use revm_interpreter::OpCode;
 
+use super::coverage::{code_hash, Instructions};
 use crate::{
-    inspectors::GasInspector,
-    interpreter::{CallInputs, CreateInputs, Interpreter},
-    primitives::{Address, U256},
+    abi::{self, Abi, Function},
+    inspectors::{parse_source_map, ContractArtifact, GasInspector, SourceElement, SourceFile},
+    interpreter::{CallInputs, CreateInputs, EOFCreateInputs, Interpreter, InterpreterResult},
+    primitives::{Address, Bytes, RevertReason, B256, U256},
     Database, EvmContext, Inspector,
 };
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Amount of output of the [CustomPrintTracer].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PrintVerbosity {
+    /// Calls and creates with decoded arguments and results.
+    Calls,
+    /// Calls and the source line whenever it changes, requires source maps.
+    Lines,
+    /// Calls and every executed instruction.
+    #[default]
+    Steps,
+}
+
+/// Source map of the code executed by a frame.
+#[derive(Debug)]
+struct FrameSource {
+    instructions: Instructions,
+    elements: Vec<SourceElement>,
+}
+
+/// Frame that is currently executed.
+#[derive(Clone, Debug)]
+struct PrintFrame {
+    address: Address,
+    is_create: bool,
+    /// Whether the frame passes the address and depth filters.
+    visible: bool,
+    /// Function that was called, used to decode the output.
+    function: Option<Function>,
+    source: Option<Arc<FrameSource>>,
+    /// Source id and line of the last executed instruction.
+    line: Option<(u32, u32)>,
+}
 
 /// Custom print [Inspector], it has step level information of execution.
 ///
-/// It is a great tool if some debugging is needed.
+/// It is a great tool if some debugging is needed. Calls are decoded with the given ABI, and
+/// with source maps every step or call is printed with its Solidity source line.
 #[derive(Clone, Debug, Default)]
 pub struct CustomPrintTracer {
     gas_inspector: GasInspector,
+    verbosity: PrintVerbosity,
+    color: bool,
+    abi: Abi,
+    artifacts: Vec<ContractArtifact>,
+    sources: Vec<SourceFile>,
+    /// Source maps by code hash, `None` if no artifact matches the code.
+    source_maps: BTreeMap<B256, Option<Arc<FrameSource>>>,
+    addresses: Vec<Address>,
+    max_depth: Option<usize>,
+    frames: Vec<PrintFrame>,
+    /// Printed output, if it is captured instead of written to stdout.
+    output: Option<String>,
+}
+
+impl CustomPrintTracer {
+    /// Creates a new tracer that prints every step.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the amount of output.
+    pub fn with_verbosity(mut self, verbosity: PrintVerbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Enables ANSI colors.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Adds functions and errors used to decode calls, return values and reverts.
+    pub fn with_abi(mut self, abi: Abi) -> Self {
+        self.abi.extend(abi);
+        self
+    }
+
+    /// Adds compiled contracts and their sources, used to print the source line of the executed
+    /// instructions.
+    pub fn with_sources(
+        mut self,
+        artifacts: impl IntoIterator<Item = ContractArtifact>,
+        sources: impl IntoIterator<Item = SourceFile>,
+    ) -> Self {
+        self.artifacts.extend(artifacts);
+        self.sources.extend(sources);
+        self.source_maps.clear();
+        self
+    }
+
+    /// Only prints frames that execute or call one of the addresses.
+    pub fn with_address_filter(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Only prints frames up to the call depth, the transaction frame has depth zero.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Captures the output instead of printing it to stdout, see [Self::take_output].
+    pub fn with_captured_output(mut self) -> Self {
+        self.output = Some(String::new());
+        self
+    }
+
+    /// Takes the captured output.
+    pub fn take_output(&mut self) -> String {
+        self.output
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn print(&mut self, line: String) {
+        match &mut self.output {
+            Some(output) => {
+                output.push_str(&line);
+                output.push('\n');
+            }
+            None => println!("{line}"),
+        }
+    }
+
+    fn paint(&self, color: &str, text: impl core::fmt::Display) -> String {
+        if self.color {
+            format!("\x1b[{color}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn indent(&self) -> String {
+        "  ".repeat(self.frames.len().saturating_sub(1))
+    }
+
+    fn push_frame(&mut self, address: Address, is_create: bool, function: Option<Function>) {
+        let depth = self.frames.len();
+        let visible = self.max_depth.is_none_or(|max| depth <= max)
+            && (self.addresses.is_empty() || self.addresses.contains(&address));
+        self.frames.push(PrintFrame {
+            address,
+            is_create,
+            visible,
+            function,
+            source: None,
+            line: None,
+        });
+    }
+
+    fn is_visible(&self) -> bool {
+        self.frames.last().is_some_and(|frame| frame.visible)
+    }
+
+    /// Prints the source line of the last instruction of the caller.
+    fn print_call_site(&mut self) {
+        let Some(location) = self.frames.iter().rev().nth(1).and_then(|f| f.line) else {
+            return;
+        };
+        if let Some(line) = self.format_location(location) {
+            let line = format!("{}  {}", self.indent(), self.paint("2", line));
+            self.print(line);
+        }
+    }
+
+    fn format_location(&self, (id, line): (u32, u32)) -> Option<String> {
+        let source = self.sources.iter().find(|source| source.id == id)?;
+        let content = source.line_content(line).unwrap_or_default().trim();
+        Some(format!("{}:{line}: {content}", source.path))
+    }
+
+    fn source_map(&mut self, interp: &Interpreter, is_creation: bool) -> Option<Arc<FrameSource>> {
+        if self.artifacts.is_empty() {
+            return None;
+        }
+        let hash = code_hash(interp);
+        if let Some(source) = self.source_maps.get(&hash) {
+            return source.clone();
+        }
+        let bytecode = &interp.contract.bytecode;
+        let source = self
+            .artifacts
+            .iter()
+            .filter_map(|artifact| match is_creation {
+                true => artifact.bytecode.as_ref(),
+                false => artifact.deployed_bytecode.as_ref(),
+            })
+            .find(|artifact| artifact.matches(bytecode, is_creation))
+            .and_then(|artifact| {
+                Some(Arc::new(FrameSource {
+                    instructions: Instructions::new(&artifact.object),
+                    elements: parse_source_map(&artifact.source_map).ok()?,
+                }))
+            });
+        self.source_maps.insert(hash, source.clone());
+        source
+    }
+
+    /// Formats the decoded result of a frame.
+    fn format_result(&self, result: &InterpreterResult, function: Option<&Function>) -> String {
+        let output = &result.output;
+        let decoded = if result.result.is_revert() {
+            self.revert_reason(output)
+        } else if result.is_ok() {
+            function
+                .and_then(|function| function.decode_output(output).ok())
+                .filter(|values| !values.is_empty())
+                .map(|values| abi::format_call("", &values))
+        } else {
+            None
+        };
+        let detail = match decoded {
+            Some(decoded) => format!(" {decoded}"),
+            None if output.is_empty() => String::new(),
+            None => format!(" {output}"),
+        };
+        let color = if result.is_ok() { "32" } else { "31" };
+        self.paint(color, format!("← {:?}{detail}", result.result))
+    }
+
+    /// Decodes `Error(string)`, `Panic(uint256)` and custom errors of the ABI.
+    fn revert_reason(&self, output: &[u8]) -> Option<String> {
+        if let Some((error, values)) = self.abi.decode_error(output) {
+            return Some(abi::format_call(&error.name, &values));
+        }
+        match RevertReason::decode(output) {
+            RevertReason::Error(message) => Some(format!("{message:?}")),
+            reason @ RevertReason::Panic(_) => Some(reason.to_string()),
+            _ => None,
+        }
+    }
+
+    fn format_input(&self, input: &Bytes) -> String {
+        if let Some((function, values)) = self.abi.decode_call(input) {
+            return abi::format_call(&function.name, &values);
+        }
+        match input.len() {
+            0 => "()".to_string(),
+            1..=3 => format!("fallback({input})"),
+            _ => format!(
+                "{}({})",
+                Bytes::copy_from_slice(&input[..4]),
+                Bytes::copy_from_slice(&input[4..])
+            ),
+        }
+    }
+
+    fn end_frame(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let is_visible = self.is_visible();
+        if is_visible {
+            let mut line = format!(
+                "{}{}",
+                self.indent(),
+                self.format_result(result, self.frames.last().and_then(|f| f.function.as_ref()))
+            );
+            if let Some(created) = created.filter(|_| result.is_ok()) {
+                line.push_str(&format!(" created {created}"));
+            }
+            self.print(line);
+        }
+        self.frames.pop();
+    }
 }
 
 impl<DB: Database> Inspector<DB> for CustomPrintTracer {
     fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
         self.gas_inspector.initialize_interp(interp, context);
+        let is_create = self.frames.last().is_some_and(|frame| frame.is_create);
+        let source = self.source_map(interp, is_create);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.source = source;
+            frame.address = interp.contract.target_address;
+        }
     }
 
     // get opcode by calling `interp.contract.opcode(interp.program_counter())`.
     // all other information can be obtained from interp.
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
-        let opcode = interp.current_opcode();
-        let name = OpCode::name_by_op(opcode);
-
-        let gas_remaining = self.gas_inspector.gas_remaining();
-
-        let memory_size = interp.shared_memory.len();
-
-        println!(
-            "depth:{}, PC:{}, gas:{:#x}({}), OPCODE: {:?}({:?})  refund:{:#x}({}) Stack:{:?}, Data size:{}",
-            context.journaled_state.depth(),
-            interp.program_counter(),
-            gas_remaining,
-            gas_remaining,
-            name,
-            opcode,
-            interp.gas.refunded(),
-            interp.gas.refunded(),
-            interp.stack.data(),
-            memory_size,
-        );
-
         self.gas_inspector.step(interp, context);
+
+        let location = self.frames.last().and_then(|frame| {
+            let source = frame.source.as_ref()?;
+            let index = source.instructions.index(
+                interp.function_stack.current_code_idx,
+                interp.program_counter(),
+            )?;
+            let element = source.elements.get(index)?;
+            let id = element.index?;
+            let source = self.sources.iter().find(|source| source.id == id)?;
+            Some((id, source.line(element.offset as usize)))
+        });
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        let line_changed = location.is_some() && frame.line != location;
+        if location.is_some() {
+            frame.line = location;
+        }
+        if !frame.visible {
+            return;
+        }
+
+        match self.verbosity {
+            PrintVerbosity::Calls => {}
+            PrintVerbosity::Lines => {
+                if let Some(line) = location
+                    .filter(|_| line_changed)
+                    .and_then(|location| self.format_location(location))
+                {
+                    let line = format!("{}  {}", self.indent(), self.paint("2", line));
+                    self.print(line);
+                }
+            }
+            PrintVerbosity::Steps => {
+                let opcode = interp.current_opcode();
+                let name = OpCode::name_by_op(opcode);
+
+                let gas_remaining = self.gas_inspector.gas_remaining();
+
+                let memory_size = interp.shared_memory.len();
+
+                let mut line = format!(
+                    "depth:{}, PC:{}, gas:{:#x}({}), OPCODE: {:?}({:?})  refund:{:#x}({}) Stack:{:?}, Data size:{}",
+                    context.journaled_state.depth(),
+                    interp.program_counter(),
+                    gas_remaining,
+                    gas_remaining,
+                    name,
+                    opcode,
+                    interp.gas.refunded(),
+                    interp.gas.refunded(),
+                    interp.stack.data(),
+                    memory_size,
+                );
+                if let Some(location) = location.and_then(|l| self.format_location(l)) {
+                    line.push_str(&format!(" {}", self.paint("2", location)));
+                }
+                self.print(line);
+            }
+        }
     }
 
     fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
@@ -62,6 +370,7 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         inputs: &CallInputs,
         outcome: CallOutcome,
     ) -> CallOutcome {
+        self.end_frame(&outcome.result, None);
         self.gas_inspector.call_end(context, inputs, outcome)
     }
 
@@ -71,52 +380,128 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         inputs: &CreateInputs,
         outcome: CreateOutcome,
     ) -> CreateOutcome {
+        self.end_frame(&outcome.result, outcome.address);
         self.gas_inspector.create_end(context, inputs, outcome)
     }
 
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(&outcome.result, outcome.address);
+        outcome
+    }
+
     fn call(
         &mut self,
         _context: &mut EvmContext<DB>,
         inputs: &mut CallInputs,
     ) -> Option<CallOutcome> {
-        println!(
-            "SM Address: {:?}, caller:{:?},target:{:?} is_static:{:?}, transfer:{:?}, input_size:{:?}",
-            inputs.bytecode_address,
-            inputs.caller,
+        let function = inputs
+            .input
+            .get(..4)
+            .and_then(|selector| self.abi.function(selector))
+            .cloned();
+        self.push_frame(inputs.target_address, false, function);
+        if !self.is_visible() && !self.addresses.contains(&inputs.bytecode_address) {
+            return None;
+        }
+        if let Some(frame) = self.frames.last_mut() {
+            frame.visible = true;
+        }
+
+        let mut line = format!(
+            "{}{} {}::{}",
+            self.indent(),
+            self.paint("1;36", format!("{:?}", inputs.scheme).to_uppercase()),
             inputs.target_address,
-            inputs.is_static,
-            inputs.value,
-            inputs.input.len(),
+            self.format_input(&inputs.input),
         );
+        if inputs.bytecode_address != inputs.target_address {
+            line.push_str(&format!(" code: {}", inputs.bytecode_address));
+        }
+        if inputs.transfers_value() {
+            line.push_str(&format!(" value: {}", inputs.call_value()));
+        }
+        line.push_str(&format!(" gas: {}", inputs.gas_limit));
+        self.print(line);
+        self.print_call_site();
         None
     }
 
     fn create(
         &mut self,
-        _context: &mut EvmContext<DB>,
+        context: &mut EvmContext<DB>,
         inputs: &mut CreateInputs,
     ) -> Option<CreateOutcome> {
-        println!(
-            "CREATE CALL: caller:{:?}, scheme:{:?}, value:{:?}, init_code:{:?}, gas:{:?}",
-            inputs.caller, inputs.scheme, inputs.value, inputs.init_code, inputs.gas_limit
+        let nonce = context
+            .journaled_state
+            .state
+            .get(&inputs.caller)
+            .map(|account| account.info.nonce)
+            .unwrap_or_default();
+        let address = inputs.created_address(nonce);
+        self.push_frame(address, true, None);
+        if !self.is_visible() {
+            return None;
+        }
+
+        let mut line = format!(
+            "{}{} {address} init code: {} bytes",
+            self.indent(),
+            self.paint("1;36", format!("{:?}", inputs.scheme).to_uppercase()),
+            inputs.init_code.len(),
         );
+        if inputs.value != U256::ZERO {
+            line.push_str(&format!(" value: {}", inputs.value));
+        }
+        line.push_str(&format!(" gas: {}", inputs.gas_limit));
+        self.print(line);
+        self.print_call_site();
+        None
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        let address = inputs.kind.created_address().copied().unwrap_or_default();
+        self.push_frame(address, true, None);
+        if self.is_visible() {
+            let line = format!(
+                "{}{} {address} gas: {}",
+                self.indent(),
+                self.paint("1;36", "EOFCREATE"),
+                inputs.gas_limit
+            );
+            self.print(line);
+        }
         None
     }
 
     fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
-        println!(
+        if !self.is_visible() {
+            return;
+        }
+        self.print(format!(
             "SELFDESTRUCT: contract: {:?}, refund target: {:?}, value {:?}",
             contract, target, value
-        );
+        ));
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::PrintVerbosity;
     use crate::{
+        abi::Abi,
         inspector_handle_register,
-        inspectors::CustomPrintTracer,
-        primitives::{address, bytes, SpecId},
+        inspectors::{ArtifactBytecode, ContractArtifact, CustomPrintTracer, SourceFile},
+        primitives::{address, bytes, Bytes, SpecId},
+        test_fixtures::{inspector_builder, Code, CHILD, TARGET},
         Evm, InMemoryDB,
     };
 
@@ -151,4 +536,99 @@ mod test {
 
         evm.transact().expect("Transaction to work");
     }
+
+    /// Forwards the calldata of the transaction to the child.
+    fn caller_code() -> Bytes {
+        // CALLDATACOPY(0, 0, CALLDATASIZE) CALL(GAS, CHILD, 0, 0, CALLDATASIZE, 0, 0) STOP
+        Code::new()
+            .bytes(&bytes!("366000600037600060003660006000"))
+            .push_address(CHILD)
+            .bytes(&bytes!("5af100"))
+            .build()
+    }
+
+    /// Reverts with `Error(string)` "no".
+    fn child_code() -> Bytes {
+        bytes!("6308c379a060e01b60005260206004526002602452616e6f60f01b60445260646000fd")
+    }
+
+    fn run(tracer: CustomPrintTracer, input: Bytes) -> String {
+        let contracts = [(TARGET, caller_code()), (CHILD, child_code())];
+        let mut evm = inspector_builder(tracer.with_captured_output(), contracts)
+            .modify_tx_env(|tx| tx.data = input)
+            .build();
+        evm.transact().unwrap();
+        evm.context.external.take_output()
+    }
+
+    #[test]
+    fn decoded_calls() {
+        let abi = Abi::parse(["function fail(uint256 value)", "function run()"]).unwrap();
+        let mut input = abi.functions()[0].selector().to_vec();
+        input.extend_from_slice(&[0; 31]);
+        input.push(7);
+
+        let tracer = CustomPrintTracer::new()
+            .with_verbosity(PrintVerbosity::Calls)
+            .with_abi(abi);
+        assert_eq!(
+            run(tracer, input.into()),
+            format!(
+                "CALL {TARGET}::fail(7) gas: 978796\n  CALL {CHILD}::fail(7) gas: 960902\n  \
+                 ← Revert \"no\"\n← Stop\n"
+            )
+        );
+    }
+
+    #[test]
+    fn filters_and_color() {
+        let tracer = CustomPrintTracer::new()
+            .with_verbosity(PrintVerbosity::Calls)
+            .with_address_filter([CHILD]);
+        let output = run(tracer, Bytes::new());
+        assert_eq!(
+            output,
+            format!("  CALL {CHILD}::() gas: 961115\n  ← Revert \"no\"\n")
+        );
+
+        let tracer = CustomPrintTracer::new()
+            .with_verbosity(PrintVerbosity::Calls)
+            .with_max_depth(0)
+            .with_color(true);
+        let output = run(tracer, Bytes::new());
+        assert_eq!(
+            output,
+            format!("\x1b[1;36mCALL\x1b[0m {TARGET}::() gas: 979000\n\x1b[32m← Stop\x1b[0m\n")
+        );
+    }
+
+    #[test]
+    fn source_lines() {
+        let source = SourceFile::new(
+            0,
+            "src/Child.sol",
+            "contract Child {\n  function f() {\n    revert(\"no\");\n  }\n}\n",
+        );
+        // The first instruction maps to line 2, all other instructions to line 3.
+        let artifact = ContractArtifact {
+            bytecode: None,
+            deployed_bytecode: Some(ArtifactBytecode {
+                object: child_code(),
+                source_map: "19:30:0:-:0;34:12".into(),
+                ..Default::default()
+            }),
+        };
+        let tracer = CustomPrintTracer::new()
+            .with_verbosity(PrintVerbosity::Lines)
+            .with_sources([artifact], [source]);
+        let output = run(tracer, Bytes::new());
+        assert_eq!(
+            output,
+            format!(
+                "CALL {TARGET}::() gas: 979000\n  CALL {CHILD}::() gas: 961115\n    \
+                 src/Child.sol:2: function f() {{\n    src/Child.sol:3: revert(\"no\");\n  \
+                 ← Revert \"no\"\n← Stop\n"
+            )
+        );
+    }
 }
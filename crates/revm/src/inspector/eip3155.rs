@@ -1,14 +1,14 @@
 use crate::{
-    inspectors::GasInspector,
+    inspectors::{GasInspector, StructLog},
     interpreter::{
         CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, InterpreterResult,
     },
-    primitives::{db::Database, hex, HashMap, B256, U256},
+    primitives::{db::Database, hex, Bytes, HashMap, B256, U256},
     EvmContext, Inspector,
 };
 use revm_interpreter::OpCode;
-use serde::Serialize;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::{fmt, io::Write};
 
 /// [EIP-3155](https://eips.ethereum.org/EIPS/eip-3155) tracer [Inspector].
 pub struct TracerEip3155 {
@@ -31,47 +31,157 @@ pub struct TracerEip3155 {
 
 // # Output
 // The CUT MUST output a `json` object for EACH operation.
-#[derive(Serialize)]
+/// EIP-3155 line of an executed operation.
+///
+/// Can be converted from and to the geth [StructLog].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct Output {
+pub struct Eip3155Step {
     // Required fields:
     /// Program counter
-    pc: u64,
+    pub pc: u64,
     /// OpCode
-    op: u8,
+    pub op: u8,
     /// Gas left before executing this operation
-    gas: String,
+    pub gas: String,
     /// Gas cost of this operation
-    gas_cost: String,
+    pub gas_cost: String,
     /// Array of all values on the stack
-    stack: Vec<String>,
+    pub stack: Vec<String>,
     /// Depth of the call stack
-    depth: u64,
+    pub depth: u64,
     /// Data returned by the function call
-    return_data: String,
+    pub return_data: String,
     /// Amount of **global** gas refunded
-    refund: String,
+    pub refund: String,
     /// Size of memory array
-    mem_size: String,
+    pub mem_size: String,
 
     // Optional fields:
     /// Name of the operation
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    op_name: Option<&'static str>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_op_name"
+    )]
+    pub op_name: OpName,
     /// Description of an error (should contain revert reason if supported)
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+    pub error: Option<String>,
     /// Array of all allocated values
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    memory: Option<String>,
+    pub memory: Option<String>,
     /// Array of all stored values
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    storage: Option<HashMap<String, String>>,
+    pub storage: Option<HashMap<String, String>>,
     /// Array of values, Stack of the called function
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    return_stack: Option<Vec<String>>,
+    pub return_stack: Option<Vec<String>>,
+}
+
+impl From<&StructLog> for Eip3155Step {
+    /// Memory size is known only if the memory was logged, it is zero otherwise.
+    fn from(log: &StructLog) -> Self {
+        let memory = log.memory.as_ref().map(|words| words.concat());
+        Self {
+            pc: log.pc,
+            op: log.op,
+            gas: hex_number(log.gas),
+            gas_cost: hex_number(log.gas_cost),
+            stack: log.stack.iter().flatten().map(hex_number_u256).collect(),
+            depth: log.depth,
+            return_data: log.return_data.clone().unwrap_or_default().to_string(),
+            refund: hex_number(log.refund),
+            mem_size: memory.as_ref().map_or(0, Vec::len).to_string(),
+            op_name: OpCode::new(log.op).map(OpCode::as_str),
+            error: log.error.clone(),
+            memory: memory.map(hex::encode_prefixed),
+            storage: log.storage.as_ref().map(|storage| {
+                storage
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect()
+            }),
+            return_stack: None,
+        }
+    }
+}
+
+impl TryFrom<&Eip3155Step> for StructLog {
+    type Error = Eip3155StepError;
+
+    fn try_from(step: &Eip3155Step) -> Result<Self, Self::Error> {
+        let err = |field| Eip3155StepError { field };
+        let quantity = |value: &str, field| {
+            value
+                .strip_prefix("0x")
+                .and_then(|value| u64::from_str_radix(value, 16).ok())
+                .ok_or(err(field))
+        };
+        let return_data = hex::decode(&step.return_data).map_err(|_| err("returnData"))?;
+        Ok(Self {
+            pc: step.pc,
+            op: step.op,
+            gas: quantity(&step.gas, "gas")?,
+            gas_cost: quantity(&step.gas_cost, "gasCost")?,
+            depth: step.depth,
+            error: step.error.clone(),
+            stack: Some(
+                step.stack
+                    .iter()
+                    .map(|value| value.parse().map_err(|_| err("stack")))
+                    .collect::<Result<_, _>>()?,
+            ),
+            return_data: (!return_data.is_empty()).then(|| Bytes::from(return_data)),
+            memory: step
+                .memory
+                .as_ref()
+                .map(|memory| {
+                    let memory = hex::decode(memory).map_err(|_| err("memory"))?;
+                    Ok(memory.chunks(32).map(B256::right_padding_from).collect())
+                })
+                .transpose()?,
+            storage: step
+                .storage
+                .as_ref()
+                .map(|storage| {
+                    storage
+                        .iter()
+                        .map(|(key, value)| {
+                            Ok((
+                                key.parse().map_err(|_| err("storage"))?,
+                                value.parse().map_err(|_| err("storage"))?,
+                            ))
+                        })
+                        .collect()
+                })
+                .transpose()?,
+            refund: quantity(&step.refund, "refund")?,
+        })
+    }
+}
+
+/// Name of an operation.
+///
+/// The alias keeps serde from borrowing the name from the input, it is deserialized with
+/// [deserialize_op_name] instead.
+type OpName = Option<&'static str>;
+
+/// Error of the conversion of an [Eip3155Step] to a [StructLog].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Eip3155StepError {
+    /// Name of the field that could not be parsed.
+    pub field: &'static str,
+}
+
+impl fmt::Display for Eip3155StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid EIP-3155 `{}` field", self.field)
+    }
 }
 
+impl std::error::Error for Eip3155StepError {}
+
 // # Summary and error handling
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -211,7 +321,7 @@ impl<DB: Database> Inspector<DB> for TracerEip3155 {
             return;
         }
 
-        let value = Output {
+        let value = Eip3155Step {
             pc: self.pc as u64,
             op: self.opcode,
             gas: hex_number(self.gas),
@@ -222,7 +332,7 @@ impl<DB: Database> Inspector<DB> for TracerEip3155 {
             refund: hex_number(self.refunded as u64),
             mem_size: self.mem_size.to_string(),
 
-            op_name: OpCode::new(self.opcode).map(|i| i.as_str()),
+            op_name: OpCode::new(self.opcode).map(OpCode::as_str),
             error: if !interp.instruction_result.is_ok() {
                 Some(format!("{:?}", interp.instruction_result))
             } else {
@@ -271,6 +381,19 @@ impl<DB: Database> Inspector<DB> for TracerEip3155 {
     }
 }
 
+/// Deserializes the name of an operation, names of unknown operations are ignored.
+fn deserialize_op_name<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<&'static str>, D::Error> {
+    let name = Option::<String>::deserialize(deserializer)?;
+    Ok(name.and_then(|name| {
+        (0..=u8::MAX)
+            .filter_map(OpCode::new)
+            .map(OpCode::as_str)
+            .find(|op_name| *op_name == name)
+    }))
+}
+
 fn hex_number(uint: u64) -> String {
     format!("0x{uint:x}")
 }
@@ -284,3 +407,55 @@ fn hex_number_u256(b: &U256) -> String {
         format!("0x{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(op: u8) -> Eip3155Step {
+        Eip3155Step {
+            pc: 2,
+            op,
+            gas: hex_number(100),
+            gas_cost: hex_number(3),
+            stack: vec![hex_number_u256(&U256::from(1))],
+            depth: 1,
+            return_data: "0x".to_string(),
+            refund: hex_number(0),
+            mem_size: "0".to_string(),
+            op_name: OpCode::new(op).map(OpCode::as_str),
+            error: None,
+            memory: None,
+            storage: None,
+            return_stack: None,
+        }
+    }
+
+    #[test]
+    fn op_name_serialization() {
+        let json = serde_json::to_string(&step(0x01)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pc":2,"op":1,"gas":"0x64","gasCost":"0x3","stack":["0x1"],"depth":1,"returnData":"0x","refund":"0x0","memSize":"0","opName":"ADD"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Eip3155Step>(&json).unwrap(),
+            step(0x01)
+        );
+
+        // Undefined opcodes have no name.
+        let json = serde_json::to_string(&step(0x0c)).unwrap();
+        assert!(!json.contains("opName"));
+        assert_eq!(
+            serde_json::from_str::<Eip3155Step>(&json).unwrap(),
+            step(0x0c)
+        );
+
+        // Unknown names are ignored.
+        let json = json.replace(r#""memSize":"0""#, r#""memSize":"0","opName":"FOO""#);
+        assert_eq!(
+            serde_json::from_str::<Eip3155Step>(&json).unwrap().op_name,
+            None
+        );
+    }
+}
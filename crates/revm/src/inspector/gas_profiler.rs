@@ -0,0 +1,505 @@
+//! GasProfiler. Aggregates gas and executed instructions per opcode, contract, call path and PC.
+
+use crate::{
+    interpreter::{
+        gas::CALL_STIPEND, CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs,
+        Gas, Interpreter, InterpreterAction, OpCode,
+    },
+    primitives::{db::Database, Address},
+    EvmContext, Inspector,
+};
+use core::fmt::Write;
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Gas and number of executed instructions accumulated for one profile key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProfileEntry {
+    /// Number of executed instructions.
+    pub count: u64,
+    /// Gas spent.
+    pub gas: u64,
+}
+
+impl ProfileEntry {
+    #[inline]
+    fn add(&mut self, count: u64, gas: u64) {
+        self.count += count;
+        self.gas += gas;
+    }
+}
+
+/// Aggregation that [GasProfiler::rows] and [GasProfiler::table] report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileGroup {
+    /// Gas of the instructions, grouped by opcode.
+    Opcode,
+    /// Gas of the frames, grouped by the address of the executed code.
+    Contract,
+    /// Gas of the frames, grouped by the call path, as in [GasProfiler::folded_stacks].
+    CallPath,
+    /// Gas of the instructions, grouped by the address of the executed code and program counter.
+    Pc,
+}
+
+/// Order of the rows returned by [GasProfiler::rows].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProfileSort {
+    /// Most gas first.
+    #[default]
+    Gas,
+    /// Most executed instructions first.
+    Count,
+    /// Ascending by label.
+    Label,
+}
+
+/// Row of the profile table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileRow {
+    /// Opcode name, address, call path or `address:pc`, depending on the [ProfileGroup].
+    pub label: String,
+    /// Number of executed instructions.
+    pub count: u64,
+    /// Gas spent.
+    pub gas: u64,
+}
+
+/// Frame that is currently executed.
+#[derive(Debug)]
+struct ProfileFrame {
+    /// Address of the executed code, known only once the interpreter is initialized for creates.
+    address: Option<Address>,
+    /// Function selector of the call.
+    selector: Option<[u8; 4]>,
+    gas_limit: u64,
+    /// Gas used by the child frames.
+    children_gas: u64,
+    /// Number of instructions executed in this frame.
+    count: u64,
+}
+
+impl ProfileFrame {
+    fn new(address: Option<Address>, input: &[u8], gas_limit: u64) -> Self {
+        Self {
+            address,
+            selector: input.get(..4).map(|s| s.try_into().unwrap()),
+            gas_limit,
+            children_gas: 0,
+            count: 0,
+        }
+    }
+
+    fn label(&self) -> String {
+        let address = self.address.unwrap_or_default();
+        match self.selector {
+            Some(selector) => format!("{address}:0x{}", crate::primitives::hex::encode(selector)),
+            None => address.to_string(),
+        }
+    }
+}
+
+/// Inspector that profiles gas usage.
+///
+/// Instruction gas is aggregated per opcode and per program counter. Gas forwarded to a child
+/// frame is not part of the cost of the `CALL`/`CREATE` instruction.
+///
+/// Frame gas is aggregated per contract and per call path. It is the gas used by the frame minus
+/// the gas used by its children, and includes charges that are not made by an instruction, like
+/// precompile execution or the code deposit of a create.
+///
+/// Profiles are accumulated over all inspected transactions until [GasProfiler::clear] is called.
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+    opcodes: BTreeMap<u8, ProfileEntry>,
+    contracts: BTreeMap<Address, ProfileEntry>,
+    call_paths: BTreeMap<String, ProfileEntry>,
+    pcs: BTreeMap<(Address, usize), ProfileEntry>,
+    frames: Vec<ProfileFrame>,
+    /// Opcode, program counter and remaining gas before the current instruction.
+    current: (u8, usize, u64),
+}
+
+impl GasProfiler {
+    /// Creates a new empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Instruction gas grouped by opcode.
+    pub fn opcodes(&self) -> &BTreeMap<u8, ProfileEntry> {
+        &self.opcodes
+    }
+
+    /// Frame gas grouped by the address of the executed code.
+    pub fn contracts(&self) -> &BTreeMap<Address, ProfileEntry> {
+        &self.contracts
+    }
+
+    /// Frame gas grouped by call path, see [GasProfiler::folded_stacks].
+    pub fn call_paths(&self) -> &BTreeMap<String, ProfileEntry> {
+        &self.call_paths
+    }
+
+    /// Instruction gas grouped by the address of the executed code and program counter.
+    pub fn pcs(&self) -> &BTreeMap<(Address, usize), ProfileEntry> {
+        &self.pcs
+    }
+
+    /// Total gas used by the profiled frames.
+    pub fn total_gas(&self) -> u64 {
+        self.call_paths.values().map(|entry| entry.gas).sum()
+    }
+
+    /// Clears the profile.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the rows of the given aggregation in the given order.
+    pub fn rows(&self, group: ProfileGroup, sort: ProfileSort) -> Vec<ProfileRow> {
+        let row = |label: String, entry: &ProfileEntry| ProfileRow {
+            label,
+            count: entry.count,
+            gas: entry.gas,
+        };
+        let mut rows: Vec<_> = match group {
+            ProfileGroup::Opcode => self
+                .opcodes
+                .iter()
+                .map(|(op, entry)| row(opcode_name(*op), entry))
+                .collect(),
+            ProfileGroup::Contract => self
+                .contracts
+                .iter()
+                .map(|(address, entry)| row(address.to_string(), entry))
+                .collect(),
+            ProfileGroup::CallPath => self
+                .call_paths
+                .iter()
+                .map(|(path, entry)| row(path.clone(), entry))
+                .collect(),
+            ProfileGroup::Pc => self
+                .pcs
+                .iter()
+                .map(|((address, pc), entry)| row(format!("{address}:{pc}"), entry))
+                .collect(),
+        };
+        match sort {
+            ProfileSort::Gas => rows.sort_by(|a, b| b.gas.cmp(&a.gas).then(a.label.cmp(&b.label))),
+            ProfileSort::Count => {
+                rows.sort_by(|a, b| b.count.cmp(&a.count).then(a.label.cmp(&b.label)))
+            }
+            ProfileSort::Label => rows.sort_by(|a, b| a.label.cmp(&b.label)),
+        }
+        rows
+    }
+
+    /// Formats the rows of the given aggregation as a plain text table with the share of the
+    /// total gas of each row.
+    pub fn table(&self, group: ProfileGroup, sort: ProfileSort) -> String {
+        let rows = self.rows(group, sort);
+        let total: u64 = rows.iter().map(|row| row.gas).sum();
+        let width = rows
+            .iter()
+            .map(|row| row.label.len())
+            .chain(core::iter::once(5))
+            .max()
+            .unwrap_or_default();
+
+        let mut table = String::new();
+        let _ = writeln!(
+            table,
+            "{:<width$} {:>12} {:>14} {:>7}",
+            "label", "count", "gas", "gas %"
+        );
+        for row in rows {
+            let share = if total == 0 {
+                0.0
+            } else {
+                row.gas as f64 * 100.0 / total as f64
+            };
+            let _ = writeln!(
+                table,
+                "{:<width$} {:>12} {:>14} {:>7.2}",
+                row.label, row.count, row.gas, share
+            );
+        }
+        table
+    }
+
+    /// Returns the frame gas in the folded stack format used by flamegraph tools.
+    ///
+    /// Every line is a call path of `address` or `address:selector` frames separated by `;`,
+    /// followed by the gas spent in the last frame of the path.
+    pub fn folded_stacks(&self) -> String {
+        let mut folded = String::new();
+        for (path, entry) in &self.call_paths {
+            let _ = writeln!(folded, "{path} {}", entry.gas);
+        }
+        folded
+    }
+
+    fn start_frame(&mut self, address: Option<Address>, input: &[u8], gas_limit: u64) {
+        self.frames
+            .push(ProfileFrame::new(address, input, gas_limit));
+    }
+
+    fn end_frame(&mut self, gas: &Gas, created: Option<Address>) {
+        let Some(mut frame) = self.frames.pop() else {
+            return;
+        };
+        if frame.address.is_none() {
+            frame.address = created;
+        }
+        let used = frame.gas_limit.saturating_sub(gas.remaining());
+        let self_gas = used.saturating_sub(frame.children_gas);
+
+        let mut path = String::new();
+        for parent in &self.frames {
+            path.push_str(&parent.label());
+            path.push(';');
+        }
+        path.push_str(&frame.label());
+
+        self.call_paths
+            .entry(path)
+            .or_default()
+            .add(frame.count, self_gas);
+        self.contracts
+            .entry(frame.address.unwrap_or_default())
+            .or_default()
+            .add(frame.count, self_gas);
+        if let Some(parent) = self.frames.last_mut() {
+            parent.children_gas += used;
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasProfiler {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.address.get_or_insert(interp.contract.target_address);
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        self.current = (
+            interp.current_opcode(),
+            interp.program_counter(),
+            interp.gas.remaining(),
+        );
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let (op, pc, gas_before) = self.current;
+        let mut cost = gas_before.saturating_sub(interp.gas.remaining());
+        // Gas forwarded to the child frame is accounted to the child.
+        let forwarded = match &interp.next_action {
+            InterpreterAction::Call { inputs } if inputs.transfers_value() => {
+                inputs.gas_limit - CALL_STIPEND
+            }
+            InterpreterAction::Call { inputs } => inputs.gas_limit,
+            InterpreterAction::Create { inputs } => inputs.gas_limit,
+            InterpreterAction::EOFCreate { inputs } => inputs.gas_limit,
+            _ => 0,
+        };
+        cost = cost.saturating_sub(forwarded);
+
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        frame.count += 1;
+        let address = frame.address.unwrap_or(interp.contract.target_address);
+        self.opcodes.entry(op).or_default().add(1, cost);
+        self.pcs.entry((address, pc)).or_default().add(1, cost);
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.start_frame(
+            Some(inputs.bytecode_address),
+            &inputs.input,
+            inputs.gas_limit,
+        );
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.end_frame(&outcome.result.gas, None);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.start_frame(None, &[], inputs.gas_limit);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(&outcome.result.gas, outcome.address);
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.start_frame(None, &[], inputs.gas_limit);
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(&outcome.result.gas, outcome.address);
+        outcome
+    }
+}
+
+/// Name of the opcode or its hex value if it is not defined.
+fn opcode_name(op: u8) -> String {
+    match OpCode::new(op) {
+        Some(op) => op.as_str().to_string(),
+        None => format!("{op:#04x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interpreter::opcode,
+        primitives::Bytes,
+        test_fixtures::{inspector_builder, Code, CHILD, TARGET},
+    };
+
+    fn profile(code: Code, input: Bytes) -> (GasProfiler, u64) {
+        // SSTORE(0, 1) STOP
+        let child = Code::new().push(1).push(0).op(0x55).op(0x00);
+        let mut evm = inspector_builder(
+            GasProfiler::new(),
+            [(TARGET, code.build()), (CHILD, child.build())],
+        )
+        .modify_tx_env(|tx| tx.data = input)
+        .build();
+        let result = evm.transact().unwrap().result;
+        let gas_used = result.gas_used();
+        (evm.into_context().external, gas_used)
+    }
+
+    /// Calls the child with the given value, then stops.
+    fn caller_code(value: u8) -> Code {
+        Code::new().call(CHILD, value).op(0x00)
+    }
+
+    #[test]
+    fn per_opcode_and_pc() {
+        let (profiler, _) = profile(caller_code(0), Bytes::new());
+
+        let sstore = profiler.opcodes()[&opcode::SSTORE];
+        assert_eq!(
+            sstore,
+            ProfileEntry {
+                count: 1,
+                gas: 22_100
+            }
+        );
+        // Cold account access, the forwarded gas is accounted to the child.
+        let call = profiler.opcodes()[&opcode::CALL];
+        assert_eq!(
+            call,
+            ProfileEntry {
+                count: 1,
+                gas: 2_600
+            }
+        );
+        assert_eq!(profiler.opcodes()[&opcode::PUSH1].count, 7);
+
+        assert_eq!(profiler.pcs()[&(CHILD, 4)], sstore);
+        assert_eq!(profiler.pcs()[&(TARGET, 32)], call);
+    }
+
+    #[test]
+    fn value_transfer_call_cost() {
+        let (profiler, _) = profile(caller_code(1), Bytes::new());
+        // Cold account access and value transfer, the stipend is not paid by the caller.
+        assert_eq!(profiler.opcodes()[&opcode::CALL].gas, 2_600 + 9_000);
+    }
+
+    #[test]
+    fn per_contract_and_call_path() {
+        let (profiler, gas_used) = profile(caller_code(0), Bytes::from_static(&[1, 2, 3, 4, 5]));
+
+        let child = profiler.contracts()[&CHILD];
+        assert_eq!(
+            child,
+            ProfileEntry {
+                count: 4,
+                gas: 22_106
+            }
+        );
+        assert_eq!(profiler.contracts()[&TARGET].count, 9);
+
+        // Intrinsic gas is not part of any frame.
+        let intrinsic = 21_000 + 5 * 16;
+        assert_eq!(profiler.total_gas(), gas_used - intrinsic);
+
+        let root = format!("{TARGET}:0x01020304");
+        assert_eq!(
+            profiler.folded_stacks(),
+            format!(
+                "{root} {}\n{root};{CHILD} {}\n",
+                profiler.contracts()[&TARGET].gas,
+                child.gas
+            )
+        );
+    }
+
+    #[test]
+    fn table() {
+        let (profiler, _) = profile(caller_code(0), Bytes::new());
+
+        let rows = profiler.rows(ProfileGroup::Opcode, ProfileSort::Gas);
+        assert_eq!(rows[0].label, "SSTORE");
+        assert_eq!(rows[1].label, "CALL");
+        let rows = profiler.rows(ProfileGroup::Opcode, ProfileSort::Count);
+        assert_eq!(rows[0].label, "PUSH1");
+        let rows = profiler.rows(ProfileGroup::Contract, ProfileSort::Label);
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].label < rows[1].label);
+
+        let table = profiler.table(ProfileGroup::Opcode, ProfileSort::Gas);
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("label "));
+        let sstore = lines.next().unwrap();
+        assert!(sstore.starts_with("SSTORE "));
+        assert!(sstore.contains(" 22100 "));
+        assert_eq!(table.lines().count(), profiler.opcodes().len() + 1);
+    }
+}
@@ -64,19 +64,42 @@ pub fn inspector_handle_register<DB: Database, EXT: GetInspector<DB>>(
 
     // Register selfdestruct function.
     table.update_boxed(opcode::SELFDESTRUCT, |prev, interpreter, host| {
+        let journal = &host.evm.journaled_state.journal;
+        let prev_journal_len = journal.len();
+        let prev_entries_len = journal.last().map_or(0, Vec::len);
         // execute selfdestruct
         prev(interpreter, host);
-        // check if selfdestruct was successful and if journal entry is made.
-        if let Some(JournalEntry::AccountDestroyed {
-            address,
-            target,
-            had_balance,
-            ..
-        }) = host.evm.journaled_state.journal.last().unwrap().last()
-        {
-            host.external
-                .get_inspector()
-                .selfdestruct(*address, *target, *had_balance);
+        // check if selfdestruct was successful and if journal entry is made. Entry set of the
+        // frame can end with entries of a committed call, so only appended entries are checked.
+        if interpreter.instruction_result != InstructionResult::SelfDestruct {
+            return;
+        }
+        let journal = &host.evm.journaled_state.journal;
+        if journal.len() != prev_journal_len {
+            return;
+        }
+        let entry = journal
+            .last()
+            .filter(|entries| entries.len() > prev_entries_len)
+            .and_then(|entries| entries.last());
+        match entry {
+            Some(JournalEntry::AccountDestroyed {
+                address,
+                target,
+                had_balance,
+                ..
+            }) => {
+                host.external
+                    .get_inspector()
+                    .selfdestruct(*address, *target, *had_balance);
+            }
+            // After Cancun, only the balance of an existing contract is sent to the target.
+            Some(JournalEntry::BalanceTransfer { from, to, balance }) => {
+                host.external
+                    .get_inspector()
+                    .selfdestruct(*from, *to, *balance);
+            }
+            _ => {}
         }
     });
 
@@ -255,6 +278,7 @@ mod tests {
     use crate::{
         inspectors::NoOpInspector,
         interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome},
+        primitives::{Address, U256},
         Evm, EvmContext,
     };
 
@@ -388,4 +412,76 @@ mod tests {
             .append_handler_register(inspector_handle_register)
             .build();
     }
+
+    #[derive(Default, Debug)]
+    struct SelfdestructInspector {
+        selfdestructs: Vec<(Address, Address, U256)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for SelfdestructInspector {
+        fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+            self.selfdestructs.push((contract, target, value));
+        }
+    }
+
+    #[test]
+    fn test_selfdestruct_after_committed_call() {
+        use crate::{
+            interpreter::opcode,
+            primitives::{AccountInfo, Bytecode, Bytes, SpecId, TxKind},
+            InMemoryDB,
+        };
+
+        let contract = Address::repeat_byte(0xc0);
+        let eoa = Address::repeat_byte(0xe0);
+        let selfdestruct_to = |target: Address| {
+            let mut code = vec![opcode::PUSH20];
+            code.extend_from_slice(target.as_slice());
+            code.push(opcode::SELFDESTRUCT);
+            code
+        };
+        // CALL(GAS, eoa, 3, 0, 0, 0, 0) POP
+        let mut call = vec![
+            opcode::PUSH0,
+            opcode::PUSH0,
+            opcode::PUSH0,
+            opcode::PUSH0,
+            opcode::PUSH1,
+            3,
+            opcode::PUSH20,
+        ];
+        call.extend_from_slice(eoa.as_slice());
+        call.extend_from_slice(&[opcode::GAS, opcode::CALL, opcode::POP]);
+
+        let run = |code: Vec<u8>| {
+            let mut evm = Evm::builder()
+                .with_db(InMemoryDB::default())
+                .modify_db(|db| {
+                    db.insert_account_info(
+                        contract,
+                        AccountInfo {
+                            balance: U256::from(10),
+                            ..AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from(code)))
+                        },
+                    )
+                })
+                .with_spec_id(SpecId::CANCUN)
+                .modify_tx_env(|tx| {
+                    tx.transact_to = TxKind::Call(contract);
+                    tx.gas_limit = 100_000;
+                })
+                .with_external_context(SelfdestructInspector::default())
+                .append_handler_register(inspector_handle_register)
+                .build();
+            assert!(evm.transact().unwrap().result.is_success());
+            evm.into_context().external.selfdestructs
+        };
+
+        // Transfer of the call is not reported as selfdestruct to the contract itself.
+        assert_eq!(run([call.clone(), selfdestruct_to(contract)].concat()), []);
+        assert_eq!(
+            run([call, selfdestruct_to(eoa)].concat()),
+            [(contract, eoa, U256::from(7))]
+        );
+    }
 }
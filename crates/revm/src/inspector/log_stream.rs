@@ -0,0 +1,418 @@
+//! LogStreamInspector. Filters, decodes and forwards the logs emitted during execution.
+
+use crate::{
+    abi::{Abi, AbiValue},
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+    },
+    primitives::{db::Database, Address, Log, B256},
+    EvmContext, Inspector,
+};
+use core::fmt;
+use std::{boxed::Box, string::String, vec::Vec};
+
+/// Log decoded with the event of an ABI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedEvent {
+    /// Name of the event.
+    pub name: String,
+    /// Names of the parameters with the decoded arguments.
+    pub params: Vec<(String, AbiValue)>,
+}
+
+impl fmt::Display for DecodedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, (name, value)) in self.params.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            if !name.is_empty() {
+                write!(f, "{name}: ")?;
+            }
+            write!(f, "{value}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Log that matched the filter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamedLog {
+    /// Emitted log.
+    pub log: Log,
+    /// Decoded event, `None` if no event of the ABIs matches the log.
+    pub event: Option<DecodedEvent>,
+    /// Index of the transaction among the transactions executed with the inspector.
+    pub tx_index: usize,
+    /// Call depth of the emitting frame, the transaction frame has depth zero.
+    pub depth: u64,
+    /// Whether the emitting frame or one of its callers reverted, discarding the log.
+    pub reverted: bool,
+}
+
+/// Callback that receives the streamed logs.
+type Subscriber = Box<dyn FnMut(&StreamedLog)>;
+
+/// [Inspector] that filters the emitted logs by address and topics, decodes them with the
+/// events of ABIs, and forwards them to a subscriber.
+///
+/// Filters work like the ones of `eth_getLogs`: a log matches if it is emitted by one of the
+/// addresses and if, for every topic position with a filter, its topic is one of the filter
+/// topics. Empty filters match all logs.
+///
+/// Logs are forwarded when the transaction ends, as only then it is known whether the emitting
+/// frame reverted. Logs of reverted frames are forwarded with [StreamedLog::reverted] set.
+#[derive(Default)]
+pub struct LogStreamInspector {
+    addresses: Vec<Address>,
+    topics: [Vec<B256>; 4],
+    abi: Abi,
+    subscriber: Option<Subscriber>,
+    /// Logs of the finished transactions, kept if there is no subscriber.
+    logs: Vec<StreamedLog>,
+    /// Matched logs of the current transaction.
+    pending: Vec<StreamedLog>,
+    /// Number of pending logs when each active frame started.
+    checkpoints: Vec<usize>,
+    tx_index: usize,
+}
+
+impl fmt::Debug for LogStreamInspector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogStreamInspector")
+            .field("addresses", &self.addresses)
+            .field("topics", &self.topics)
+            .field("abi", &self.abi)
+            .field("subscribed", &self.subscriber.is_some())
+            .field("logs", &self.logs)
+            .field("pending", &self.pending)
+            .field("tx_index", &self.tx_index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LogStreamInspector {
+    /// Creates a new inspector that matches all logs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches logs emitted by the address, in addition to the other addresses of the filter.
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.addresses.push(address);
+        self
+    }
+
+    /// Matches logs with one of the topics at the position.
+    ///
+    /// Logs have at most four topics, positions above 3 are ignored.
+    pub fn with_topics(mut self, position: usize, topics: impl IntoIterator<Item = B256>) -> Self {
+        if let Some(filter) = self.topics.get_mut(position) {
+            filter.extend(topics);
+        }
+        self
+    }
+
+    /// Decodes logs with the events of the ABI, in addition to the other ABIs.
+    ///
+    /// JSON ABIs are parsed with [Abi::from_json].
+    pub fn with_abi(mut self, abi: Abi) -> Self {
+        self.abi.extend(abi);
+        self
+    }
+
+    /// Forwards the logs to the subscriber instead of keeping them.
+    pub fn with_subscriber(mut self, subscriber: impl FnMut(&StreamedLog) + 'static) -> Self {
+        self.subscriber = Some(Box::new(subscriber));
+        self
+    }
+
+    /// Returns the logs of the finished transactions, empty if there is a subscriber.
+    pub fn logs(&self) -> &[StreamedLog] {
+        &self.logs
+    }
+
+    /// Takes the logs of the finished transactions.
+    pub fn take_logs(&mut self) -> Vec<StreamedLog> {
+        core::mem::take(&mut self.logs)
+    }
+
+    /// Returns `true` if the log matches the address and topic filters.
+    pub fn matches(&self, log: &Log) -> bool {
+        let topics = log.topics();
+        (self.addresses.is_empty() || self.addresses.contains(&log.address))
+            && self.topics.iter().enumerate().all(|(i, filter)| {
+                filter.is_empty() || topics.get(i).is_some_and(|topic| filter.contains(topic))
+            })
+    }
+
+    /// Decodes the log with the events of the ABIs.
+    pub fn decode(&self, log: &Log) -> Option<DecodedEvent> {
+        let (event, values) = self.abi.decode_log(&log.data)?;
+        Some(DecodedEvent {
+            name: event.name.clone(),
+            params: event
+                .inputs
+                .iter()
+                .map(|param| param.name.clone())
+                .zip(values)
+                .collect(),
+        })
+    }
+
+    fn frame_start(&mut self) {
+        self.checkpoints.push(self.pending.len());
+    }
+
+    /// Ends the frame, flagging its logs if it did not succeed, and forwards the logs of the
+    /// transaction when its frame ends.
+    fn frame_end(&mut self, success: bool) {
+        let checkpoint = self.checkpoints.pop().unwrap_or_default();
+        if !success {
+            for log in &mut self.pending[checkpoint..] {
+                log.reverted = true;
+            }
+        }
+        if !self.checkpoints.is_empty() {
+            return;
+        }
+        let pending = core::mem::take(&mut self.pending);
+        match &mut self.subscriber {
+            Some(subscriber) => pending.iter().for_each(subscriber),
+            None => self.logs.extend(pending),
+        }
+        self.tx_index += 1;
+    }
+}
+
+impl<DB: Database> Inspector<DB> for LogStreamInspector {
+    fn log(&mut self, _interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &Log) {
+        if !self.matches(log) {
+            return;
+        }
+        self.pending.push(StreamedLog {
+            log: log.clone(),
+            event: self.decode(log),
+            tx_index: self.tx_index,
+            depth: context.journaled_state.depth().saturating_sub(1),
+            reverted: false,
+        });
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{keccak256, Bytes, LogData, SpecId, U256},
+        test_fixtures::{eof, inspector_builder, Code, CALLER, CHILD, TARGET},
+    };
+    use core::cell::RefCell;
+    use std::{rc::Rc, vec};
+
+    const RECEIVER: Address = Address::repeat_byte(0x14);
+
+    fn transfer_topic() -> B256 {
+        keccak256("Transfer(address,address,uint256)")
+    }
+
+    fn run(inspector: LogStreamInspector, txs: usize) -> LogStreamInspector {
+        // MSTORE(0, 5) LOG3(0, 32, Transfer, CALLER, RECEIVER) CALL(GAS, CHILD, 0, 0, 0, 0, 0) POP
+        let code = Code::new()
+            .push(5)
+            .push(0)
+            .op(0x52)
+            .push_address(RECEIVER)
+            .push_address(CALLER)
+            .push_word(transfer_topic())
+            .push(32)
+            .push(0)
+            .op(0xa3)
+            .call(CHILD, 0)
+            .op(0x50);
+        // LOG1(0, 0, 0x01) REVERT(0, 0)
+        let child = Code::new().push(1).push(0).push(0).op(0xa1).revert();
+
+        let mut evm =
+            inspector_builder(inspector, [(TARGET, code.build()), (CHILD, child.build())]).build();
+        for _ in 0..txs {
+            assert!(evm.transact_commit().unwrap().is_success());
+        }
+        evm.into_context().external
+    }
+
+    #[test]
+    fn decode_and_flag_reverted() {
+        let abi =
+            Abi::parse(["event Transfer(address indexed from, address indexed to, uint256 value)"])
+                .unwrap();
+        let mut inspector = run(LogStreamInspector::new().with_abi(abi), 2);
+        let logs = inspector.take_logs();
+        assert!(inspector.logs().is_empty());
+
+        let transfer = DecodedEvent {
+            name: "Transfer".into(),
+            params: vec![
+                ("from".into(), AbiValue::Address(CALLER)),
+                ("to".into(), AbiValue::Address(RECEIVER)),
+                ("value".into(), AbiValue::Uint(U256::from(5))),
+            ],
+        };
+        assert_eq!(
+            transfer.to_string(),
+            format!("Transfer(from: {CALLER}, to: {RECEIVER}, value: 5)")
+        );
+        let summary: Vec<_> = logs
+            .iter()
+            .map(|l| {
+                (
+                    l.log.address,
+                    l.event.clone(),
+                    l.tx_index,
+                    l.depth,
+                    l.reverted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (TARGET, Some(transfer.clone()), 0, 0, false),
+                (CHILD, None, 0, 1, true),
+                (TARGET, Some(transfer), 1, 0, false),
+                (CHILD, None, 1, 1, true),
+            ]
+        );
+        assert_eq!(
+            logs[1].log.data,
+            LogData::new_unchecked(vec![U256::from(1).into()], Bytes::new())
+        );
+    }
+
+    #[test]
+    fn filter_and_subscribe() {
+        let filter = LogStreamInspector::new()
+            .with_address(CHILD)
+            .with_topics(0, [U256::from(1).into()]);
+        let log = |address, topics| Log {
+            address,
+            data: LogData::new_unchecked(topics, Bytes::new()),
+        };
+        assert!(filter.matches(&log(CHILD, vec![U256::from(1).into(), B256::ZERO])));
+        assert!(!filter.matches(&log(CHILD, vec![B256::ZERO])));
+        assert!(!filter.matches(&log(CHILD, vec![])));
+        assert!(!filter.matches(&log(TARGET, vec![U256::from(1).into()])));
+        let filter = filter.with_topics(4, [B256::ZERO]);
+        assert!(filter.matches(&log(CHILD, vec![U256::from(1).into()])));
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let inspector = run(
+            LogStreamInspector::new()
+                .with_topics(0, [transfer_topic()])
+                .with_topics(2, [RECEIVER.into_word()])
+                .with_subscriber(move |log| sink.borrow_mut().push(log.clone())),
+            1,
+        );
+        assert!(inspector.logs().is_empty());
+        let received = received.borrow();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].log.address, TARGET);
+        assert_eq!(received[0].event, None);
+        assert!(!received[0].reverted);
+    }
+
+    #[test]
+    fn eofcreate() {
+        // LOG1(0, 0, 0x01) REVERT(0, 0)
+        let init = eof(Code::new().push(1).push(0).push(0).op(0xa1).revert(), []);
+        // EOFCREATE(0, 0, 0, 0) of the subcontainer, LOG1(0, 0, 0x02)
+        let code = Code::new()
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(0)
+            .bytes(&[0xec, 0x00, 0x50])
+            .push(2)
+            .push(0)
+            .push(0)
+            .bytes(&[0xa1, 0x00]);
+        let mut evm = inspector_builder(LogStreamInspector::new(), [(TARGET, eof(code, [init]))])
+            .with_spec_id(SpecId::PRAGUE_EOF)
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        let summary: Vec<_> = evm
+            .context
+            .external
+            .logs()
+            .iter()
+            .map(|l| (l.log.topics()[0], l.depth, l.reverted))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (U256::from(1).into(), 1, true),
+                (U256::from(2).into(), 0, false)
+            ]
+        );
+    }
+}
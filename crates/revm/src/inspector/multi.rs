@@ -0,0 +1,596 @@
+//! Inspector implementations for tuples and vectors of inspectors, and [MultiInspector].
+
+use crate::{
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+    },
+    primitives::{db::Database, Address, Log, U256},
+    EvmContext, Inspector,
+};
+use std::vec::Vec;
+
+/// How outcomes returned by `call`, `create` and `eofcreate` of several inspectors combine.
+///
+/// Every inspector is called regardless of the policy, so that it sees the matching `*_end` call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OverridePolicy {
+    /// Outcome of the first inspector that returns `Some` is used.
+    #[default]
+    First,
+    /// Outcome of the last inspector that returns `Some` is used.
+    Last,
+}
+
+impl OverridePolicy {
+    /// Combine the outcome chosen so far with the outcome of the next inspector.
+    #[inline]
+    pub fn combine<T>(self, current: Option<T>, next: Option<T>) -> Option<T> {
+        match self {
+            Self::First => current.or(next),
+            Self::Last => next.or(current),
+        }
+    }
+}
+
+/// Inspectors that are called one after another, in order.
+///
+/// Implemented for tuples and vectors of inspectors. Their [Inspector] implementation
+/// fans out every hook in order, passing the outcome of `*_end` hooks from one inspector
+/// to the next, and combines overriding outcomes with [OverridePolicy::First].
+pub trait InspectorList<DB: Database>: Inspector<DB> {
+    /// Calls `call` of every inspector, combining the outcomes with the `policy`.
+    fn call_with(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+        policy: OverridePolicy,
+    ) -> Option<CallOutcome>;
+
+    /// Calls `create` of every inspector, combining the outcomes with the `policy`.
+    fn create_with(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+        policy: OverridePolicy,
+    ) -> Option<CreateOutcome>;
+
+    /// Calls `eofcreate` of every inspector, combining the outcomes with the `policy`.
+    fn eofcreate_with(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+        policy: OverridePolicy,
+    ) -> Option<CreateOutcome>;
+}
+
+/// [Inspector] that calls a list of inspectors, with a configurable [OverridePolicy].
+///
+/// # Example
+///
+/// ```
+/// use revm::inspectors::{CallTracer, GasInspector, MultiInspector, OverridePolicy};
+///
+/// let inspector = MultiInspector::new((GasInspector::default(), CallTracer::default()))
+///     .with_policy(OverridePolicy::Last);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiInspector<I> {
+    inspectors: I,
+    policy: OverridePolicy,
+}
+
+impl<I> MultiInspector<I> {
+    /// Create new multi inspector that uses [OverridePolicy::First].
+    pub fn new(inspectors: I) -> Self {
+        Self {
+            inspectors,
+            policy: OverridePolicy::default(),
+        }
+    }
+
+    /// Sets the policy of combining overriding outcomes.
+    pub fn with_policy(mut self, policy: OverridePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the policy of combining overriding outcomes.
+    pub fn policy(&self) -> OverridePolicy {
+        self.policy
+    }
+
+    /// Returns the inspectors.
+    pub fn inspectors(&self) -> &I {
+        &self.inspectors
+    }
+
+    /// Returns the inspectors.
+    pub fn inspectors_mut(&mut self) -> &mut I {
+        &mut self.inspectors
+    }
+
+    /// Returns the inspectors, consuming the multi inspector.
+    pub fn into_inner(self) -> I {
+        self.inspectors
+    }
+}
+
+impl<DB: Database, I: InspectorList<DB>> Inspector<DB> for MultiInspector<I> {
+    #[inline]
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspectors.initialize_interp(interp, context);
+    }
+
+    #[inline]
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspectors.step(interp, context);
+    }
+
+    #[inline]
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspectors.step_end(interp, context);
+    }
+
+    #[inline]
+    fn log(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &Log) {
+        self.inspectors.log(interp, context, log);
+    }
+
+    #[inline]
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.inspectors.call_with(context, inputs, self.policy)
+    }
+
+    #[inline]
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.inspectors.call_end(context, inputs, outcome)
+    }
+
+    #[inline]
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inspectors.create_with(context, inputs, self.policy)
+    }
+
+    #[inline]
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.inspectors.create_end(context, inputs, outcome)
+    }
+
+    #[inline]
+    fn eofcreate(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inspectors.eofcreate_with(context, inputs, self.policy)
+    }
+
+    #[inline]
+    fn eofcreate_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.inspectors.eofcreate_end(context, inputs, outcome)
+    }
+
+    #[inline]
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        self.inspectors.selfdestruct(contract, target, value);
+    }
+}
+
+/// Implements [Inspector] and [InspectorList] for a list of inspectors, given
+/// a macro that applies an expression to every inspector in order.
+macro_rules! impl_inspector_list {
+    ([$($generics:tt)*], $ty:ty, $for_each:ident) => {
+        impl<DB: Database, $($generics)*> Inspector<DB> for $ty {
+            #[inline]
+            fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+                $for_each!(self, |inspector| inspector.initialize_interp(interp, context));
+            }
+
+            #[inline]
+            fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+                $for_each!(self, |inspector| inspector.step(interp, context));
+            }
+
+            #[inline]
+            fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+                $for_each!(self, |inspector| inspector.step_end(interp, context));
+            }
+
+            #[inline]
+            fn log(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &Log) {
+                $for_each!(self, |inspector| inspector.log(interp, context, log));
+            }
+
+            #[inline]
+            fn call(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut CallInputs,
+            ) -> Option<CallOutcome> {
+                self.call_with(context, inputs, OverridePolicy::First)
+            }
+
+            #[inline]
+            fn call_end(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &CallInputs,
+                mut outcome: CallOutcome,
+            ) -> CallOutcome {
+                $for_each!(self, |inspector| {
+                    outcome = inspector.call_end(context, inputs, outcome)
+                });
+                outcome
+            }
+
+            #[inline]
+            fn create(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut CreateInputs,
+            ) -> Option<CreateOutcome> {
+                self.create_with(context, inputs, OverridePolicy::First)
+            }
+
+            #[inline]
+            fn create_end(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &CreateInputs,
+                mut outcome: CreateOutcome,
+            ) -> CreateOutcome {
+                $for_each!(self, |inspector| {
+                    outcome = inspector.create_end(context, inputs, outcome)
+                });
+                outcome
+            }
+
+            #[inline]
+            fn eofcreate(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut EOFCreateInputs,
+            ) -> Option<CreateOutcome> {
+                self.eofcreate_with(context, inputs, OverridePolicy::First)
+            }
+
+            #[inline]
+            fn eofcreate_end(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &EOFCreateInputs,
+                mut outcome: CreateOutcome,
+            ) -> CreateOutcome {
+                $for_each!(self, |inspector| {
+                    outcome = inspector.eofcreate_end(context, inputs, outcome)
+                });
+                outcome
+            }
+
+            #[inline]
+            fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+                $for_each!(self, |inspector| inspector.selfdestruct(contract, target, value));
+            }
+        }
+
+        impl<DB: Database, $($generics)*> InspectorList<DB> for $ty {
+            #[inline]
+            fn call_with(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut CallInputs,
+                policy: OverridePolicy,
+            ) -> Option<CallOutcome> {
+                let mut outcome = None;
+                $for_each!(self, |inspector| {
+                    outcome = policy.combine(outcome.take(), inspector.call(context, inputs))
+                });
+                outcome
+            }
+
+            #[inline]
+            fn create_with(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut CreateInputs,
+                policy: OverridePolicy,
+            ) -> Option<CreateOutcome> {
+                let mut outcome = None;
+                $for_each!(self, |inspector| {
+                    outcome = policy.combine(outcome.take(), inspector.create(context, inputs))
+                });
+                outcome
+            }
+
+            #[inline]
+            fn eofcreate_with(
+                &mut self,
+                context: &mut EvmContext<DB>,
+                inputs: &mut EOFCreateInputs,
+                policy: OverridePolicy,
+            ) -> Option<CreateOutcome> {
+                let mut outcome = None;
+                $for_each!(self, |inspector| {
+                    outcome = policy.combine(outcome.take(), inspector.eofcreate(context, inputs))
+                });
+                outcome
+            }
+        }
+    };
+}
+
+macro_rules! for_each_in_vec {
+    ($list:expr, |$inspector:ident| $body:expr) => {
+        for $inspector in $list.iter_mut() {
+            $body;
+        }
+    };
+}
+
+impl_inspector_list!([I: Inspector<DB>], Vec<I>, for_each_in_vec);
+
+macro_rules! impl_tuple {
+    ($($ty:ident . $idx:tt),+) => {
+        macro_rules! for_each_in_tuple {
+            ($list:expr, |$inspector:ident| $body:expr) => {
+                $({
+                    let $inspector = &mut $list.$idx;
+                    $body;
+                })+
+            };
+        }
+
+        impl_inspector_list!([$($ty: Inspector<DB>),+], ($($ty,)+), for_each_in_tuple);
+    };
+}
+
+impl_tuple!(A.0, B.1);
+impl_tuple!(A.0, B.1, C.2);
+impl_tuple!(A.0, B.1, C.2, D.3);
+impl_tuple!(A.0, B.1, C.2, D.3, E.4);
+impl_tuple!(A.0, B.1, C.2, D.3, E.4, F.5);
+impl_tuple!(A.0, B.1, C.2, D.3, E.4, F.5, G.6);
+impl_tuple!(A.0, B.1, C.2, D.3, E.4, F.5, G.6, H.7);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        interpreter::{Gas, InstructionResult, InterpreterResult},
+        primitives::{AccountInfo, Bytecode, Bytes, TxKind},
+        Evm,
+    };
+    use core::cell::RefCell;
+    use std::{boxed::Box, rc::Rc, vec};
+
+    type Events = Rc<RefCell<Vec<(&'static str, &'static str)>>>;
+
+    /// Records every hook it sees and optionally overrides the top call with its name.
+    struct Recorder {
+        name: &'static str,
+        events: Events,
+        overrides: bool,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, events: &Events) -> Self {
+            Self {
+                name,
+                events: events.clone(),
+                overrides: false,
+            }
+        }
+
+        fn overriding(name: &'static str, events: &Events) -> Self {
+            Self {
+                overrides: true,
+                ..Self::new(name, events)
+            }
+        }
+
+        fn record(&self, hook: &'static str) {
+            self.events.borrow_mut().push((self.name, hook));
+        }
+    }
+
+    impl<DB: Database> Inspector<DB> for Recorder {
+        fn initialize_interp(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+            self.record("initialize_interp");
+        }
+
+        fn step(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+            self.record("step");
+        }
+
+        fn step_end(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+            self.record("step_end");
+        }
+
+        fn log(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>, _log: &Log) {
+            self.record("log");
+        }
+
+        fn call(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            inputs: &mut CallInputs,
+        ) -> Option<CallOutcome> {
+            self.record("call");
+            self.overrides.then(|| {
+                CallOutcome::new(
+                    InterpreterResult::new(
+                        InstructionResult::Return,
+                        Bytes::copy_from_slice(self.name.as_bytes()),
+                        Gas::new(inputs.gas_limit),
+                    ),
+                    inputs.return_memory_offset.clone(),
+                )
+            })
+        }
+
+        fn call_end(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            _inputs: &CallInputs,
+            outcome: CallOutcome,
+        ) -> CallOutcome {
+            self.record("call_end");
+            outcome
+        }
+
+        fn create(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            _inputs: &mut CreateInputs,
+        ) -> Option<CreateOutcome> {
+            self.record("create");
+            None
+        }
+
+        fn create_end(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            _inputs: &CreateInputs,
+            outcome: CreateOutcome,
+        ) -> CreateOutcome {
+            self.record("create_end");
+            outcome
+        }
+
+        fn selfdestruct(&mut self, _contract: Address, _target: Address, _value: U256) {
+            self.record("selfdestruct");
+        }
+    }
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const CONTRACT: Address = Address::repeat_byte(0xc0);
+
+    /// Contract logs and creates a contract that selfdestructs in its init code.
+    ///
+    /// PUSH0 PUSH0 LOG0 PUSH2 (CALLER SELFDESTRUCT) PUSH0 MSTORE
+    /// PUSH1 2 PUSH1 30 PUSH0 CREATE POP STOP
+    const CODE: &[u8] = &[
+        0x5f, 0x5f, 0xa0, 0x61, 0x33, 0xff, 0x5f, 0x52, 0x60, 0x02, 0x60, 0x1e, 0x5f, 0xf0, 0x50,
+        0x00,
+    ];
+
+    fn transact<I: Inspector<CacheDB<EmptyDB>>>(inspector: I) -> Bytes {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(CODE))),
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(inspector)
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(CONTRACT);
+                tx.gas_limit = 200_000;
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        assert!(result.is_success());
+        result.into_output().unwrap_or_default()
+    }
+
+    /// Every hook is seen by all inspectors one after another, in order.
+    fn assert_fan_out(events: &Events, names: &[&str]) {
+        let events = events.borrow();
+        assert!(!events.is_empty());
+        for chunk in events.chunks(names.len()) {
+            let inspectors = chunk.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            assert_eq!(inspectors, names);
+            assert!(chunk.iter().all(|(_, hook)| *hook == chunk[0].1));
+        }
+        for hook in [
+            "initialize_interp",
+            "step",
+            "step_end",
+            "log",
+            "call",
+            "call_end",
+            "create",
+            "create_end",
+            "selfdestruct",
+        ] {
+            assert!(events.iter().any(|(_, event)| *event == hook), "{hook}");
+        }
+    }
+
+    #[test]
+    fn tuple_fans_out_in_order() {
+        let events = Events::default();
+        transact((
+            Recorder::new("a", &events),
+            Recorder::new("b", &events),
+            Recorder::new("c", &events),
+        ));
+        assert_fan_out(&events, &["a", "b", "c"]);
+    }
+
+    #[test]
+    fn vec_fans_out_in_order() {
+        let events = Events::default();
+        let inspectors: Vec<Box<dyn Inspector<CacheDB<EmptyDB>>>> = vec![
+            Box::new(Recorder::new("a", &events)),
+            Box::new(Recorder::new("b", &events)),
+        ];
+        transact(inspectors);
+        assert_fan_out(&events, &["a", "b"]);
+    }
+
+    #[test]
+    fn override_policy() {
+        let events = Events::default();
+        let inspectors = || {
+            (
+                Recorder::new("a", &events),
+                Recorder::overriding("b", &events),
+                Recorder::overriding("c", &events),
+            )
+        };
+
+        assert_eq!(transact(inspectors()), Bytes::from_static(b"b"));
+        assert_eq!(
+            transact(MultiInspector::new(inspectors()).with_policy(OverridePolicy::Last)),
+            Bytes::from_static(b"c")
+        );
+        // overridden call is not executed, but all inspectors see its end.
+        assert_eq!(
+            *events.borrow(),
+            [("a", "call"), ("b", "call"), ("c", "call")]
+                .into_iter()
+                .chain([("a", "call_end"), ("b", "call_end"), ("c", "call_end")])
+                .cycle()
+                .take(12)
+                .collect::<Vec<_>>()
+        );
+    }
+}
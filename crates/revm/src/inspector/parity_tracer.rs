@@ -0,0 +1,1142 @@
+//! ParityTracer. Builds flat traces, vmTrace and stateDiff in the format of Parity `trace_*` methods.
+
+use super::prestate_tracer::PrestateTracer;
+use crate::{
+    interpreter::{
+        opcode::{self, OpCode},
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, EOFCreateInputs, Gas,
+        InstructionResult, Interpreter, SuccessOrHalt,
+    },
+    primitives::{
+        alloy_primitives::U64, db::Database, AccountInfo, Address, Bytes, CreateScheme, EvmState,
+        HaltReason, ResultAndState, B256, U256,
+    },
+    EvmContext, Inspector,
+};
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Outputs built by the [ParityTracer], named as the Parity trace types.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct ParityTraceTypes {
+    /// Build flat traces of the calls.
+    pub trace: bool,
+    /// Build the trace of the executed instructions.
+    pub vm_trace: bool,
+    /// Build the state difference made by the transaction.
+    pub state_diff: bool,
+}
+
+/// Type of the call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CallType {
+    /// `CALL` or `EXTCALL`.
+    #[default]
+    Call,
+    /// `CALLCODE`.
+    CallCode,
+    /// `DELEGATECALL` or `EXTDELEGATECALL`.
+    DelegateCall,
+    /// `STATICCALL` or `EXTSTATICCALL`.
+    StaticCall,
+}
+
+impl From<CallScheme> for CallType {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call | CallScheme::ExtCall => Self::Call,
+            CallScheme::CallCode => Self::CallCode,
+            CallScheme::DelegateCall | CallScheme::ExtDelegateCall => Self::DelegateCall,
+            CallScheme::StaticCall | CallScheme::ExtStaticCall => Self::StaticCall,
+        }
+    }
+}
+
+/// How the contract was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CreationMethod {
+    /// `CREATE` or creation transaction.
+    #[default]
+    Create,
+    /// `CREATE2`.
+    Create2,
+    /// `EOFCREATE` or EOF creation transaction.
+    EofCreate,
+}
+
+impl From<CreateScheme> for CreationMethod {
+    fn from(scheme: CreateScheme) -> Self {
+        match scheme {
+            CreateScheme::Create => Self::Create,
+            CreateScheme::Create2 { .. } => Self::Create2,
+        }
+    }
+}
+
+/// Call action.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CallAction {
+    /// Caller.
+    pub from: Address,
+    /// Type of the call.
+    pub call_type: CallType,
+    /// Gas available to the call.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas: u64,
+    /// Call data.
+    pub input: Bytes,
+    /// Called address.
+    pub to: Address,
+    /// Transferred value, apparent value for delegate calls.
+    pub value: U256,
+}
+
+/// Create action.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CreateAction {
+    /// Creator.
+    pub from: Address,
+    /// Gas available to the creation.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas: u64,
+    /// Init code.
+    pub init: Bytes,
+    /// Endowment of the created contract.
+    pub value: U256,
+    /// How the contract was created.
+    pub creation_method: CreationMethod,
+}
+
+/// Selfdestruct action.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct SelfdestructAction {
+    /// Destroyed contract.
+    pub address: Address,
+    /// Beneficiary of the balance.
+    pub refund_address: Address,
+    /// Transferred balance.
+    pub balance: U256,
+}
+
+/// Action of the trace, serialized as the `type` and `action` fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", content = "action", rename_all = "lowercase")
+)]
+pub enum Action {
+    /// Call.
+    Call(CallAction),
+    /// Creation.
+    Create(CreateAction),
+    /// Selfdestruct, named `suicide` as in Parity.
+    #[cfg_attr(feature = "serde", serde(rename = "suicide"))]
+    Selfdestruct(SelfdestructAction),
+}
+
+/// Result of a successful call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CallOutput {
+    /// Gas used by the call.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas_used: u64,
+    /// Returned data.
+    pub output: Bytes,
+}
+
+/// Result of a successful creation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CreateOutput {
+    /// Created address.
+    pub address: Address,
+    /// Deployed code.
+    pub code: Bytes,
+    /// Gas used by the creation.
+    #[cfg_attr(feature = "serde", serde(with = "crate::quantity"))]
+    pub gas_used: u64,
+}
+
+/// Result of a successful action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum TraceOutput {
+    /// Result of a creation.
+    Create(CreateOutput),
+    /// Result of a call.
+    Call(CallOutput),
+}
+
+/// Flat trace of a call, creation or selfdestruct.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TransactionTrace {
+    /// Traced action.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub action: Action,
+    /// Error if the action did not succeed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<String>,
+    /// Result of the action, `None` if it failed and for selfdestructs.
+    pub result: Option<TraceOutput>,
+    /// Number of direct subtraces.
+    pub subtraces: usize,
+    /// Position in the call tree, indices of the subtraces leading to this trace.
+    pub trace_address: Vec<usize>,
+}
+
+/// Memory written by the instruction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryDelta {
+    /// Offset of the written memory.
+    pub off: usize,
+    /// Written data.
+    pub data: Bytes,
+}
+
+/// Storage slot written by the instruction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageDelta {
+    /// Storage key.
+    pub key: U256,
+    /// Written value.
+    pub val: U256,
+}
+
+/// Effects of an executed instruction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VmExecutedOperation {
+    /// Written memory.
+    pub mem: Option<MemoryDelta>,
+    /// Stack items pushed by the instruction.
+    pub push: Vec<U256>,
+    /// Written storage slot.
+    pub store: Option<StorageDelta>,
+    /// Gas remaining after the instruction.
+    pub used: u64,
+}
+
+/// Instruction of the [VmTrace].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VmInstruction {
+    /// Gas cost of the instruction, including gas given to the nested call.
+    pub cost: u64,
+    /// Effects of the instruction, `None` if it failed.
+    pub ex: Option<VmExecutedOperation>,
+    /// Program counter.
+    pub pc: usize,
+    /// Trace of the call or creation made by the instruction.
+    pub sub: Option<VmTrace>,
+}
+
+/// Trace of the instructions executed by a call frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VmTrace {
+    /// Executed code, empty for precompiles.
+    pub code: Bytes,
+    /// Executed instructions.
+    pub ops: Vec<VmInstruction>,
+}
+
+/// Values before and after a change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangedType<T> {
+    /// Value before the transaction.
+    pub from: T,
+    /// Value after the transaction.
+    pub to: T,
+}
+
+/// Change of a value made by the transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Delta<T> {
+    /// Value did not change, serialized as `"="`.
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "="))]
+    Unchanged,
+    /// Account was created.
+    #[cfg_attr(feature = "serde", serde(rename = "+"))]
+    Added(T),
+    /// Account was destroyed.
+    #[cfg_attr(feature = "serde", serde(rename = "-"))]
+    Removed(T),
+    /// Value changed.
+    #[cfg_attr(feature = "serde", serde(rename = "*"))]
+    Changed(ChangedType<T>),
+}
+
+impl<T: PartialEq> Delta<T> {
+    /// Returns [Delta::Changed] if the values differ, [Delta::Unchanged] otherwise.
+    pub fn new(from: T, to: T) -> Self {
+        if from == to {
+            Self::Unchanged
+        } else {
+            Self::Changed(ChangedType { from, to })
+        }
+    }
+
+    /// Returns true if the value did not change.
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Self::Unchanged)
+    }
+}
+
+/// Change of an account made by the transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountDiff {
+    /// Change of the balance.
+    pub balance: Delta<U256>,
+    /// Change of the code.
+    pub code: Delta<Bytes>,
+    /// Change of the nonce.
+    pub nonce: Delta<U64>,
+    /// Changed storage slots.
+    pub storage: BTreeMap<B256, Delta<B256>>,
+}
+
+impl AccountDiff {
+    /// Returns true if nothing changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.balance.is_unchanged()
+            && self.code.is_unchanged()
+            && self.nonce.is_unchanged()
+            && self.storage.is_empty()
+    }
+}
+
+/// State difference made by the transaction, only changed accounts are included.
+pub type StateDiff = BTreeMap<Address, AccountDiff>;
+
+/// Outputs of the transaction, named as in Parity `trace_call` result.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TraceResults {
+    /// Output of the transaction.
+    pub output: Bytes,
+    /// State difference, if requested.
+    pub state_diff: Option<StateDiff>,
+    /// Flat traces, empty if not requested.
+    pub trace: Vec<TransactionTrace>,
+    /// Trace of the instructions, if requested.
+    pub vm_trace: Option<VmTrace>,
+}
+
+/// Instruction that is being executed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct PendingStep {
+    opcode: u8,
+    gas: u64,
+    /// Offset and size of the memory written by the instruction.
+    mem: Option<(usize, usize)>,
+    store: Option<StorageDelta>,
+}
+
+/// [VmTrace] of the frame that is being executed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct VmFrame {
+    trace: VmTrace,
+    pending: Option<PendingStep>,
+}
+
+/// [Inspector] that builds Parity style traces of the transaction.
+///
+/// All three outputs selected by [ParityTraceTypes] are built from one execution.
+/// Flat traces are ordered depth first, as in Parity. State difference needs the state
+/// after the transaction, so the results are returned by [ParityTracer::trace_results]
+/// that takes the output of the execution.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParityTracer {
+    types: ParityTraceTypes,
+    /// Finished and running flat traces.
+    traces: Vec<TransactionTrace>,
+    /// Indices of the running flat traces.
+    trace_stack: Vec<usize>,
+    /// Frames that are being executed.
+    vm_stack: Vec<VmFrame>,
+    /// Finished top frame.
+    vm_trace: Option<VmTrace>,
+    /// Selfdestruct that is being executed.
+    selfdestruct: Option<SelfdestructAction>,
+    /// Captures the state before the transaction for the state difference.
+    prestate: PrestateTracer,
+}
+
+impl ParityTracer {
+    /// Create new tracer that builds the given outputs.
+    pub fn new(types: ParityTraceTypes) -> Self {
+        Self {
+            types,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the outputs built by the tracer.
+    pub fn types(&self) -> &ParityTraceTypes {
+        &self.types
+    }
+
+    /// Returns flat traces of the transaction.
+    pub fn traces(&self) -> &[TransactionTrace] {
+        &self.traces
+    }
+
+    /// Returns the trace of the executed instructions.
+    pub fn vm_trace(&self) -> Option<&VmTrace> {
+        self.vm_trace.as_ref()
+    }
+
+    /// Returns the state difference made by the transaction.
+    ///
+    /// `state` is the state returned by the execution of the transaction.
+    pub fn state_diff(&self, state: &EvmState) -> StateDiff {
+        let mut diff = StateDiff::new();
+        for (address, pre) in self.prestate.pre_accounts() {
+            let Some(account) = state.get(address) else {
+                continue;
+            };
+            let pre_info = pre.info.as_ref().filter(|info| !info.is_empty());
+            let post_info =
+                Some(&account.info).filter(|info| !account.is_selfdestructed() && !info.is_empty());
+            let account_diff = match (pre_info, post_info) {
+                (None, None) => continue,
+                (None, Some(post)) => AccountDiff {
+                    balance: Delta::Added(post.balance),
+                    code: Delta::Added(code(post)),
+                    nonce: Delta::Added(U64::from(post.nonce)),
+                    storage: account
+                        .storage
+                        .iter()
+                        .filter(|(_, slot)| !slot.present_value.is_zero())
+                        .map(|(key, slot)| ((*key).into(), Delta::Added(slot.present_value.into())))
+                        .collect(),
+                },
+                (Some(pre_info), None) => AccountDiff {
+                    balance: Delta::Removed(pre_info.balance),
+                    code: Delta::Removed(code(pre_info)),
+                    nonce: Delta::Removed(U64::from(pre_info.nonce)),
+                    storage: pre
+                        .storage
+                        .iter()
+                        .filter(|(_, value)| !value.is_zero())
+                        .map(|(key, value)| ((*key).into(), Delta::Removed((*value).into())))
+                        .collect(),
+                },
+                (Some(pre_info), Some(post)) => AccountDiff {
+                    balance: Delta::new(pre_info.balance, post.balance),
+                    code: if pre_info.code_hash == post.code_hash {
+                        Delta::Unchanged
+                    } else {
+                        Delta::new(code(pre_info), code(post))
+                    },
+                    nonce: Delta::new(U64::from(pre_info.nonce), U64::from(post.nonce)),
+                    storage: pre
+                        .storage
+                        .iter()
+                        .filter_map(|(key, original)| {
+                            let present = account.storage.get(key)?.present_value;
+                            let delta = Delta::new(B256::from(*original), present.into());
+                            (!delta.is_unchanged()).then_some(((*key).into(), delta))
+                        })
+                        .collect(),
+                },
+            };
+            if !account_diff.is_unchanged() {
+                diff.insert(*address, account_diff);
+            }
+        }
+        diff
+    }
+
+    /// Returns the requested outputs of the transaction.
+    ///
+    /// `result` is the output of the execution of the transaction.
+    pub fn trace_results(&self, result: &ResultAndState) -> TraceResults {
+        TraceResults {
+            output: result.result.output().cloned().unwrap_or_default(),
+            state_diff: self
+                .types
+                .state_diff
+                .then(|| self.state_diff(&result.state)),
+            trace: if self.types.trace {
+                self.traces.clone()
+            } else {
+                Vec::new()
+            },
+            vm_trace: self.vm_trace.clone(),
+        }
+    }
+
+    fn start_frame(&mut self, action: Action) {
+        if self.types.trace {
+            self.push_trace(action, true);
+        }
+        if self.types.vm_trace {
+            if self.vm_stack.is_empty() {
+                self.vm_trace = None;
+            }
+            self.vm_stack.push(VmFrame::default());
+        }
+    }
+
+    /// Add the trace as the next subtrace of the running frame.
+    fn push_trace(&mut self, action: Action, running: bool) {
+        let trace_address = match self.trace_stack.last() {
+            Some(&parent) => {
+                let parent = &mut self.traces[parent];
+                let mut trace_address = parent.trace_address.clone();
+                trace_address.push(parent.subtraces);
+                parent.subtraces += 1;
+                trace_address
+            }
+            None => {
+                self.traces.clear();
+                Vec::new()
+            }
+        };
+        if running {
+            self.trace_stack.push(self.traces.len());
+        }
+        self.traces.push(TransactionTrace {
+            action,
+            error: None,
+            result: None,
+            subtraces: 0,
+            trace_address,
+        });
+    }
+
+    fn end_frame(
+        &mut self,
+        result: InstructionResult,
+        gas: &Gas,
+        output: &Bytes,
+        address: Option<Address>,
+        push: U256,
+        mem: Option<MemoryDelta>,
+    ) {
+        if let Some(index) = self.trace_stack.pop() {
+            let trace = &mut self.traces[index];
+            match SuccessOrHalt::from(result) {
+                SuccessOrHalt::Success(_) => {
+                    let gas_used = gas.spent();
+                    trace.result = Some(match trace.action {
+                        Action::Create(_) => TraceOutput::Create(CreateOutput {
+                            address: address.unwrap_or_default(),
+                            code: output.clone(),
+                            gas_used,
+                        }),
+                        _ => TraceOutput::Call(CallOutput {
+                            gas_used,
+                            output: output.clone(),
+                        }),
+                    })
+                }
+                SuccessOrHalt::Revert => trace.error = Some("Reverted".to_string()),
+                SuccessOrHalt::Halt(reason) => trace.error = Some(halt_error(reason)),
+                other => trace.error = Some(format!("{other:?}")),
+            }
+        }
+
+        let Some(frame) = self.vm_stack.pop() else {
+            return;
+        };
+        let Some(parent) = self.vm_stack.last_mut() else {
+            self.vm_trace = Some(frame.trace);
+            return;
+        };
+        let Some(instruction) = parent.trace.ops.last_mut() else {
+            return;
+        };
+        instruction.sub = Some(frame.trace);
+        // the result is pushed and unused gas is returned after the frame ends.
+        if let Some(ex) = &mut instruction.ex {
+            if result.is_ok() || result.is_revert() {
+                ex.used += gas.remaining();
+            }
+            ex.push = vec![push];
+            ex.mem = mem.filter(|mem| !mem.data.is_empty());
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for ParityTracer {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(frame) = self.vm_stack.last_mut() {
+            frame.trace.code = interp.contract.bytecode.original_bytes();
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        // `selfdestruct` hook is not called for contracts that are not destroyed since Cancun.
+        if self.types.trace && interp.current_opcode() == opcode::SELFDESTRUCT {
+            let address = interp.contract.target_address;
+            self.selfdestruct = interp
+                .stack()
+                .peek(0)
+                .ok()
+                .map(|target| SelfdestructAction {
+                    address,
+                    refund_address: Address::from_word(target.into()),
+                    balance: context
+                        .journaled_state
+                        .state
+                        .get(&address)
+                        .map(|account| account.info.balance)
+                        .unwrap_or_default(),
+                });
+        }
+
+        let Some(frame) = self.vm_stack.last_mut() else {
+            return;
+        };
+        let stack = interp.stack();
+        let range = |offset: usize, size: Option<usize>| {
+            let offset = stack.peek(offset).ok()?.saturating_to::<usize>();
+            let size = match size {
+                Some(size) => size,
+                None => stack.peek(2).ok()?.saturating_to::<usize>(),
+            };
+            Some((offset, size))
+        };
+        let opcode = interp.current_opcode();
+        let mem = match opcode {
+            opcode::MSTORE => range(0, Some(32)),
+            opcode::MSTORE8 => range(0, Some(1)),
+            opcode::CALLDATACOPY
+            | opcode::CODECOPY
+            | opcode::RETURNDATACOPY
+            | opcode::MCOPY
+            | opcode::DATACOPY => range(0, None),
+            opcode::EXTCODECOPY => stack
+                .peek(3)
+                .ok()
+                .and_then(|size| range(1, Some(size.saturating_to::<usize>()))),
+            _ => None,
+        };
+        let store = match (opcode, stack.peek(0), stack.peek(1)) {
+            (opcode::SSTORE, Ok(key), Ok(val)) => Some(StorageDelta { key, val }),
+            _ => None,
+        };
+
+        frame.trace.ops.push(VmInstruction {
+            pc: interp.program_counter(),
+            ..Default::default()
+        });
+        frame.pending = Some(PendingStep {
+            opcode,
+            gas: interp.gas.remaining(),
+            mem,
+            store,
+        });
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(action) = self.selfdestruct.take() {
+            if !interp.instruction_result.is_error() && !self.trace_stack.is_empty() {
+                self.push_trace(Action::Selfdestruct(action), false);
+            }
+        }
+
+        let Some(frame) = self.vm_stack.last_mut() else {
+            return;
+        };
+        let (Some(pending), Some(instruction)) = (frame.pending.take(), frame.trace.ops.last_mut())
+        else {
+            return;
+        };
+        instruction.cost = pending.gas.saturating_sub(interp.gas.remaining());
+        if interp.instruction_result.is_error() {
+            return;
+        }
+
+        // result of calls and creations is pushed when the nested frame ends.
+        let push = if interp.instruction_result == InstructionResult::CallOrCreate {
+            Vec::new()
+        } else {
+            let stack = interp.stack().data();
+            let outputs = OpCode::new(pending.opcode).map_or(0, |op| op.outputs() as usize);
+            stack[stack.len() - outputs.min(stack.len())..].to_vec()
+        };
+        let mem = pending.mem.and_then(|(off, size)| {
+            let memory = &interp.shared_memory;
+            (size != 0 && off.saturating_add(size) <= memory.len()).then(|| MemoryDelta {
+                off,
+                data: Bytes::copy_from_slice(memory.slice(off, size)),
+            })
+        });
+        instruction.ex = Some(VmExecutedOperation {
+            mem,
+            push,
+            store: pending.store,
+            used: interp.gas.remaining(),
+        });
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if self.types.state_diff {
+            self.prestate.call(context, inputs);
+        }
+        self.start_frame(Action::Call(CallAction {
+            from: inputs.caller,
+            call_type: inputs.scheme.into(),
+            gas: inputs.gas_limit,
+            input: inputs.input.clone(),
+            to: inputs.target_address,
+            value: inputs.call_value(),
+        }));
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let result = outcome.result.result;
+        let push = match inputs.scheme {
+            CallScheme::ExtCall | CallScheme::ExtDelegateCall | CallScheme::ExtStaticCall => {
+                if result.is_ok() {
+                    U256::ZERO
+                } else if result.is_revert() {
+                    U256::from(1)
+                } else {
+                    U256::from(2)
+                }
+            }
+            _ => U256::from(result.is_ok() as u8),
+        };
+        let mem = (result.is_ok() || result.is_revert()).then(|| {
+            let output = &outcome.result.output;
+            let size = output.len().min(inputs.return_memory_offset.len());
+            MemoryDelta {
+                off: inputs.return_memory_offset.start,
+                data: output.slice(..size),
+            }
+        });
+        self.end_frame(
+            result,
+            &outcome.result.gas,
+            &outcome.result.output,
+            None,
+            push,
+            mem,
+        );
+        if self.types.state_diff {
+            self.prestate.call_end(context, inputs, outcome)
+        } else {
+            outcome
+        }
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        if self.types.state_diff {
+            self.prestate.create(context, inputs);
+        }
+        self.start_frame(Action::Create(CreateAction {
+            from: inputs.caller,
+            gas: inputs.gas_limit,
+            init: inputs.init_code.clone(),
+            value: inputs.value,
+            creation_method: inputs.scheme.into(),
+        }));
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.create_frame_end(&outcome);
+        if self.types.state_diff {
+            self.prestate.create_end(context, inputs, outcome)
+        } else {
+            outcome
+        }
+    }
+
+    fn eofcreate(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        if self.types.state_diff {
+            self.prestate.eofcreate(context, inputs);
+        }
+        let init = match &inputs.kind {
+            crate::interpreter::EOFCreateKind::Tx { initdata } => initdata.clone(),
+            crate::interpreter::EOFCreateKind::Opcode {
+                initcode, input, ..
+            } => [initcode.raw.as_ref(), input.as_ref()].concat().into(),
+        };
+        self.start_frame(Action::Create(CreateAction {
+            from: inputs.caller,
+            gas: inputs.gas_limit,
+            init,
+            value: inputs.value,
+            creation_method: CreationMethod::EofCreate,
+        }));
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.create_frame_end(&outcome);
+        if self.types.state_diff {
+            self.prestate.eofcreate_end(context, inputs, outcome)
+        } else {
+            outcome
+        }
+    }
+}
+
+impl ParityTracer {
+    fn create_frame_end(&mut self, outcome: &CreateOutcome) {
+        let push = match outcome.address {
+            Some(address) if outcome.result.result.is_ok() => address.into_word().into(),
+            _ => U256::ZERO,
+        };
+        self.end_frame(
+            outcome.result.result,
+            &outcome.result.gas,
+            &outcome.result.output,
+            outcome.address,
+            push,
+            None,
+        );
+    }
+}
+
+/// Returns the code of the account, empty if it has none.
+fn code(info: &AccountInfo) -> Bytes {
+    info.code
+        .as_ref()
+        .map(|code| code.original_bytes())
+        .unwrap_or_default()
+}
+
+/// Returns the Parity error message of the halt.
+fn halt_error(reason: HaltReason) -> String {
+    let message = match reason {
+        HaltReason::OutOfGas(_) => "Out of gas",
+        HaltReason::OpcodeNotFound | HaltReason::InvalidFEOpcode | HaltReason::NotActivated => {
+            "Bad instruction"
+        }
+        HaltReason::InvalidJump => "Bad jump destination",
+        HaltReason::StackUnderflow => "Stack underflow",
+        HaltReason::StackOverflow => "Out of stack",
+        HaltReason::OutOfOffset => "Out of bounds",
+        HaltReason::PrecompileError => "Built-in failed",
+        HaltReason::StateChangeDuringStaticCall | HaltReason::CallNotAllowedInsideStatic => {
+            "Mutable call in static context"
+        }
+        other => return format!("{other:?}"),
+    };
+    message.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{Bytecode, TxKind},
+        Evm,
+    };
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const OUTER: Address = Address::repeat_byte(0x0a);
+    const INNER: Address = Address::repeat_byte(0x0b);
+
+    /// Outer contract calls the inner contract with 1 wei, copies 32 returned bytes
+    /// to memory and selfdestructs to the caller.
+    fn outer_code() -> Bytes {
+        let mut code = vec![0x60, 0x20, 0x5f, 0x5f, 0x5f, 0x60, 0x01, 0x73];
+        code.extend_from_slice(INNER.as_slice());
+        code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x50, 0x33, 0xff]);
+        code.into()
+    }
+
+    /// PUSH1 0x2a PUSH1 1 SSTORE PUSH1 0x2a PUSH0 MSTORE PUSH1 32 PUSH0 RETURN
+    const INNER_CODE: &[u8] = &[
+        0x60, 0x2a, 0x60, 0x01, 0x55, 0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3,
+    ];
+
+    fn evm(types: ParityTraceTypes) -> Evm<'static, ParityTracer, CacheDB<EmptyDB>> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balance: U256::from(100),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            OUTER,
+            AccountInfo {
+                balance: U256::from(10),
+                ..AccountInfo::from_bytecode(Bytecode::new_raw(outer_code()))
+            },
+        );
+        db.insert_account_info(
+            INNER,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(INNER_CODE))),
+        );
+        Evm::builder()
+            .with_db(db)
+            .with_external_context(ParityTracer::new(types))
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(OUTER);
+                tx.gas_limit = 200_000;
+            })
+            .build()
+    }
+
+    fn word(value: u64) -> B256 {
+        U256::from(value).into()
+    }
+
+    #[test]
+    fn flat_traces() {
+        let mut evm = evm(ParityTraceTypes {
+            trace: true,
+            ..Default::default()
+        });
+        let result = evm.transact().unwrap();
+        let results = evm.context.external.trace_results(&result);
+        assert!(results.vm_trace.is_none() && results.state_diff.is_none());
+
+        let traces = results.trace;
+        assert_eq!(traces.len(), 3);
+        assert_eq!(traces[0].trace_address, Vec::<usize>::new());
+        assert_eq!(traces[0].subtraces, 2);
+        let Action::Call(root) = &traces[0].action else {
+            panic!("expected call");
+        };
+        assert_eq!(root.gas, 200_000 - 21_000);
+
+        assert_eq!(traces[1].trace_address, vec![0]);
+        assert_eq!(
+            traces[1].action,
+            Action::Call(CallAction {
+                from: OUTER,
+                call_type: CallType::Call,
+                // value transfer adds the stipend.
+                gas: 0xffff + 2300,
+                input: Bytes::new(),
+                to: INNER,
+                value: U256::from(1),
+            })
+        );
+        let Some(TraceOutput::Call(output)) = &traces[1].result else {
+            panic!("expected call output");
+        };
+        assert_eq!(output.output, Bytes::from(word(0x2a)));
+
+        assert_eq!(traces[2].trace_address, vec![1]);
+        assert_eq!(
+            traces[2].action,
+            Action::Selfdestruct(SelfdestructAction {
+                address: OUTER,
+                refund_address: CALLER,
+                balance: U256::from(9),
+            })
+        );
+        assert_eq!(traces[2].result, None);
+    }
+
+    #[test]
+    fn vm_trace() {
+        let mut evm = evm(ParityTraceTypes {
+            vm_trace: true,
+            ..Default::default()
+        });
+        evm.transact().unwrap();
+        let vm_trace = evm.context.external.vm_trace().unwrap();
+        assert_eq!(vm_trace.code, outer_code());
+        assert_eq!(vm_trace.ops.len(), 11);
+
+        let push = &vm_trace.ops[0];
+        assert_eq!((push.pc, push.cost), (0, 3));
+        let ex = push.ex.as_ref().unwrap();
+        assert_eq!(ex.push, vec![U256::from(32)]);
+        assert_eq!(ex.used, 200_000 - 21_000 - 3);
+
+        let call = &vm_trace.ops[7];
+        let ex = call.ex.as_ref().unwrap();
+        assert_eq!(ex.push, vec![U256::from(1)]);
+        assert_eq!(
+            ex.mem,
+            Some(MemoryDelta {
+                off: 0,
+                data: word(0x2a).into()
+            })
+        );
+        // gas left after the call is the gas before the next instruction.
+        let pop = &vm_trace.ops[8];
+        assert_eq!(pop.ex.as_ref().unwrap().used + pop.cost, ex.used);
+
+        let sub = call.sub.as_ref().unwrap();
+        assert_eq!(sub.code, Bytes::from_static(INNER_CODE));
+        assert_eq!(sub.ops.len(), 9);
+        assert_eq!(
+            sub.ops[2].ex.as_ref().unwrap().store,
+            Some(StorageDelta {
+                key: U256::from(1),
+                val: U256::from(0x2a)
+            })
+        );
+        assert_eq!(
+            sub.ops[5].ex.as_ref().unwrap().mem,
+            Some(MemoryDelta {
+                off: 0,
+                data: word(0x2a).into()
+            })
+        );
+    }
+
+    #[test]
+    fn state_diff() {
+        let mut evm = evm(ParityTraceTypes {
+            state_diff: true,
+            ..Default::default()
+        });
+        let result = evm.transact().unwrap();
+        let diff = evm
+            .context
+            .external
+            .trace_results(&result)
+            .state_diff
+            .unwrap();
+
+        // coinbase is empty before and after the transaction.
+        assert_eq!(diff.len(), 3);
+        assert_eq!(
+            diff[&CALLER],
+            AccountDiff {
+                balance: Delta::new(U256::from(100), U256::from(109)),
+                nonce: Delta::new(U64::from(0), U64::from(1)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            diff[&OUTER],
+            AccountDiff {
+                balance: Delta::new(U256::from(10), U256::ZERO),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            diff[&INNER],
+            AccountDiff {
+                balance: Delta::new(U256::ZERO, U256::from(1)),
+                storage: BTreeMap::from([(word(1), Delta::new(word(0), word(0x2a)))]),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn parity_format() {
+        let results = TraceResults {
+            output: Bytes::from_static(&[0x01]),
+            state_diff: Some(BTreeMap::from([(
+                INNER,
+                AccountDiff {
+                    balance: Delta::Added(U256::from(1)),
+                    nonce: Delta::new(U64::from(1), U64::from(2)),
+                    ..Default::default()
+                },
+            )])),
+            trace: vec![TransactionTrace {
+                action: Action::Call(CallAction {
+                    from: CALLER,
+                    gas: 0x10,
+                    to: INNER,
+                    ..Default::default()
+                }),
+                error: None,
+                result: Some(TraceOutput::Call(CallOutput {
+                    gas_used: 3,
+                    output: Bytes::from_static(&[0x01]),
+                })),
+                subtraces: 0,
+                trace_address: vec![],
+            }],
+            vm_trace: Some(VmTrace {
+                code: Bytes::from_static(&[0x00]),
+                ops: vec![VmInstruction {
+                    cost: 0,
+                    ex: Some(VmExecutedOperation {
+                        used: 16,
+                        ..Default::default()
+                    }),
+                    pc: 0,
+                    sub: None,
+                }],
+            }),
+        };
+        let json = serde_json::to_string(&results).unwrap();
+        assert_eq!(
+            json,
+            r#"{"output":"0x01","stateDiff":{"0x0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b":{"balance":{"+":"0x1"},"code":"=","nonce":{"*":{"from":"0x1","to":"0x2"}},"storage":{}}},"trace":[{"type":"call","action":{"from":"0xcacacacacacacacacacacacacacacacacacacaca","callType":"call","gas":"0x10","input":"0x","to":"0x0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b","value":"0x0"},"result":{"gasUsed":"0x3","output":"0x01"},"subtraces":0,"traceAddress":[]}],"vmTrace":{"code":"0x00","ops":[{"cost":0,"ex":{"mem":null,"push":[],"store":null,"used":16},"pc":0,"sub":null}]}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TraceResults>(&json).unwrap(),
+            results
+        );
+    }
+}
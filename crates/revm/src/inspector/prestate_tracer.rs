@@ -0,0 +1,540 @@
+//! PrestateTracer. Captures the state accessed by the transaction in the format of geth `prestateTracer`.
+
+use crate::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs},
+    primitives::{
+        db::Database, AccountInfo, Address, Bytes, EVMError, EvmState, B256, KECCAK_EMPTY, U256,
+    },
+    EvmContext, Inspector,
+};
+use std::{collections::BTreeMap, vec::Vec};
+
+/// Options of the [PrestateTracer], named as in geth `prestateTracer` config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct PrestateTracerConfig {
+    /// Return the state before and after the transaction, only for changed accounts.
+    pub diff_mode: bool,
+    /// Do not include code of the accounts.
+    pub disable_code: bool,
+    /// Do not include storage of the accounts.
+    pub disable_storage: bool,
+}
+
+/// State of an account, fields are ordered and named as in geth `prestateTracer` output.
+///
+/// Fields that are not set are omitted, in diff mode post state contains only changed fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountState {
+    /// Balance of the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub balance: Option<U256>,
+    /// Code of the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub code: Option<Bytes>,
+    /// Nonce of the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub nonce: Option<u64>,
+    /// Storage slots of the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+/// Output of the [PrestateTracer].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum PrestateFrame {
+    /// State of all accessed accounts before the transaction.
+    Prestate(BTreeMap<Address, AccountState>),
+    /// State of changed accounts before and after the transaction.
+    Diff {
+        /// Changed accounts before the transaction.
+        pre: BTreeMap<Address, AccountState>,
+        /// Changed fields after the transaction. Destroyed accounts are omitted.
+        post: BTreeMap<Address, AccountState>,
+    },
+}
+
+/// Account as it was before the transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PreAccount {
+    /// `None` if the account did not exist.
+    pub(crate) info: Option<AccountInfo>,
+    pub(crate) storage: BTreeMap<U256, U256>,
+}
+
+/// [Inspector] that captures the state accessed by the transaction, as geth `prestateTracer` does.
+///
+/// Accounts and storage slots loaded by the transaction are captured when the top frame ends,
+/// together with the coinbase. State after the transaction, needed by the diff mode,
+/// is not known until the transaction is finalized, so [PrestateTracer::frame] takes
+/// the state returned by the execution.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrestateTracer {
+    config: PrestateTracerConfig,
+    depth: usize,
+    pre: BTreeMap<Address, PreAccount>,
+}
+
+impl PrestateTracer {
+    /// Create new tracer with the given options.
+    pub fn new(config: PrestateTracerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options of the tracer.
+    pub fn config(&self) -> &PrestateTracerConfig {
+        &self.config
+    }
+
+    /// Returns the state before the transaction of all accessed accounts.
+    pub fn prestate(&self) -> BTreeMap<Address, AccountState> {
+        self.pre
+            .iter()
+            .map(|(address, pre)| (*address, self.pre_account_state(pre)))
+            .collect()
+    }
+
+    /// Returns the state before and after the transaction of the changed accounts.
+    ///
+    /// `state` is the state returned by the execution of the transaction.
+    pub fn diff(
+        &self,
+        state: &EvmState,
+    ) -> (
+        BTreeMap<Address, AccountState>,
+        BTreeMap<Address, AccountState>,
+    ) {
+        let mut pre = BTreeMap::new();
+        let mut post = BTreeMap::new();
+        for (address, pre_account) in &self.pre {
+            let Some(account) = state.get(address) else {
+                continue;
+            };
+            let mut pre_state = self.pre_account_state(pre_account);
+            if account.is_selfdestructed() {
+                pre.insert(*address, pre_state);
+                continue;
+            }
+
+            let info = pre_account.info.clone().unwrap_or_default();
+            let mut post_state = AccountState::default();
+            if account.info.balance != info.balance {
+                post_state.balance = Some(account.info.balance);
+            }
+            if account.info.nonce != info.nonce {
+                post_state.nonce = Some(account.info.nonce);
+            }
+            if account.info.code_hash != info.code_hash && !self.config.disable_code {
+                post_state.code = account
+                    .info
+                    .code
+                    .as_ref()
+                    .map(|code| code.original_bytes())
+                    .filter(|code| !code.is_empty());
+            }
+            let mut storage_changed = false;
+            pre_state.storage.clear();
+            if !self.config.disable_storage {
+                for (slot, original) in &pre_account.storage {
+                    let present = account
+                        .storage
+                        .get(slot)
+                        .map(|slot| slot.present_value)
+                        .unwrap_or(*original);
+                    if present == *original {
+                        continue;
+                    }
+                    storage_changed = true;
+                    if !original.is_zero() {
+                        pre_state.storage.insert((*slot).into(), (*original).into());
+                    }
+                    if !present.is_zero() {
+                        post_state.storage.insert((*slot).into(), present.into());
+                    }
+                }
+            }
+
+            let modified = post_state.balance.is_some()
+                || post_state.nonce.is_some()
+                || post_state.code.is_some()
+                || storage_changed;
+            if !modified {
+                continue;
+            }
+            // accounts that did not exist are only in the post state.
+            if pre_account.info.is_some() {
+                pre.insert(*address, pre_state);
+            }
+            post.insert(*address, post_state);
+        }
+        (pre, post)
+    }
+
+    /// Returns the output in the mode given by the options.
+    ///
+    /// `state` is the state returned by the execution of the transaction.
+    pub fn frame(&self, state: &EvmState) -> PrestateFrame {
+        if self.config.diff_mode {
+            let (pre, post) = self.diff(state);
+            PrestateFrame::Diff { pre, post }
+        } else {
+            PrestateFrame::Prestate(self.prestate())
+        }
+    }
+
+    /// Returns captured accounts as they were before the transaction.
+    pub(crate) fn pre_accounts(&self) -> &BTreeMap<Address, PreAccount> {
+        &self.pre
+    }
+
+    fn pre_account_state(&self, pre: &PreAccount) -> AccountState {
+        let info = pre.info.clone().unwrap_or_default();
+        AccountState {
+            balance: Some(info.balance),
+            code: info
+                .code
+                .map(|code| code.original_bytes())
+                .filter(|code| !code.is_empty() && !self.config.disable_code),
+            nonce: Some(info.nonce).filter(|nonce| *nonce != 0),
+            storage: if self.config.disable_storage {
+                BTreeMap::new()
+            } else {
+                pre.storage
+                    .iter()
+                    .map(|(slot, value)| ((*slot).into(), (*value).into()))
+                    .collect()
+            },
+        }
+    }
+
+    fn frame_start(&mut self) {
+        if self.depth == 0 {
+            self.pre.clear();
+        }
+        self.depth += 1;
+    }
+
+    /// Capture the prestate when the top frame ends.
+    fn frame_end<DB: Database>(&mut self, context: &mut EvmContext<DB>) {
+        self.depth -= 1;
+        if self.depth != 0 {
+            return;
+        }
+        if let Err(e) = self.capture(context) {
+            context.error = Err(EVMError::Database(e));
+        }
+    }
+
+    fn capture<DB: Database>(&mut self, context: &mut EvmContext<DB>) -> Result<(), DB::Error> {
+        let mut addresses = context
+            .journaled_state
+            .state
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        addresses.push(context.env.block.coinbase);
+        for address in addresses {
+            let mut info = context.db.basic(address)?;
+            if let Some(info) = &mut info {
+                if info.code.is_none() && info.code_hash != KECCAK_EMPTY {
+                    info.code = Some(context.db.code_by_hash(info.code_hash)?);
+                }
+            }
+            let storage = context
+                .journaled_state
+                .state
+                .get(&address)
+                .map(|account| {
+                    account
+                        .storage
+                        .iter()
+                        .map(|(slot, value)| (*slot, value.original_value))
+                        .collect()
+                })
+                .unwrap_or_default();
+            self.pre.insert(address, PreAccount { info, storage });
+        }
+        Ok(())
+    }
+}
+
+impl<DB: Database> Inspector<DB> for PrestateTracer {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frame_end(context);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frame_end(context);
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start();
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frame_end(context);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{Bytecode, SpecId, TxKind},
+        Evm,
+    };
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const CONTRACT: Address = Address::repeat_byte(0xc0);
+    const COINBASE: Address = Address::repeat_byte(0xcb);
+
+    /// PUSH1 1 SLOAD POP PUSH1 7 PUSH1 2 SSTORE PUSH0 PUSH1 3 SSTORE STOP
+    const CODE: &[u8] = &[
+        0x60, 0x01, 0x54, 0x50, 0x60, 0x07, 0x60, 0x02, 0x55, 0x5f, 0x60, 0x03, 0x55, 0x00,
+    ];
+
+    fn evm(config: PrestateTracerConfig) -> Evm<'static, PrestateTracer, CacheDB<EmptyDB>> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balance: U256::from(1_000_000),
+                nonce: 1,
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(CODE))),
+        );
+        for (slot, value) in [(1, 1), (2, 2), (3, 3)] {
+            db.insert_account_storage(CONTRACT, U256::from(slot), U256::from(value))
+                .unwrap();
+        }
+        Evm::builder()
+            .with_db(db)
+            .with_external_context(PrestateTracer::new(config))
+            .append_handler_register(inspector_handle_register)
+            .modify_block_env(|block| {
+                block.coinbase = COINBASE;
+                block.basefee = U256::from(1);
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(CONTRACT);
+                tx.gas_limit = 100_000;
+                tx.gas_price = U256::from(2);
+            })
+            .build()
+    }
+
+    fn slot(value: u64) -> B256 {
+        U256::from(value).into()
+    }
+
+    #[test]
+    fn prestate() {
+        let mut evm = evm(PrestateTracerConfig::default());
+        let state = evm.transact().unwrap().state;
+        let PrestateFrame::Prestate(prestate) = evm.context.external.frame(&state) else {
+            panic!("expected prestate");
+        };
+
+        assert_eq!(prestate.len(), 3);
+        assert_eq!(
+            prestate[&CALLER],
+            AccountState {
+                balance: Some(U256::from(1_000_000)),
+                nonce: Some(1),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            prestate[&CONTRACT],
+            AccountState {
+                balance: Some(U256::ZERO),
+                code: Some(Bytes::from_static(CODE)),
+                nonce: Some(1),
+                storage: BTreeMap::from([
+                    (slot(1), slot(1)),
+                    (slot(2), slot(2)),
+                    (slot(3), slot(3)),
+                ]),
+            }
+        );
+        assert_eq!(
+            prestate[&COINBASE],
+            AccountState {
+                balance: Some(U256::ZERO),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn diff_mode() {
+        let mut evm = evm(PrestateTracerConfig {
+            diff_mode: true,
+            ..Default::default()
+        });
+        let result = evm.transact().unwrap();
+        let PrestateFrame::Diff { pre, post } = evm.context.external.frame(&result.state) else {
+            panic!("expected diff");
+        };
+        let fee = U256::from(2 * result.result.gas_used());
+
+        // coinbase did not exist before, so it is only in the post state.
+        assert_eq!(pre.keys().collect::<Vec<_>>(), vec![&CONTRACT, &CALLER]);
+        assert_eq!(
+            post.keys().collect::<Vec<_>>(),
+            vec![&CONTRACT, &CALLER, &COINBASE]
+        );
+
+        assert_eq!(
+            pre[&CONTRACT].storage,
+            BTreeMap::from([(slot(2), slot(2)), (slot(3), slot(3))])
+        );
+        // zeroed slot is omitted from the post state.
+        assert_eq!(
+            post[&CONTRACT],
+            AccountState {
+                storage: BTreeMap::from([(slot(2), slot(7))]),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            post[&CALLER],
+            AccountState {
+                balance: Some(U256::from(1_000_000) - fee),
+                nonce: Some(2),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            post[&COINBASE],
+            AccountState {
+                balance: Some(fee / U256::from(2)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn diff_mode_selfdestruct() {
+        let mut evm = evm(PrestateTracerConfig {
+            diff_mode: true,
+            ..Default::default()
+        });
+        // PUSH1 1 SLOAD POP PUSH1 2 SLOAD POP CALLER SELFDESTRUCT
+        let code =
+            Bytes::from_static(&[0x60, 0x01, 0x54, 0x50, 0x60, 0x02, 0x54, 0x50, 0x33, 0xff]);
+        evm.db_mut().insert_account_info(
+            CONTRACT,
+            AccountInfo::from_bytecode(Bytecode::new_raw(code)),
+        );
+        evm.modify_spec_id(SpecId::SHANGHAI);
+        let result = evm.transact().unwrap();
+        assert!(result.result.is_success());
+        let PrestateFrame::Diff { pre, post } = evm.context.external.frame(&result.state) else {
+            panic!("expected diff");
+        };
+
+        // accessed slots stay in the pre state of the destroyed account.
+        assert_eq!(
+            pre[&CONTRACT].storage,
+            BTreeMap::from([(slot(1), slot(1)), (slot(2), slot(2))])
+        );
+        assert!(!post.contains_key(&CONTRACT));
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn geth_format() {
+        let frame = PrestateFrame::Diff {
+            pre: BTreeMap::from([(
+                CONTRACT,
+                AccountState {
+                    balance: Some(U256::from(16)),
+                    code: Some(Bytes::from_static(&[0x00])),
+                    nonce: Some(1),
+                    storage: BTreeMap::from([(slot(1), slot(2))]),
+                },
+            )]),
+            post: BTreeMap::from([(
+                CONTRACT,
+                AccountState {
+                    nonce: Some(2),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pre":{"0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0":{"balance":"0x10","code":"0x00","nonce":1,"storage":{"0x0000000000000000000000000000000000000000000000000000000000000001":"0x0000000000000000000000000000000000000000000000000000000000000002"}}},"post":{"0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0":{"nonce":2}}}"#
+        );
+        assert_eq!(serde_json::from_str::<PrestateFrame>(&json).unwrap(), frame);
+    }
+}
@@ -0,0 +1,310 @@
+//! Inspector that finds where a revert originated and how it bubbled up through the callers.
+
+use crate::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs,
+        InstructionResult, Interpreter, InterpreterResult,
+    },
+    primitives::{db::Database, Address, Bytes, RevertReason},
+    EvmContext, Inspector,
+};
+use std::vec::Vec;
+
+/// Frame that reverted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RevertLocation {
+    /// Address of the frame, the created address for creations.
+    pub address: Address,
+    /// Program counter of the last executed instruction.
+    pub pc: usize,
+    /// Call depth, the transaction frame has depth zero.
+    pub depth: usize,
+}
+
+/// Revert and the frames that re-raised it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RevertTrace {
+    /// Frame that executed `REVERT` first with the data.
+    pub origin: RevertLocation,
+    /// Revert data.
+    pub output: Bytes,
+    /// Decoded revert data.
+    pub reason: RevertReason,
+    /// Callers that reverted with the same data, from the caller of the origin outwards.
+    pub bubbled: Vec<RevertLocation>,
+}
+
+/// Frame that is currently executed.
+#[derive(Clone, Debug)]
+struct Frame {
+    location: RevertLocation,
+    /// Last revert of a nested call, it bubbles up if the frame reverts with the same data.
+    child_revert: Option<RevertTrace>,
+    /// Whether `RETURNDATACOPY` was executed after the last nested call ended.
+    return_data_copied: bool,
+}
+
+/// [Inspector] that records where reverts originated.
+///
+/// A revert whose data is copied and returned unchanged by the caller, as Solidity does for
+/// failed calls to other contracts, is followed through the callers instead of reported as a
+/// new revert.
+#[derive(Clone, Debug, Default)]
+pub struct RevertTracer {
+    frames: Vec<Frame>,
+    revert: Option<RevertTrace>,
+    caught: Vec<RevertTrace>,
+}
+
+impl RevertTracer {
+    /// Creates a new tracer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the revert of the transaction, if it reverted.
+    pub fn revert(&self) -> Option<&RevertTrace> {
+        self.revert.as_ref()
+    }
+
+    /// Returns the reverts of nested calls that were handled by a caller, in the order they
+    /// stopped bubbling up.
+    pub fn caught(&self) -> &[RevertTrace] {
+        &self.caught
+    }
+
+    /// Clears the recorded reverts.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.revert = None;
+        self.caught.clear();
+    }
+
+    fn push_frame(&mut self, address: Address) {
+        self.frames.push(Frame {
+            location: RevertLocation {
+                address,
+                pc: 0,
+                depth: self.frames.len(),
+            },
+            child_revert: None,
+            return_data_copied: false,
+        });
+    }
+
+    fn end_frame(&mut self, result: &InterpreterResult) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        // Return data of the frame replaces the one the parent could copy.
+        if let Some(parent) = self.frames.last_mut() {
+            parent.return_data_copied = false;
+        }
+        let trace = if result.result == InstructionResult::Revert {
+            match frame.child_revert {
+                Some(mut trace) if frame.return_data_copied && trace.output == result.output => {
+                    trace.bubbled.push(frame.location);
+                    trace
+                }
+                child => {
+                    self.caught.extend(child);
+                    RevertTrace {
+                        origin: frame.location,
+                        output: result.output.clone(),
+                        reason: RevertReason::decode(&result.output),
+                        bubbled: Vec::new(),
+                    }
+                }
+            }
+        } else {
+            self.caught.extend(frame.child_revert);
+            return;
+        };
+        match self.frames.last_mut() {
+            Some(parent) => {
+                if let Some(previous) = parent.child_revert.replace(trace) {
+                    self.caught.push(previous);
+                }
+            }
+            None => self.revert = Some(trace),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for RevertTracer {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.location.address = interp.contract.target_address;
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.location.pc = interp.program_counter();
+            if interp.current_opcode() == opcode::RETURNDATACOPY {
+                frame.return_data_copied = true;
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if self.frames.is_empty() {
+            self.clear();
+        }
+        self.push_frame(inputs.target_address);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.end_frame(&outcome.result);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        if self.frames.is_empty() {
+            self.clear();
+        }
+        self.push_frame(Address::ZERO);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(&outcome.result);
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        if self.frames.is_empty() {
+            self.clear();
+        }
+        self.push_frame(Address::ZERO);
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end_frame(&outcome.result);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{bytes, PanicCode},
+        test_fixtures::{inspector_builder, Code, CHILD, REVERT, TARGET},
+    };
+
+    const MIDDLE: Address = Address::repeat_byte(0xbb);
+
+    /// Calls the address and reverts with the returned data if the call failed.
+    fn forwarding_code(callee: Address) -> Bytes {
+        Code::new()
+            .call(callee, 0)
+            .forward_revert()
+            .op(0x00)
+            .build()
+    }
+
+    /// Calls the address and ignores the result, then reverts without data.
+    fn catching_code(callee: Address) -> Bytes {
+        Code::new().call(callee, 0).op(0x50).revert().build()
+    }
+
+    /// Panics with an arithmetic overflow.
+    fn panic_code() -> Bytes {
+        bytes!("634e487b7160e01b600052601160045260246000fd")
+    }
+
+    fn run(outer: Bytes, middle: Bytes) -> RevertTracer {
+        let contracts = [(TARGET, outer), (MIDDLE, middle), (CHILD, panic_code())];
+        let mut evm = inspector_builder(RevertTracer::new(), contracts).build();
+        let result = evm.transact().unwrap().result;
+        let tracer = evm.into_context().external;
+        assert_eq!(
+            result.revert_reason(),
+            tracer.revert().map(|r| r.reason.clone())
+        );
+        tracer
+    }
+
+    #[test]
+    fn bubbled_revert() {
+        let tracer = run(forwarding_code(MIDDLE), forwarding_code(CHILD));
+        let revert = tracer.revert().unwrap();
+        assert_eq!(
+            revert.origin,
+            RevertLocation {
+                address: CHILD,
+                pc: 20,
+                depth: 2
+            }
+        );
+        assert_eq!(
+            revert.reason.panic_code(),
+            Some(PanicCode::ArithmeticOverflow)
+        );
+        let bubbled: Vec<_> = revert
+            .bubbled
+            .iter()
+            .map(|l| (l.address, l.depth))
+            .collect();
+        assert_eq!(bubbled, [(MIDDLE, 1), (TARGET, 0)]);
+        assert!(revert.bubbled.iter().all(|l| l.pc == 45));
+        assert!(tracer.caught().is_empty());
+    }
+
+    #[test]
+    fn caught_revert() {
+        let tracer = run(forwarding_code(MIDDLE), catching_code(CHILD));
+        let revert = tracer.revert().unwrap();
+        assert_eq!(revert.origin.address, MIDDLE);
+        assert_eq!(revert.reason, RevertReason::Empty);
+        assert_eq!(revert.bubbled.len(), 1);
+
+        assert_eq!(tracer.caught().len(), 1);
+        assert_eq!(tracer.caught()[0].origin.address, CHILD);
+        assert!(tracer.caught()[0].bubbled.is_empty());
+    }
+
+    #[test]
+    fn handled_failure() {
+        // Same empty data as the failed call, but not copied from it.
+        let tracer = run(catching_code(MIDDLE), REVERT.into());
+        let revert = tracer.revert().unwrap();
+        assert_eq!(revert.origin.address, TARGET);
+        assert!(revert.bubbled.is_empty());
+
+        assert_eq!(tracer.caught().len(), 1);
+        assert_eq!(tracer.caught()[0].origin.address, MIDDLE);
+    }
+}
@@ -0,0 +1,508 @@
+//! SecurityInspector. Flags risky runtime behaviour of the executed contracts.
+
+use crate::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, EOFCreateInputs,
+        Interpreter,
+    },
+    primitives::{db::Database, Address, HashSet, U256},
+    EvmContext, Inspector, JournalEntry,
+};
+use core::fmt;
+use std::vec::Vec;
+
+/// Risky behaviour found by [SecurityInspector].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum SecurityIssue {
+    /// Contract called while an outer frame of it has not finished and has written to storage.
+    Reentrancy {
+        /// Slots written by the outer frames of the contract so far.
+        pending_writes: Vec<U256>,
+    },
+    /// `tx.origin` compared with `EQ`, as done by `require(tx.origin == owner)`.
+    TxOriginAuth,
+    /// Result of a failed call discarded with `POP` right after the call.
+    UncheckedCallFailure {
+        /// Called address.
+        target: Address,
+    },
+    /// `DELEGATECALL` or `CALLCODE` of code that is not trusted.
+    UntrustedDelegateCall {
+        /// Address of the executed code.
+        code_address: Address,
+    },
+    /// `SELFDESTRUCT` in code that is not trusted.
+    SelfDestruct {
+        /// Address receiving the balance.
+        beneficiary: Address,
+    },
+    /// Call that forwarded more than half of the gas and where the callee consumed all of it.
+    GasGriefing {
+        /// Called address.
+        target: Address,
+        /// Gas consumed by the callee.
+        gas_used: u64,
+    },
+}
+
+impl fmt::Display for SecurityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reentrancy { pending_writes } => write!(
+                f,
+                "reentrancy with {} pending storage writes",
+                pending_writes.len()
+            ),
+            Self::TxOriginAuth => f.write_str("authorization with tx.origin"),
+            Self::UncheckedCallFailure { target } => {
+                write!(f, "unchecked failed call to {target}")
+            }
+            Self::UntrustedDelegateCall { code_address } => {
+                write!(f, "delegatecall to untrusted code at {code_address}")
+            }
+            Self::SelfDestruct { beneficiary } => write!(f, "selfdestruct to {beneficiary}"),
+            Self::GasGriefing { target, gas_used } => {
+                write!(
+                    f,
+                    "call to {target} consumed all forwarded gas ({gas_used})"
+                )
+            }
+        }
+    }
+}
+
+/// Issue and where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct SecurityFinding {
+    /// Found issue.
+    pub issue: SecurityIssue,
+    /// Contract whose frame executed the instruction.
+    pub address: Address,
+    /// Program counter of the instruction.
+    pub pc: usize,
+    /// Addresses of the frames from the transaction frame to the contract.
+    pub call_path: Vec<Address>,
+}
+
+impl fmt::Display for SecurityFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{} via ", self.issue, self.address, self.pc)?;
+        for (i, address) in self.call_path.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" > ")?;
+            }
+            write!(f, "{address}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Frame that is currently executed.
+#[derive(Clone, Debug)]
+struct Frame {
+    address: Address,
+    /// Number of journal entry sets when the frame was called, later sets belong to it.
+    journal_len: usize,
+    pc: usize,
+    /// Whether `ORIGIN` was executed by the frame.
+    read_origin: bool,
+    /// Gas remaining before the last call of the frame.
+    gas_before_call: u64,
+    /// Target of the last call of the frame if it failed and the next instruction was not
+    /// executed yet.
+    failed_call: Option<Address>,
+}
+
+/// [Inspector] that flags risky runtime behaviour during fork tests.
+///
+/// The checks are heuristics on the executed instructions, they can have false positives.
+/// Addresses marked as trusted with [SecurityInspector::with_trusted] can be delegate called
+/// and self destruct without findings.
+#[derive(Clone, Debug, Default)]
+pub struct SecurityInspector {
+    trusted: HashSet<Address>,
+    frames: Vec<Frame>,
+    findings: Vec<SecurityFinding>,
+}
+
+impl SecurityInspector {
+    /// Creates a new inspector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the code at the addresses as trusted.
+    pub fn with_trusted(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.trusted.extend(addresses);
+        self
+    }
+
+    /// Returns the findings, in execution order.
+    pub fn findings(&self) -> &[SecurityFinding] {
+        &self.findings
+    }
+
+    /// Takes the findings.
+    pub fn take_findings(&mut self) -> Vec<SecurityFinding> {
+        core::mem::take(&mut self.findings)
+    }
+
+    /// Records a finding at the last executed instruction of the current frame.
+    fn flag(&mut self, issue: SecurityIssue) {
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        self.findings.push(SecurityFinding {
+            issue,
+            address: frame.address,
+            pc: frame.pc,
+            call_path: self.frames.iter().map(|frame| frame.address).collect(),
+        });
+    }
+
+    fn push_frame<DB: Database>(&mut self, context: &EvmContext<DB>, address: Address) {
+        if self.frames.is_empty() {
+            self.findings.clear();
+        }
+        self.frames.push(Frame {
+            address,
+            journal_len: context.journaled_state.journal.len(),
+            pc: 0,
+            read_origin: false,
+            gas_before_call: 0,
+            failed_call: None,
+        });
+    }
+}
+
+impl<DB: Database> Inspector<DB> for SecurityInspector {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.address = interp.contract.target_address;
+        }
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        frame.pc = interp.program_counter();
+        let failed_call = frame.failed_call.take();
+
+        match op {
+            opcode::POP => {
+                if let Some(target) = failed_call {
+                    self.flag(SecurityIssue::UncheckedCallFailure { target });
+                }
+            }
+            opcode::ORIGIN => frame.read_origin = true,
+            opcode::EQ if frame.read_origin => {
+                let origin = U256::from_be_slice(context.env.tx.caller.as_slice());
+                if interp.stack.peek(0) == Ok(origin) || interp.stack.peek(1) == Ok(origin) {
+                    self.flag(SecurityIssue::TxOriginAuth);
+                }
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                frame.gas_before_call = interp.gas.remaining();
+            }
+            opcode::SELFDESTRUCT => {
+                let contract = &interp.contract;
+                let code_address = contract.bytecode_address.unwrap_or(contract.target_address);
+                if !self.trusted.contains(&code_address) {
+                    if let Ok(beneficiary) = interp.stack.peek(0) {
+                        self.flag(SecurityIssue::SelfDestruct {
+                            beneficiary: Address::from_word(beneficiary.into()),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let target = inputs.target_address;
+        match inputs.scheme {
+            CallScheme::DelegateCall | CallScheme::CallCode | CallScheme::ExtDelegateCall => {
+                if !self.trusted.contains(&inputs.bytecode_address) {
+                    self.flag(SecurityIssue::UntrustedDelegateCall {
+                        code_address: inputs.bytecode_address,
+                    });
+                }
+            }
+            _ => {
+                if let Some(outer) = self.frames.iter().position(|frame| frame.address == target) {
+                    // Writes of the outer frames of the target that are still in flight.
+                    let journal = &context.journaled_state.journal;
+                    let mut pending_writes: Vec<U256> = journal[self.frames[outer].journal_len..]
+                        .iter()
+                        .flatten()
+                        .filter_map(|entry| match entry {
+                            JournalEntry::StorageChanged { address, key, .. }
+                                if *address == target =>
+                            {
+                                Some(*key)
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    pending_writes.sort_unstable();
+                    pending_writes.dedup();
+                    if !pending_writes.is_empty() {
+                        self.flag(SecurityIssue::Reentrancy { pending_writes });
+                    }
+                }
+            }
+        }
+        self.push_frame(context, target);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frames.pop();
+        let target = inputs.target_address;
+        let result = &outcome.result;
+        if !result.is_ok() {
+            if let Some(frame) = self.frames.last_mut() {
+                frame.failed_call = Some(target);
+                let forwarded_most = inputs.gas_limit > frame.gas_before_call / 2;
+                // Halted calls consume all of their gas.
+                if forwarded_most && !result.is_revert() {
+                    self.flag(SecurityIssue::GasGriefing {
+                        target,
+                        gas_used: inputs.gas_limit,
+                    });
+                }
+            }
+        }
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.push_frame(context, Address::ZERO);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frames.pop();
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.push_frame(context, Address::ZERO);
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.frames.pop();
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{hex, keccak256, Bytes, SpecId, B256},
+        test_fixtures::{eof, inspector_builder, Code, CALLER, CHILD, REVERT, TARGET},
+    };
+
+    const OTHER: Address = Address::repeat_byte(0xbb);
+    const BOMB: Address = Address::repeat_byte(0xdd);
+
+    /// CALL(GAS, address, 0, 0, 0, 0, 0) POP
+    fn call(address: Address) -> Code {
+        Code::new().call(address, 0).op(0x50)
+    }
+
+    fn run(contracts: [(Address, Code); 2], inspector: SecurityInspector) -> SecurityInspector {
+        let contracts = contracts
+            .map(|(address, code)| (address, code.build()))
+            .into_iter()
+            .chain([(CHILD, REVERT.into()), (BOMB, Bytes::from_static(&[0xfe]))]);
+        let mut evm = inspector_builder(inspector, contracts).build();
+        assert!(evm.transact().unwrap().result.is_success());
+        evm.into_context().external
+    }
+
+    /// Stops if called by OTHER, otherwise runs the code and calls OTHER.
+    fn reentrant(code: Code) -> Code {
+        Code::new()
+            .op(0x33)
+            .push_address(OTHER)
+            .op(0x14)
+            .push(u8::try_from(code.len() + 61).unwrap())
+            .op(0x57)
+            .append(code)
+            .append(call(OTHER))
+            .op(0x00)
+            .op(0x5b)
+            .op(0x00)
+    }
+
+    #[test]
+    fn reentrancy() {
+        // SSTORE(1, 1)
+        let target = reentrant(Code::new().push(1).push(1).op(0x55));
+        let inspector = run(
+            [(TARGET, target), (OTHER, call(TARGET))],
+            SecurityInspector::new(),
+        );
+        assert_eq!(
+            inspector.findings(),
+            [SecurityFinding {
+                issue: SecurityIssue::Reentrancy {
+                    pending_writes: vec![U256::from(1)]
+                },
+                address: OTHER,
+                pc: 32,
+                call_path: vec![TARGET, OTHER],
+            }]
+        );
+        assert_eq!(
+            inspector.findings()[0].to_string(),
+            format!(
+                "reentrancy with 1 pending storage writes at {OTHER}:32 via {TARGET} > {OTHER}"
+            )
+        );
+    }
+
+    #[test]
+    fn reentrancy_without_writes() {
+        let inspector = run(
+            [(TARGET, reentrant(Code::new())), (OTHER, call(TARGET))],
+            SecurityInspector::new(),
+        );
+        assert!(inspector.findings().is_empty());
+    }
+
+    #[test]
+    fn risky_instructions() {
+        let target = Code::new()
+            .append(call(CHILD))
+            .append(call(BOMB))
+            // DELEGATECALL(GAS, OTHER, 0, 0, 0, 0) POP
+            .bytes(&hex!("600060006000600073"))
+            .bytes(OTHER.as_slice())
+            .bytes(&hex!("5af450"))
+            // ORIGIN == CALLER
+            .op(0x32)
+            .push_address(CALLER)
+            .bytes(&hex!("1450"))
+            // SELFDESTRUCT(CALLER)
+            .push_address(CALLER)
+            .op(0xff);
+        let issues = |inspector: &SecurityInspector| -> Vec<_> {
+            inspector
+                .findings()
+                .iter()
+                .map(|finding| finding.issue.clone())
+                .collect()
+        };
+
+        let inspector = run(
+            [(TARGET, target.clone()), (OTHER, Code::new())],
+            SecurityInspector::new(),
+        );
+        let SecurityIssue::GasGriefing { gas_used, .. } = inspector.findings()[1].issue else {
+            panic!("expected gas griefing");
+        };
+        assert!(gas_used > 900_000);
+        assert_eq!(
+            issues(&inspector),
+            [
+                SecurityIssue::UncheckedCallFailure { target: CHILD },
+                SecurityIssue::GasGriefing {
+                    target: BOMB,
+                    gas_used,
+                },
+                SecurityIssue::UncheckedCallFailure { target: BOMB },
+                SecurityIssue::UntrustedDelegateCall {
+                    code_address: OTHER
+                },
+                SecurityIssue::TxOriginAuth,
+                SecurityIssue::SelfDestruct {
+                    beneficiary: CALLER
+                },
+            ]
+        );
+        assert!(inspector.findings().iter().all(|f| f.call_path == [TARGET]));
+
+        let inspector = run(
+            [(TARGET, target), (OTHER, Code::new())],
+            SecurityInspector::new().with_trusted([TARGET, OTHER]),
+        );
+        assert_eq!(issues(&inspector).len(), 4);
+    }
+
+    #[test]
+    fn eofcreate() {
+        // ORIGIN == CALLER
+        let origin_auth = Code::new()
+            .op(0x32)
+            .push_address(CALLER)
+            .bytes(&hex!("1450"));
+        let init = eof(origin_auth.clone().revert(), []);
+        // EOFCREATE(0, 0, 0, 0) of the subcontainer
+        let code = Code::new()
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(0)
+            .bytes(&[0xec, 0x00, 0x50])
+            .append(origin_auth)
+            .op(0x00);
+        let contracts = [(TARGET, eof(code, [init.clone()]))];
+        let mut evm = inspector_builder(SecurityInspector::new(), contracts)
+            .with_spec_id(SpecId::PRAGUE_EOF)
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        let created = TARGET.create2(B256::ZERO, keccak256(&init));
+        let finding = |address, pc, call_path| SecurityFinding {
+            issue: SecurityIssue::TxOriginAuth,
+            address,
+            pc,
+            call_path,
+        };
+        assert_eq!(
+            evm.context.external.findings(),
+            [
+                finding(created, 22, vec![TARGET, created]),
+                finding(TARGET, 33, vec![TARGET]),
+            ]
+        );
+    }
+}
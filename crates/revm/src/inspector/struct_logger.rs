@@ -0,0 +1,596 @@
+//! StructLogger. Logs executed instructions in the format of geth default `structLogger`.
+
+use super::call_tracer::halt_error;
+use crate::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+        OpCode, SuccessOrHalt,
+    },
+    primitives::{db::Database, Address, Bytes, ExecutionResult, B256, U256},
+    EvmContext, Inspector,
+};
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Options of the [StructLogger], named as in geth logger config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct StructLoggerConfig {
+    /// Include memory of the frame in every step.
+    pub enable_memory: bool,
+    /// Do not include the stack.
+    pub disable_stack: bool,
+    /// Do not include the storage accessed by `SLOAD` and `SSTORE`.
+    pub disable_storage: bool,
+    /// Include data returned by the last call of the frame.
+    pub enable_return_data: bool,
+}
+
+/// Executed instruction, fields are ordered and named as in geth struct log output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct StructLog {
+    /// Program counter.
+    pub pc: u64,
+    /// Opcode, serialized as its name.
+    #[cfg_attr(feature = "serde", serde(with = "opcode_name"))]
+    pub op: u8,
+    /// Gas left before executing the instruction.
+    pub gas: u64,
+    /// Gas cost of the instruction, including gas given to the nested call.
+    pub gas_cost: u64,
+    /// Depth of the call frame, starting from 1.
+    pub depth: u64,
+    /// Error if the instruction failed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<String>,
+    /// Stack before executing the instruction, top item last.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub stack: Option<Vec<U256>>,
+    /// Data returned by the last call of the frame.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub return_data: Option<Bytes>,
+    /// Memory before executing the instruction, as 32-byte words.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", with = "words")
+    )]
+    pub memory: Option<Vec<B256>>,
+    /// Storage slots of the contract accessed so far, set for `SLOAD` and `SSTORE`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", with = "storage")
+    )]
+    pub storage: Option<BTreeMap<B256, B256>>,
+    /// Gas refund counter of the transaction before executing the instruction.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_zero"))]
+    pub refund: u64,
+}
+
+impl StructLog {
+    /// Returns the name of the opcode, as geth names it.
+    pub fn op_name(&self) -> String {
+        match OpCode::new(self.op) {
+            Some(op) => op.as_str().to_string(),
+            None => format!("opcode {:#x} not defined", self.op),
+        }
+    }
+}
+
+/// Result of the transaction with the struct logs, named as in geth output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct StructLoggerResult {
+    /// Gas used by the transaction.
+    pub gas: u64,
+    /// True if the transaction did not succeed.
+    pub failed: bool,
+    /// Output of the transaction, revert data if it reverted.
+    pub return_value: Bytes,
+    /// Executed instructions.
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// [Inspector] that logs every executed instruction as geth `structLogger` does.
+///
+/// For the EIP-3155 line format see [TracerEip3155](crate::inspectors::TracerEip3155),
+/// the logs can be converted between the two formats.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructLogger {
+    config: StructLoggerConfig,
+    logs: Vec<StructLog>,
+    /// Storage slots accessed so far by every contract.
+    storage: BTreeMap<Address, BTreeMap<B256, B256>>,
+    /// Key of the `SLOAD` that is being executed.
+    sload_key: Option<U256>,
+    /// Refund of the transaction when every frame started, the interpreter counts only the
+    /// refunds of its own frame.
+    parent_refunds: Vec<i64>,
+    /// Refund of the current frame at its last instruction.
+    frame_refund: i64,
+}
+
+impl StructLogger {
+    /// Create new logger with the given options.
+    pub fn new(config: StructLoggerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options of the logger.
+    pub fn config(&self) -> &StructLoggerConfig {
+        &self.config
+    }
+
+    /// Returns logs of the executed instructions.
+    pub fn logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    /// Takes the logs, resetting the logger for the next transaction.
+    pub fn take_logs(&mut self) -> Vec<StructLog> {
+        self.storage.clear();
+        core::mem::take(&mut self.logs)
+    }
+
+    /// Records the refund of the transaction when the frame starts, the logs of the previous
+    /// transaction are cleared when the top frame starts.
+    fn frame_start<DB: Database>(&mut self, context: &EvmContext<DB>) {
+        if context.journaled_state.depth() == 0 {
+            self.logs.clear();
+            self.storage.clear();
+            self.parent_refunds.clear();
+            self.frame_refund = 0;
+        }
+        let refund = self.parent_refund() + self.frame_refund;
+        self.parent_refunds.push(refund);
+    }
+
+    /// Returns the refund of the transaction when the current frame started.
+    fn parent_refund(&self) -> i64 {
+        self.parent_refunds.last().copied().unwrap_or_default()
+    }
+
+    /// Returns the result of the transaction with the logs, as geth `debug_traceTransaction` does.
+    ///
+    /// `result` is the result of the execution of the transaction.
+    pub fn result(&self, result: &ExecutionResult) -> StructLoggerResult {
+        StructLoggerResult {
+            gas: result.gas_used(),
+            failed: !result.is_success(),
+            return_value: result.output().cloned().unwrap_or_default(),
+            struct_logs: self.logs.clone(),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogger {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let stack = interp.stack().data();
+        self.frame_refund = interp.gas.refunded();
+        let refund = self.parent_refund() + self.frame_refund;
+        self.logs.push(StructLog {
+            pc: interp.program_counter() as u64,
+            op: interp.current_opcode(),
+            gas: interp.gas.remaining(),
+            depth: context.journaled_state.depth(),
+            stack: (!self.config.disable_stack).then(|| stack.clone()),
+            return_data: (self.config.enable_return_data && !interp.return_data_buffer.is_empty())
+                .then(|| interp.return_data_buffer.clone()),
+            memory: self.config.enable_memory.then(|| {
+                interp
+                    .shared_memory
+                    .context_memory()
+                    .chunks(32)
+                    .map(B256::right_padding_from)
+                    .collect()
+            }),
+            // Refunds of reverted frames are discarded, the sum is not negative.
+            refund: u64::try_from(refund).unwrap_or_default(),
+            ..Default::default()
+        });
+        if self.config.disable_storage {
+            return;
+        }
+        // `SSTORE` writes the slot from the stack, `SLOAD` result is known at the step end.
+        match (interp.current_opcode(), stack.as_slice()) {
+            (opcode::SLOAD, [.., key]) => self.sload_key = Some(*key),
+            (opcode::SSTORE, [.., value, key]) => {
+                let storage = self
+                    .storage
+                    .entry(interp.contract.target_address)
+                    .or_default();
+                storage.insert((*key).into(), (*value).into());
+                if let Some(log) = self.logs.last_mut() {
+                    log.storage = Some(storage.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Some(log) = self.logs.last_mut() else {
+            return;
+        };
+        log.gas_cost = log.gas.saturating_sub(interp.gas.remaining());
+        match SuccessOrHalt::from(interp.instruction_result) {
+            SuccessOrHalt::Revert => log.error = Some("execution reverted".to_string()),
+            SuccessOrHalt::Halt(reason) => log.error = Some(halt_error(reason)),
+            _ => {}
+        }
+
+        let (Some(key), Ok(value)) = (self.sload_key.take(), interp.stack().peek(0)) else {
+            return;
+        };
+        if log.error.is_some() {
+            return;
+        }
+        let storage = self
+            .storage
+            .entry(interp.contract.target_address)
+            .or_default();
+        storage.insert(key.into(), value.into());
+        log.storage = Some(storage.clone());
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.frame_start(context);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.parent_refunds.pop();
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start(context);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.parent_refunds.pop();
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frame_start(context);
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.parent_refunds.pop();
+        outcome
+    }
+}
+
+#[cfg(feature = "serde")]
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// (De)serialize opcode as its name.
+#[cfg(feature = "serde")]
+mod opcode_name {
+    use crate::interpreter::OpCode;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::string::String;
+
+    pub(super) fn serialize<S: Serializer>(op: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+        match OpCode::new(*op) {
+            Some(op) => serializer.serialize_str(op.as_str()),
+            None => serializer.collect_str(&format_args!("opcode {op:#x} not defined")),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        if let Some(op) =
+            (0..=u8::MAX).find(|op| OpCode::new(*op).map(OpCode::as_str) == Some(&name))
+        {
+            return Ok(op);
+        }
+        name.strip_prefix("opcode 0x")
+            .and_then(|name| name.strip_suffix(" not defined"))
+            .and_then(|op| u8::from_str_radix(op, 16).ok())
+            .ok_or_else(|| de::Error::custom("unknown opcode"))
+    }
+}
+
+/// (De)serialize memory as 32-byte words in hex without `0x` prefix, as geth does.
+#[cfg(feature = "serde")]
+mod words {
+    use crate::primitives::{hex, B256};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::{string::String, vec::Vec};
+
+    pub(super) fn serialize<S: Serializer>(
+        words: &Option<Vec<B256>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match words {
+            Some(words) => serializer.collect_seq(words.iter().map(hex::encode)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<B256>>, D::Error> {
+        let Some(words) = Option::<Vec<String>>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        words
+            .iter()
+            .map(|word| word.parse().map_err(de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+/// (De)serialize storage slots in hex without `0x` prefix, as geth does.
+#[cfg(feature = "serde")]
+mod storage {
+    use crate::primitives::{hex, B256};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::{collections::BTreeMap, string::String};
+
+    pub(super) fn serialize<S: Serializer>(
+        storage: &Option<BTreeMap<B256, B256>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match storage {
+            Some(storage) => serializer.collect_map(
+                storage
+                    .iter()
+                    .map(|(key, value)| (hex::encode(key), hex::encode(value))),
+            ),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BTreeMap<B256, B256>>, D::Error> {
+        let Some(storage) = Option::<BTreeMap<String, String>>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        storage
+            .iter()
+            .map(|(key, value)| {
+                Ok((
+                    key.parse().map_err(de::Error::custom)?,
+                    value.parse().map_err(de::Error::custom)?,
+                ))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{AccountInfo, Bytecode, TxKind},
+        test_fixtures::{inspector_builder, Code, CHILD, TARGET},
+        Evm,
+    };
+    use std::vec;
+
+    const CALLER: Address = Address::repeat_byte(0xca);
+    const CONTRACT: Address = Address::repeat_byte(0xc0);
+
+    /// PUSH1 0x2a PUSH1 1 SSTORE PUSH1 1 SLOAD PUSH0 MSTORE PUSH1 32 PUSH0 RETURN
+    const CODE: &[u8] = &[
+        0x60, 0x2a, 0x60, 0x01, 0x55, 0x60, 0x01, 0x54, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3,
+    ];
+
+    fn trace(config: StructLoggerConfig) -> StructLoggerResult {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(CODE))),
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(StructLogger::new(config))
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TxKind::Call(CONTRACT);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        evm.context.external.result(&result)
+    }
+
+    fn word(value: u64) -> B256 {
+        U256::from(value).into()
+    }
+
+    #[test]
+    fn struct_logs() {
+        let result = trace(StructLoggerConfig {
+            enable_memory: true,
+            enable_return_data: true,
+            ..Default::default()
+        });
+        assert!(!result.failed);
+        assert_eq!(result.return_value, Bytes::from(word(0x2a)));
+
+        let logs = result.struct_logs;
+        assert_eq!(logs.len(), 10);
+        assert_eq!(logs[0].op_name(), "PUSH1");
+        assert_eq!(
+            (logs[0].gas, logs[0].gas_cost, logs[0].depth),
+            (79_000, 3, 1)
+        );
+        assert_eq!(logs[1].gas, 79_000 - 3);
+        assert_eq!(logs[2].stack, Some(vec![U256::from(0x2a), U256::from(1)]));
+
+        let storage = Some(BTreeMap::from([(word(1), word(0x2a))]));
+        assert_eq!(logs[2].storage, storage);
+        assert_eq!(logs[3].storage, None);
+        assert_eq!(logs[4].storage, storage);
+
+        assert_eq!(logs[6].memory, Some(vec![]));
+        assert_eq!(logs[7].memory, Some(vec![word(0x2a)]));
+        assert!(logs.iter().all(|log| log.return_data.is_none()));
+        assert!(logs.iter().all(|log| log.error.is_none()));
+    }
+
+    #[test]
+    fn transaction_refund() {
+        // SSTORE(1, 0)
+        let clear = Code::new().push(0).push(1).op(0x55);
+        let target = clear.clone().call(CHILD, 0).op(0x50).op(0x00);
+        let contracts = [(TARGET, target.build()), (CHILD, clear.op(0x00).build())];
+        let mut evm = inspector_builder(StructLogger::default(), contracts)
+            .modify_db(|db| {
+                for address in [TARGET, CHILD] {
+                    db.insert_account_storage(address, U256::from(1), U256::from(1))
+                        .unwrap();
+                }
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        let refunds: Vec<_> = evm
+            .context
+            .external
+            .logs()
+            .iter()
+            .filter(|log| matches!(log.op, opcode::SSTORE | opcode::STOP | opcode::POP))
+            .map(|log| (log.op_name(), log.depth, log.refund))
+            .collect();
+        assert_eq!(
+            refunds,
+            [
+                ("SSTORE".into(), 1, 0),
+                ("SSTORE".into(), 2, 4800),
+                ("STOP".into(), 2, 9600),
+                ("POP".into(), 1, 9600),
+                ("STOP".into(), 1, 9600),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_fields() {
+        let logs = trace(StructLoggerConfig::default()).struct_logs;
+        assert!(logs.iter().all(|log| log.memory.is_none()));
+        assert!(logs[2].stack.is_some() && logs[2].storage.is_some());
+
+        let logs = trace(StructLoggerConfig {
+            disable_stack: true,
+            disable_storage: true,
+            ..Default::default()
+        })
+        .struct_logs;
+        assert!(logs
+            .iter()
+            .all(|log| log.stack.is_none() && log.storage.is_none()));
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn geth_format() {
+        let log = StructLog {
+            pc: 7,
+            op: opcode::SLOAD,
+            gas: 100,
+            gas_cost: 2100,
+            depth: 1,
+            error: Some("out of gas".into()),
+            stack: Some(vec![U256::from(1)]),
+            return_data: None,
+            memory: Some(vec![word(0x2a)]),
+            storage: Some(BTreeMap::from([(word(1), word(2))])),
+            refund: 0,
+        };
+        let json = serde_json::to_string(&log).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pc":7,"op":"SLOAD","gas":100,"gasCost":2100,"depth":1,"error":"out of gas","stack":["0x1"],"memory":["000000000000000000000000000000000000000000000000000000000000002a"],"storage":{"0000000000000000000000000000000000000000000000000000000000000001":"0000000000000000000000000000000000000000000000000000000000000002"}}"#
+        );
+        assert_eq!(serde_json::from_str::<StructLog>(&json).unwrap(), log);
+    }
+
+    #[cfg(all(feature = "std", feature = "serde-json"))]
+    #[test]
+    fn eip3155_conversion() {
+        use crate::inspectors::Eip3155Step;
+
+        let logs = trace(StructLoggerConfig {
+            enable_memory: true,
+            ..Default::default()
+        })
+        .struct_logs;
+        for log in &logs {
+            let step = Eip3155Step::from(log);
+            assert_eq!(StructLog::try_from(&step).unwrap(), *log);
+        }
+
+        let step = Eip3155Step::from(&logs[6]);
+        assert_eq!(step.op_name, Some("MSTORE"));
+        assert_eq!(step.gas_cost, "0x6");
+        assert_eq!(step.stack, vec!["0x2a", "0x0"]);
+
+        let step = Eip3155Step::from(&logs[7]);
+        assert_eq!(step.mem_size, "32");
+        assert_eq!(
+            step.memory.as_deref(),
+            Some("0x000000000000000000000000000000000000000000000000000000000000002a")
+        );
+    }
+}
@@ -0,0 +1,523 @@
+//! TokenFlowInspector. Collects ETH and token transfers and the resulting balance changes.
+
+use super::transfer::TRANSFER_EVENT_TOPIC;
+use crate::{
+    abi::{self, AbiType, AbiValue},
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+    },
+    primitives::{b256, db::Database, Address, Log, B256, I256, U256},
+    EvmContext, Inspector,
+};
+use std::{collections::BTreeMap, vec::Vec};
+
+/// Topic of the ERC-1155 `TransferSingle(address,address,address,uint256,uint256)` event.
+pub const TRANSFER_SINGLE_EVENT_TOPIC: B256 =
+    b256!("c3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62");
+
+/// Topic of the ERC-1155 `TransferBatch(address,address,address,uint256[],uint256[])` event.
+pub const TRANSFER_BATCH_EVENT_TOPIC: B256 =
+    b256!("4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb");
+
+/// Asset that is transferred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Asset {
+    /// Native ETH.
+    Native,
+    /// ERC-20 token.
+    Erc20(Address),
+    /// ERC-721 token with its id.
+    Erc721 {
+        /// Token contract.
+        token: Address,
+        /// Token id.
+        id: U256,
+    },
+    /// ERC-1155 token with its id.
+    Erc1155 {
+        /// Token contract.
+        token: Address,
+        /// Token id.
+        id: U256,
+    },
+}
+
+/// Transfer of an asset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TokenTransfer {
+    /// Transferred asset.
+    pub asset: Asset,
+    /// Sender.
+    pub from: Address,
+    /// Recipient.
+    pub to: Address,
+    /// Transferred amount, one for ERC-721 tokens.
+    pub amount: U256,
+    /// Addresses of the frames from the transaction frame to the frame that made the transfer,
+    /// empty for the value of the transaction.
+    pub call_path: Vec<Address>,
+}
+
+/// [Inspector] that collects the transfers of ETH and of ERC-20, ERC-721 and ERC-1155 tokens,
+/// to show what a transaction does to balances.
+///
+/// ETH transfers are the values of calls and creations, and the balances sent by
+/// `SELFDESTRUCT`. Token transfers are decoded from the `Transfer`, `TransferSingle` and
+/// `TransferBatch` events. Transfers of reverted frames are discarded.
+#[derive(Clone, Debug, Default)]
+pub struct TokenFlowInspector {
+    transfers: Vec<TokenTransfer>,
+    /// Address of each active frame, with the number of transfers when it started.
+    frames: Vec<(Address, usize)>,
+}
+
+impl TokenFlowInspector {
+    /// Creates a new inspector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the transfers, in execution order.
+    pub fn transfers(&self) -> &[TokenTransfer] {
+        &self.transfers
+    }
+
+    /// Takes the transfers and resets the inspector.
+    pub fn take_transfers(&mut self) -> Vec<TokenTransfer> {
+        self.frames.clear();
+        core::mem::take(&mut self.transfers)
+    }
+
+    /// Returns the net balance change of every address and asset with a non zero change.
+    pub fn balance_deltas(&self) -> BTreeMap<Address, BTreeMap<Asset, I256>> {
+        let mut deltas: BTreeMap<Address, BTreeMap<Asset, I256>> = BTreeMap::new();
+        for transfer in &self.transfers {
+            let amount = I256::try_from(transfer.amount).unwrap_or(I256::MAX);
+            for (address, amount) in [(transfer.from, -amount), (transfer.to, amount)] {
+                let delta = deltas
+                    .entry(address)
+                    .or_default()
+                    .entry(transfer.asset)
+                    .or_default();
+                *delta = delta.saturating_add(amount);
+            }
+        }
+        for assets in deltas.values_mut() {
+            assets.retain(|_, delta| !delta.is_zero());
+        }
+        deltas.retain(|_, assets| !assets.is_empty());
+        deltas
+    }
+
+    fn push(&mut self, asset: Asset, from: Address, to: Address, amount: U256) {
+        if amount.is_zero() || from == to {
+            return;
+        }
+        self.transfers.push(TokenTransfer {
+            asset,
+            from,
+            to,
+            amount,
+            call_path: self.frames.iter().map(|(address, _)| *address).collect(),
+        });
+    }
+
+    /// Ends the frame, discarding its transfers if it did not succeed.
+    fn frame_end(&mut self, success: bool) {
+        let (_, checkpoint) = self.frames.pop().unwrap_or_default();
+        if !success {
+            self.transfers.truncate(checkpoint);
+        }
+    }
+
+    /// Ends a creation frame, the value transfer is recorded only if the creation succeeded.
+    fn create_frame_end(&mut self, caller: Address, value: U256, outcome: &CreateOutcome) {
+        let (_, checkpoint) = self.frames.pop().unwrap_or_default();
+        match (outcome.result.is_ok(), outcome.address) {
+            (true, Some(address)) => {
+                // Created address is known only now, transfer is placed before the ones of the
+                // constructor.
+                let start = self.transfers.len();
+                self.push(Asset::Native, caller, address, value);
+                let pushed = self.transfers.len() - start;
+                self.transfers[checkpoint..].rotate_right(pushed);
+            }
+            _ => self.transfers.truncate(checkpoint),
+        }
+    }
+}
+
+/// Decodes the token transfers of an ERC-20, ERC-721 or ERC-1155 event.
+fn decode_transfers(log: &Log) -> Vec<(Asset, Address, Address, U256)> {
+    let token = log.address;
+    let topics = log.topics();
+    let data = &log.data.data;
+    let address = |topic: &B256| Address::from_word(*topic);
+    match topics {
+        [topic, from, to] if *topic == TRANSFER_EVENT_TOPIC && data.len() == 32 => {
+            vec![(
+                Asset::Erc20(token),
+                address(from),
+                address(to),
+                U256::from_be_slice(data),
+            )]
+        }
+        [topic, from, to, id] if *topic == TRANSFER_EVENT_TOPIC && data.is_empty() => {
+            let asset = Asset::Erc721 {
+                token,
+                id: (*id).into(),
+            };
+            vec![(asset, address(from), address(to), U256::from(1))]
+        }
+        [topic, _, from, to] if *topic == TRANSFER_SINGLE_EVENT_TOPIC && data.len() == 64 => {
+            let asset = Asset::Erc1155 {
+                token,
+                id: U256::from_be_slice(&data[..32]),
+            };
+            vec![(
+                asset,
+                address(from),
+                address(to),
+                U256::from_be_slice(&data[32..]),
+            )]
+        }
+        [topic, _, from, to] if *topic == TRANSFER_BATCH_EVENT_TOPIC => {
+            let ty = AbiType::Array(AbiType::Uint(256).into());
+            let Ok(values) = abi::decode(&[ty.clone(), ty], data) else {
+                return Vec::new();
+            };
+            let [AbiValue::Array(ids), AbiValue::Array(amounts)] = values.as_slice() else {
+                return Vec::new();
+            };
+            ids.iter()
+                .zip(amounts)
+                .filter_map(|(id, amount)| {
+                    let asset = Asset::Erc1155 {
+                        token,
+                        id: id.as_uint()?,
+                    };
+                    Some((asset, address(from), address(to), amount.as_uint()?))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+impl<DB: Database> Inspector<DB> for TokenFlowInspector {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some((address, _)) = self.frames.last_mut() {
+            *address = interp.contract.target_address;
+        }
+    }
+
+    fn log(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>, log: &Log) {
+        for (asset, from, to, amount) in decode_transfers(log) {
+            self.push(asset, from, to, amount);
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let checkpoint = self.transfers.len();
+        if let Some(value) = inputs.transfer_value() {
+            self.push(
+                Asset::Native,
+                inputs.transfer_from(),
+                inputs.transfer_to(),
+                value,
+            );
+        }
+        self.frames.push((inputs.target_address, checkpoint));
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frames.push((Address::ZERO, self.transfers.len()));
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.create_frame_end(inputs.caller, inputs.value, &outcome);
+        outcome
+    }
+
+    fn eofcreate(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.frames.push((Address::ZERO, self.transfers.len()));
+        None
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.create_frame_end(inputs.caller, inputs.value, &outcome);
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        self.push(Asset::Native, contract, target, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::SpecId,
+        primitives::{keccak256, AccountInfo, LogData},
+        test_fixtures::{eof, inspector_builder, Code, CALLER, CHILD, REVERT, TARGET},
+    };
+
+    const RECEIVER: Address = Address::repeat_byte(0x14);
+    const TOKEN: Address = Address::repeat_byte(0x15);
+
+    fn word(value: u64) -> B256 {
+        U256::from(value).into()
+    }
+
+    fn log(topics: Vec<B256>, data: Vec<u8>) -> Log {
+        Log {
+            address: TOKEN,
+            data: LogData::new_unchecked(topics, data.into()),
+        }
+    }
+
+    #[test]
+    fn decode_events() {
+        assert_eq!(
+            TRANSFER_SINGLE_EVENT_TOPIC,
+            keccak256("TransferSingle(address,address,address,uint256,uint256)")
+        );
+        assert_eq!(
+            TRANSFER_BATCH_EVENT_TOPIC,
+            keccak256("TransferBatch(address,address,address,uint256[],uint256[])")
+        );
+        let (from, to) = (CALLER.into_word(), RECEIVER.into_word());
+
+        let erc20 = log(vec![TRANSFER_EVENT_TOPIC, from, to], word(5).to_vec());
+        assert_eq!(
+            decode_transfers(&erc20),
+            [(Asset::Erc20(TOKEN), CALLER, RECEIVER, U256::from(5))]
+        );
+
+        let erc721 = log(vec![TRANSFER_EVENT_TOPIC, from, to, word(7)], Vec::new());
+        let asset = Asset::Erc721 {
+            token: TOKEN,
+            id: U256::from(7),
+        };
+        assert_eq!(
+            decode_transfers(&erc721),
+            [(asset, CALLER, RECEIVER, U256::from(1))]
+        );
+
+        let operator = TARGET.into_word();
+        let single = log(
+            vec![TRANSFER_SINGLE_EVENT_TOPIC, operator, from, to],
+            [word(7), word(3)].concat(),
+        );
+        let asset = |id| Asset::Erc1155 {
+            token: TOKEN,
+            id: U256::from(id),
+        };
+        assert_eq!(
+            decode_transfers(&single),
+            [(asset(7), CALLER, RECEIVER, U256::from(3))]
+        );
+
+        let batch = log(
+            vec![TRANSFER_BATCH_EVENT_TOPIC, operator, from, to],
+            abi::encode(&[
+                AbiValue::Array(vec![
+                    AbiValue::Uint(U256::from(1)),
+                    AbiValue::Uint(U256::from(2)),
+                ]),
+                AbiValue::Array(vec![
+                    AbiValue::Uint(U256::from(10)),
+                    AbiValue::Uint(U256::from(20)),
+                ]),
+            ]),
+        );
+        assert_eq!(
+            decode_transfers(&batch),
+            [
+                (asset(1), CALLER, RECEIVER, U256::from(10)),
+                (asset(2), CALLER, RECEIVER, U256::from(20)),
+            ]
+        );
+
+        // Approval has a different topic.
+        let approval = log(vec![B256::ZERO, from, to], word(5).to_vec());
+        assert!(decode_transfers(&approval).is_empty());
+    }
+
+    #[test]
+    fn balance_deltas() {
+        let code = Code::new()
+            .call(RECEIVER, 3)
+            .op(0x50)
+            .call(CHILD, 1)
+            .op(0x50)
+            // MSTORE(0, 5) LOG3(0, 32, TRANSFER_EVENT_TOPIC, CALLER, RECEIVER)
+            .push(5)
+            .push(0)
+            .op(0x52)
+            .push_address(RECEIVER)
+            .push_address(CALLER)
+            .push_word(TRANSFER_EVENT_TOPIC)
+            .push(32)
+            .push(0)
+            .op(0xa3)
+            // SELFDESTRUCT(RECEIVER)
+            .push_address(RECEIVER)
+            .op(0xff);
+
+        let contracts = [(TARGET, code.build()), (CHILD, REVERT.into())];
+        let mut evm = inspector_builder(TokenFlowInspector::new(), contracts)
+            .modify_db(|db| {
+                db.insert_account_info(CALLER, AccountInfo::from_balance(U256::from(100)))
+            })
+            .modify_tx_env(|tx| tx.value = U256::from(10))
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        let inspector = &evm.context.external;
+        let transfers: Vec<_> = inspector
+            .transfers()
+            .iter()
+            .map(|t| {
+                (
+                    t.asset,
+                    t.from,
+                    t.to,
+                    t.amount.to::<u64>(),
+                    t.call_path.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transfers,
+            [
+                (Asset::Native, CALLER, TARGET, 10, vec![]),
+                (Asset::Native, TARGET, RECEIVER, 3, vec![TARGET]),
+                (Asset::Erc20(TARGET), CALLER, RECEIVER, 5, vec![TARGET]),
+                (Asset::Native, TARGET, RECEIVER, 7, vec![TARGET]),
+            ]
+        );
+
+        let delta = |value: i64| I256::try_from(value).unwrap();
+        let deltas = inspector.balance_deltas();
+        assert_eq!(
+            deltas,
+            BTreeMap::from([
+                (
+                    CALLER,
+                    BTreeMap::from([
+                        (Asset::Native, delta(-10)),
+                        (Asset::Erc20(TARGET), delta(-5))
+                    ])
+                ),
+                (
+                    RECEIVER,
+                    BTreeMap::from([(Asset::Native, delta(10)), (Asset::Erc20(TARGET), delta(5))])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn eofcreate() {
+        // EOFCREATE(value, 0, 0, 0) of the subcontainer
+        let create = |value: u8, container: u8| {
+            Code::new()
+                .push(0)
+                .push(0)
+                .push(0)
+                .push(value)
+                .bytes(&[0xec, container, 0x50])
+        };
+        // RETURNCONTRACT(0, 0) of the STOP container
+        let deployed = eof(Code::new().op(0x00), []);
+        let init = eof(Code::new().push(0).push(0).bytes(&[0xee, 0x00]), [deployed]);
+        let reverting = eof(Code::new().revert(), []);
+        let code = create(2, 0)
+            .append(create(3, 1))
+            // MSTORE(0, 5) LOG3(0, 32, TRANSFER_EVENT_TOPIC, CALLER, RECEIVER) STOP
+            .push(5)
+            .push(0)
+            .op(0x52)
+            .push_address(RECEIVER)
+            .push_address(CALLER)
+            .push_word(TRANSFER_EVENT_TOPIC)
+            .push(32)
+            .push(0)
+            .bytes(&[0xa3, 0x00]);
+
+        let mut evm = inspector_builder(
+            TokenFlowInspector::new(),
+            [(TARGET, eof(code, [init.clone(), reverting]))],
+        )
+        .modify_db(|db| {
+            db.accounts.get_mut(&TARGET).unwrap().info.balance = U256::from(10);
+        })
+        .with_spec_id(SpecId::PRAGUE_EOF)
+        .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        let created = TARGET.create2(B256::ZERO, keccak256(&init));
+        let transfers: Vec<_> = evm
+            .context
+            .external
+            .transfers()
+            .iter()
+            .map(|t| {
+                (
+                    t.asset,
+                    t.from,
+                    t.to,
+                    t.amount.to::<u64>(),
+                    t.call_path.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transfers,
+            [
+                (Asset::Native, TARGET, created, 2, vec![TARGET]),
+                (Asset::Erc20(TARGET), CALLER, RECEIVER, 5, vec![TARGET]),
+            ]
+        );
+    }
+}
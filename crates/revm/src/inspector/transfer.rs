@@ -0,0 +1,195 @@
+//! TransferInspector. Records ETH transfers as synthetic logs.
+
+use crate::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    primitives::{address, b256, db::Database, Address, Log, LogData, B256, U256},
+    EvmContext, Inspector,
+};
+use std::vec::Vec;
+
+/// Address that emits synthetic transfer logs, as used by `eth_simulateV1`.
+pub const TRANSFER_LOG_ADDRESS: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// Topic of the ERC-20 `Transfer(address,address,uint256)` event.
+pub const TRANSFER_EVENT_TOPIC: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// [Inspector] that collects emitted logs together with synthetic ERC-20 like
+/// `Transfer` logs for every ETH value transfer.
+///
+/// Transfer logs are emitted by [TRANSFER_LOG_ADDRESS] and are placed in execution order
+/// among the logs of the contracts. Logs of reverted frames are discarded.
+#[derive(Clone, Debug, Default)]
+pub struct TransferInspector {
+    logs: Vec<Log>,
+    /// Number of logs at the start of each active frame.
+    checkpoints: Vec<usize>,
+}
+
+impl TransferInspector {
+    /// Returns collected logs.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Takes collected logs and resets the inspector.
+    pub fn take_logs(&mut self) -> Vec<Log> {
+        self.checkpoints.clear();
+        core::mem::take(&mut self.logs)
+    }
+
+    /// Create transfer log of `value` from `from` to `to`.
+    pub fn transfer_log(from: Address, to: Address, value: U256) -> Log {
+        Log {
+            address: TRANSFER_LOG_ADDRESS,
+            data: LogData::new_unchecked(
+                vec![TRANSFER_EVENT_TOPIC, from.into_word(), to.into_word()],
+                value.to_be_bytes_vec().into(),
+            ),
+        }
+    }
+
+    /// Ends the frame, discarding its logs if it did not succeed.
+    fn frame_end(&mut self, success: bool) {
+        let checkpoint = self.checkpoints.pop().unwrap_or_default();
+        if !success {
+            self.logs.truncate(checkpoint);
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for TransferInspector {
+    fn log(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>, log: &Log) {
+        self.logs.push(log.clone());
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.checkpoints.push(self.logs.len());
+        if let Some(value) = inputs.transfer_value().filter(|value| !value.is_zero()) {
+            self.logs.push(Self::transfer_log(
+                inputs.transfer_from(),
+                inputs.transfer_to(),
+                value,
+            ));
+        }
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.checkpoints.push(self.logs.len());
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        // Created address is known only now, transfer is placed before logs of the constructor.
+        let checkpoint = self.checkpoints.last().copied().unwrap_or_default();
+        if let (true, Some(address)) = (outcome.result.is_ok(), outcome.address) {
+            if !inputs.value.is_zero() {
+                self.logs.insert(
+                    checkpoint,
+                    Self::transfer_log(inputs.caller, address, inputs.value),
+                );
+            }
+        }
+        self.frame_end(outcome.result.is_ok());
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        if !value.is_zero() {
+            self.logs.push(Self::transfer_log(contract, target, value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{AccountInfo, Bytecode, Bytes, TxKind},
+        Evm,
+    };
+
+    #[test]
+    fn reverted_transfers_are_dropped() {
+        let caller = Address::repeat_byte(0x11);
+        let forwarder = Address::repeat_byte(0x12);
+        let reverter = Address::repeat_byte(0x13);
+        let receiver = Address::repeat_byte(0x14);
+
+        // CALL(gas, target, 1, 0, 0, 0, 0)
+        let call = |target: Address| {
+            let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x60, 0x01, 0x73];
+            code.extend_from_slice(target.as_slice());
+            code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+            code
+        };
+        let mut code = call(reverter);
+        code.extend(call(receiver));
+        code.push(0x00);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balance: U256::from(100),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            forwarder,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from(code))),
+        );
+        // PUSH0 PUSH0 REVERT
+        db.insert_account_info(
+            reverter,
+            AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(&[0x5f, 0x5f, 0xfd]))),
+        );
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(TransferInspector::default())
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TxKind::Call(forwarder);
+                tx.value = U256::from(10);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        assert_eq!(
+            evm.context.external.take_logs(),
+            vec![
+                TransferInspector::transfer_log(caller, forwarder, U256::from(10)),
+                TransferInspector::transfer_log(forwarder, receiver, U256::from(1)),
+            ]
+        );
+    }
+}
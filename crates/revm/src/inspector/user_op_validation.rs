@@ -0,0 +1,981 @@
+//! UserOpValidationTracer. Checks the ERC-7562 validation rules of ERC-4337 user operations.
+
+use crate::{
+    interpreter::{
+        opcode::{self, OpCode},
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, CreateScheme, InstructionResult,
+        Interpreter, SuccessOrHalt,
+    },
+    primitives::{db::Database, Address, HaltReason, B256, U256},
+    EvmContext, Inspector,
+};
+use core::fmt;
+use std::vec::Vec;
+
+/// Selector of `depositTo(address)` of the EntryPoint.
+const DEPOSIT_TO_SELECTOR: [u8; 4] = [0xb7, 0x60, 0xfa, 0xf9];
+
+/// Number of slots after a `keccak256(address || x)` slot that are associated with the address.
+const ASSOCIATED_SLOTS: u64 = 128;
+
+/// Entity of a user operation that is validated in its own phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Entity {
+    /// Factory that deploys the sender.
+    Factory,
+    /// Sender account, validated by `validateUserOp`.
+    Account,
+    /// Paymaster, validated by `validatePaymasterUserOp`.
+    Paymaster,
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Factory => "factory",
+            Self::Account => "account",
+            Self::Paymaster => "paymaster",
+        })
+    }
+}
+
+/// Storage rule broken by a storage access, by the kind of the accessed storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum StorageRule {
+    /// Storage associated with the sender, deployed by an unstaked factory, STO-022.
+    SenderAssociated,
+    /// Storage of the entity, which is not staked, STO-031.
+    Entity,
+    /// Storage associated with the entity, which is not staked, STO-032.
+    EntityAssociated,
+    /// Storage of a non-entity contract, written or read by an unstaked entity, STO-033.
+    NonEntity,
+}
+
+impl StorageRule {
+    /// Returns the identifier of the ERC-7562 rule.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::SenderAssociated => "STO-022",
+            Self::Entity => "STO-031",
+            Self::EntityAssociated => "STO-032",
+            Self::NonEntity => "STO-033",
+        }
+    }
+}
+
+/// Broken ERC-7562 validation rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum ValidationRule {
+    /// Opcode that depends on the environment, OP-011, or balance read by an unstaked entity,
+    /// OP-080.
+    BannedOpcode(u8),
+    /// `GAS` not followed by a call, OP-012.
+    GasNotFollowedByCall,
+    /// Frame that ran out of gas, OP-020.
+    OutOfGas,
+    /// `CREATE2` other than the one of the factory that deploys the sender, OP-031.
+    Create2,
+    /// `CREATE` other than by a sender that is deployed by a factory, OP-032.
+    Create,
+    /// `EXTCODE*` or call of an address without code, OP-041.
+    CodeAccessWithoutCode(Address),
+    /// `EXTCODE*` of the EntryPoint other than `EXTCODESIZE ISZERO`, OP-051.
+    EntryPointCodeAccess,
+    /// `depositTo` of the EntryPoint called by other than the sender or the factory, OP-052.
+    EntryPointDeposit,
+    /// Fallback of the EntryPoint called by other than the sender, OP-053.
+    EntryPointFallback,
+    /// Call of any other function of the EntryPoint, OP-054.
+    EntryPointCall,
+    /// Call with value to an address other than the EntryPoint, OP-061.
+    CallWithValue(Address),
+    /// Call of an address up to `0xffff` that is not a precompile, OP-062.
+    UnknownPrecompile(Address),
+    /// Storage access that is not allowed for the entity, STO-022 to STO-033.
+    Storage {
+        /// Owner of the storage.
+        address: Address,
+        /// Accessed slot.
+        slot: B256,
+        /// Whether the slot was written.
+        write: bool,
+        /// Broken storage rule.
+        rule: StorageRule,
+    },
+}
+
+impl ValidationRule {
+    /// Returns the identifier of the ERC-7562 rule.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::BannedOpcode(opcode::BALANCE | opcode::SELFBALANCE) => "OP-080",
+            Self::BannedOpcode(_) => "OP-011",
+            Self::GasNotFollowedByCall => "OP-012",
+            Self::OutOfGas => "OP-020",
+            Self::Create2 => "OP-031",
+            Self::Create => "OP-032",
+            Self::CodeAccessWithoutCode(_) => "OP-041",
+            Self::EntryPointCodeAccess => "OP-051",
+            Self::EntryPointDeposit => "OP-052",
+            Self::EntryPointFallback => "OP-053",
+            Self::EntryPointCall => "OP-054",
+            Self::CallWithValue(_) => "OP-061",
+            Self::UnknownPrecompile(_) => "OP-062",
+            Self::Storage { rule, .. } => rule.id(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.id())?;
+        match self {
+            Self::BannedOpcode(op) => write!(f, "banned opcode {}", OpCode::name_by_op(*op)),
+            Self::GasNotFollowedByCall => f.write_str("GAS not followed by a call"),
+            Self::OutOfGas => f.write_str("out of gas"),
+            Self::Create2 => f.write_str("CREATE2 that does not deploy the sender"),
+            Self::Create => f.write_str("contract creation"),
+            Self::CodeAccessWithoutCode(address) => write!(f, "access to {address} without code"),
+            Self::EntryPointCodeAccess => f.write_str("code access of the EntryPoint"),
+            Self::EntryPointDeposit => f.write_str("depositTo of the EntryPoint"),
+            Self::EntryPointFallback => f.write_str("fallback of the EntryPoint"),
+            Self::EntryPointCall => f.write_str("call of the EntryPoint"),
+            Self::CallWithValue(address) => write!(f, "call with value to {address}"),
+            Self::UnknownPrecompile(address) => write!(f, "call to unknown precompile {address}"),
+            Self::Storage {
+                address,
+                slot,
+                write,
+                ..
+            } => write!(
+                f,
+                "{} of slot {slot} of {address}",
+                if *write { "write" } else { "read" }
+            ),
+        }
+    }
+}
+
+/// Rule violation and where it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct RuleViolation {
+    /// Broken rule.
+    pub rule: ValidationRule,
+    /// Entity whose validation broke the rule.
+    pub entity: Entity,
+    /// Contract that executed the instruction.
+    pub address: Address,
+    /// Program counter of the instruction.
+    pub pc: usize,
+    /// Call depth of the frame, the transaction frame has depth zero.
+    pub depth: u64,
+}
+
+/// Validation phase of an entity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ValidationPhase {
+    /// Validated entity.
+    pub entity: Entity,
+    /// Address of the entity.
+    pub address: Address,
+    /// Result of the validation call, `None` while it is executed.
+    pub result: Option<InstructionResult>,
+    /// Rules broken during the phase, in execution order.
+    pub violations: Vec<RuleViolation>,
+}
+
+/// Entity of the user operation and whether it is staked.
+#[derive(Clone, Copy, Debug)]
+struct EntityInfo {
+    entity: Entity,
+    address: Address,
+    staked: bool,
+}
+
+/// [Inspector] that checks the ERC-7562 validation rules during `simulateValidation`.
+///
+/// A phase starts with the first call to an entity of the user operation made outside of
+/// another phase, as the calls of the EntryPoint to the factory, `validateUserOp` of the sender
+/// and `validatePaymasterUserOp` of the paymaster, and includes all nested calls. Code executed
+/// outside of the phases or by the EntryPoint, as `depositTo`, is not checked.
+///
+/// Storage is associated with an address if the slot is the address, or is at most
+/// 128 slots after `keccak256(address || x)` computed during the simulation.
+#[derive(Clone, Debug)]
+pub struct UserOpValidationTracer {
+    entry_point: Address,
+    sender: Address,
+    entities: Vec<EntityInfo>,
+    phases: Vec<ValidationPhase>,
+    /// Depth of the call that started the active phase.
+    phase_depth: Option<u64>,
+    /// Slots derived with `keccak256` from the entities, with their owner.
+    keccak_slots: Vec<(Address, U256)>,
+    /// Address, program counter and depth of the last executed instruction.
+    location: (Address, usize, u64),
+    /// Location of the last instruction if it was `GAS`.
+    after_gas: Option<(Address, usize, u64)>,
+    /// Entity that is the first word hashed by the current `KECCAK256` instruction.
+    keccak_owner: Option<Address>,
+    /// Address whose code is accessed by the current `EXTCODE*` instruction, with the opcode.
+    code_access: Option<(Address, u8)>,
+    sender_created: bool,
+}
+
+impl UserOpValidationTracer {
+    /// Creates a new tracer for the user operation of the sender, validated by the EntryPoint.
+    pub fn new(entry_point: Address, sender: Address) -> Self {
+        Self {
+            entry_point,
+            sender,
+            entities: vec![EntityInfo {
+                entity: Entity::Account,
+                address: sender,
+                staked: false,
+            }],
+            phases: Vec::new(),
+            phase_depth: None,
+            keccak_slots: Vec::new(),
+            location: (Address::ZERO, 0, 0),
+            after_gas: None,
+            keccak_owner: None,
+            code_access: None,
+            sender_created: false,
+        }
+    }
+
+    /// Sets whether the sender is staked.
+    pub fn with_staked_account(mut self, staked: bool) -> Self {
+        self.entities[0].staked = staked;
+        self
+    }
+
+    /// Sets the factory of the `initCode` and whether it is staked.
+    pub fn with_factory(self, factory: Address, staked: bool) -> Self {
+        self.with_entity(Entity::Factory, factory, staked)
+    }
+
+    /// Sets the paymaster and whether it is staked.
+    pub fn with_paymaster(self, paymaster: Address, staked: bool) -> Self {
+        self.with_entity(Entity::Paymaster, paymaster, staked)
+    }
+
+    fn with_entity(mut self, entity: Entity, address: Address, staked: bool) -> Self {
+        self.entities.retain(|info| info.entity != entity);
+        self.entities.push(EntityInfo {
+            entity,
+            address,
+            staked,
+        });
+        self
+    }
+
+    /// Returns the validation phases, in execution order.
+    pub fn phases(&self) -> &[ValidationPhase] {
+        &self.phases
+    }
+
+    /// Returns the rule violations of all phases.
+    pub fn violations(&self) -> impl Iterator<Item = &RuleViolation> {
+        self.phases.iter().flat_map(|phase| &phase.violations)
+    }
+
+    /// Returns `true` if no rule was broken.
+    pub fn is_valid(&self) -> bool {
+        self.violations().next().is_none()
+    }
+
+    fn reset(&mut self) {
+        self.phases.clear();
+        self.phase_depth = None;
+        self.keccak_slots.clear();
+        self.after_gas = None;
+        self.keccak_owner = None;
+        self.code_access = None;
+        self.sender_created = false;
+    }
+
+    fn entity(&self, entity: Entity) -> Option<&EntityInfo> {
+        self.entities.iter().find(|info| info.entity == entity)
+    }
+
+    /// Returns the active phase.
+    fn phase(&self) -> Option<&ValidationPhase> {
+        self.phase_depth.and(self.phases.last())
+    }
+
+    /// Records a violation of the active phase at the last executed instruction.
+    fn violation(&mut self, rule: ValidationRule) {
+        self.violation_at(self.location, rule);
+    }
+
+    fn violation_at(&mut self, (address, pc, depth): (Address, usize, u64), rule: ValidationRule) {
+        if let Some(phase) = self.phase_depth.and(self.phases.last_mut()) {
+            phase.violations.push(RuleViolation {
+                rule,
+                entity: phase.entity,
+                address,
+                pc,
+                depth,
+            });
+        }
+    }
+
+    /// Returns `true` if the slot is associated with the address.
+    fn is_associated(&self, address: Address, slot: U256) -> bool {
+        slot == U256::from_be_slice(address.as_slice())
+            || self.keccak_slots.iter().any(|(owner, base)| {
+                *owner == address
+                    && slot
+                        .checked_sub(*base)
+                        .is_some_and(|offset| offset <= U256::from(ASSOCIATED_SLOTS))
+            })
+    }
+
+    /// Returns the storage rule broken if the entity accesses the slot of the address.
+    fn storage_rule(
+        &self,
+        info: EntityInfo,
+        address: Address,
+        slot: U256,
+        write: bool,
+    ) -> Option<StorageRule> {
+        // STO-010: storage of the sender.
+        if address == self.sender {
+            return None;
+        }
+        // STO-021 and STO-022: storage associated with the sender, if it is already deployed or
+        // deployed by a staked factory.
+        if self.is_associated(self.sender, slot) {
+            let allowed = self
+                .entity(Entity::Factory)
+                .is_none_or(|factory| factory.staked);
+            return (!allowed).then_some(StorageRule::SenderAssociated);
+        }
+        // STO-031 and STO-032: storage of the entity and associated with it, if staked.
+        if address == info.address {
+            return (!info.staked).then_some(StorageRule::Entity);
+        }
+        if self.is_associated(info.address, slot) {
+            return (!info.staked).then_some(StorageRule::EntityAssociated);
+        }
+        // STO-033: read-only access to any storage, if staked.
+        (write || !info.staked).then_some(StorageRule::NonEntity)
+    }
+
+    /// Checks the code access of `EXTCODE*` or a call of an address, OP-041.
+    fn check_code_access<DB: Database>(&mut self, context: &EvmContext<DB>, address: Address) {
+        if address != self.sender
+            && !context.precompiles.contains(&address)
+            && context
+                .journaled_state
+                .state
+                .get(&address)
+                .is_some_and(|account| account.info.is_empty_code_hash())
+        {
+            self.violation(ValidationRule::CodeAccessWithoutCode(address));
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for UserOpValidationTracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let address = interp.contract.target_address;
+        if op == opcode::KECCAK256 {
+            // Mapping slots hash the key first, record the ones keyed by an entity.
+            if let (Ok(offset), Ok(size)) = (interp.stack.peek(0), interp.stack.peek(1)) {
+                let (offset, size) = (
+                    offset.saturating_to::<usize>(),
+                    size.saturating_to::<usize>(),
+                );
+                let memory = &interp.shared_memory;
+                if size >= 32 && offset.saturating_add(32) <= memory.len() {
+                    let key = memory.slice(offset, 32);
+                    self.keccak_owner = self
+                        .entities
+                        .iter()
+                        .find(|info| key[..12] == [0; 12] && key[12..] == info.address[..])
+                        .map(|info| info.address);
+                }
+            }
+        }
+
+        let Some(info) = self
+            .phase()
+            .and_then(|phase| self.entity(phase.entity))
+            .copied()
+            .filter(|_| address != self.entry_point)
+        else {
+            return;
+        };
+        self.location = (
+            address,
+            interp.program_counter(),
+            context.journaled_state.depth() - 1,
+        );
+
+        if let Some(location) = self.after_gas.take().filter(|_| {
+            !matches!(
+                op,
+                opcode::CALL
+                    | opcode::CALLCODE
+                    | opcode::DELEGATECALL
+                    | opcode::STATICCALL
+                    | opcode::EXTCALL
+                    | opcode::EXTDELEGATECALL
+                    | opcode::EXTSTATICCALL
+            )
+        }) {
+            self.violation_at(location, ValidationRule::GasNotFollowedByCall);
+        }
+
+        match op {
+            opcode::GAS => self.after_gas = Some(self.location),
+            opcode::BALANCE | opcode::SELFBALANCE if info.staked => {}
+            opcode::ORIGIN
+            | opcode::GASPRICE
+            | opcode::BLOCKHASH
+            | opcode::COINBASE
+            | opcode::TIMESTAMP
+            | opcode::NUMBER
+            | opcode::DIFFICULTY
+            | opcode::GASLIMIT
+            | opcode::BALANCE
+            | opcode::SELFBALANCE
+            | opcode::BASEFEE
+            | opcode::BLOBHASH
+            | opcode::BLOBBASEFEE
+            | opcode::INVALID
+            | opcode::SELFDESTRUCT => self.violation(ValidationRule::BannedOpcode(op)),
+            opcode::SLOAD | opcode::SSTORE | opcode::TLOAD | opcode::TSTORE => {
+                let Ok(slot) = interp.stack.peek(0) else {
+                    return;
+                };
+                let write = matches!(op, opcode::SSTORE | opcode::TSTORE);
+                if let Some(rule) = self.storage_rule(info, address, slot, write) {
+                    self.violation(ValidationRule::Storage {
+                        address,
+                        slot: slot.into(),
+                        write,
+                        rule,
+                    });
+                }
+            }
+            opcode::EXTCODESIZE | opcode::EXTCODECOPY | opcode::EXTCODEHASH => {
+                if let Ok(target) = interp.stack.peek(0) {
+                    self.code_access = Some((Address::from_word(target.into()), op));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if let Some(owner) = self.keccak_owner.take() {
+            if let Ok(base) = interp.stack.peek(0) {
+                self.keccak_slots.push((owner, base));
+            }
+        }
+        if let Some((address, op)) = self.code_access.take() {
+            if address == self.entry_point {
+                // OP-051: `EXTCODESIZE ISZERO` checks that the EntryPoint is deployed.
+                if op != opcode::EXTCODESIZE || interp.current_opcode() != opcode::ISZERO {
+                    self.violation(ValidationRule::EntryPointCodeAccess);
+                }
+            } else {
+                // The account is loaded by the instruction.
+                self.check_code_access(context, address);
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let depth = context.journaled_state.depth();
+        if depth == 0 {
+            self.reset();
+        }
+        let target = inputs.target_address;
+        if self.phase_depth.is_none() {
+            if let Some(info) = self.entities.iter().find(|info| info.address == target) {
+                self.phases.push(ValidationPhase {
+                    entity: info.entity,
+                    address: target,
+                    result: None,
+                    violations: Vec::new(),
+                });
+                self.phase_depth = Some(depth);
+            }
+            return None;
+        }
+
+        if target == self.entry_point {
+            // OP-052 and OP-053: `depositTo` by the sender or the factory, fallback by the sender.
+            let caller = inputs.caller;
+            let rule = if inputs.input.is_empty() {
+                (caller != self.sender).then_some(ValidationRule::EntryPointFallback)
+            } else if inputs.input.get(..4) == Some(&DEPOSIT_TO_SELECTOR[..]) {
+                let factory = self.entity(Entity::Factory).map(|info| info.address);
+                (caller != self.sender && Some(caller) != factory)
+                    .then_some(ValidationRule::EntryPointDeposit)
+            } else {
+                Some(ValidationRule::EntryPointCall)
+            };
+            if let Some(rule) = rule {
+                self.violation(rule);
+            }
+            return None;
+        }
+        if inputs.transfers_value() {
+            self.violation(ValidationRule::CallWithValue(target));
+        }
+        let code_address = inputs.bytecode_address;
+        if code_address[..18] == [0; 18] && !context.precompiles.contains(&code_address) {
+            self.violation(ValidationRule::UnknownPrecompile(code_address));
+        } else {
+            self.check_code_access(context, code_address);
+        }
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let depth = context.journaled_state.depth();
+        if self.phase_depth.is_none() {
+            return outcome;
+        }
+        if matches!(
+            SuccessOrHalt::from(outcome.result.result),
+            SuccessOrHalt::Halt(HaltReason::OutOfGas(_))
+        ) {
+            // The last instruction is the one that ran out of gas if executed by the callee.
+            if self.location.0 != inputs.target_address || self.location.2 != depth {
+                self.location = (inputs.target_address, 0, depth);
+            }
+            self.violation(ValidationRule::OutOfGas);
+        }
+        if self.phase_depth == Some(depth) {
+            if let Some(phase) = self.phases.last_mut() {
+                phase.result = Some(outcome.result.result);
+            }
+            self.phase_depth = None;
+            self.after_gas = None;
+        }
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        if context.journaled_state.depth() == 0 {
+            self.reset();
+        }
+        let entity = self.phase()?.entity;
+        // OP-031: the factory deploys the sender with `CREATE2` once.
+        // OP-032: the sender may use `CREATE` if it is deployed by a factory.
+        match inputs.scheme {
+            CreateScheme::Create2 { .. } => {
+                if entity == Entity::Factory
+                    && !self.sender_created
+                    && inputs.created_address(0) == self.sender
+                {
+                    self.sender_created = true;
+                } else {
+                    self.violation(ValidationRule::Create2);
+                }
+            }
+            CreateScheme::Create => {
+                if entity != Entity::Account || self.entity(Entity::Factory).is_none() {
+                    self.violation(ValidationRule::Create);
+                }
+            }
+        }
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if self.phase_depth.is_some()
+            && matches!(
+                SuccessOrHalt::from(outcome.result.result),
+                SuccessOrHalt::Halt(HaltReason::OutOfGas(_))
+            )
+        {
+            let depth = context.journaled_state.depth();
+            if self.location.2 != depth {
+                self.location = (outcome.address.unwrap_or_default(), 0, depth);
+            }
+            self.violation(ValidationRule::OutOfGas);
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primitives::{address, hex, Bytes, TxKind, KECCAK_EMPTY},
+        test_fixtures::inspector_builder,
+    };
+
+    const ENTRY_POINT: Address = address!("0000000071727de22e5e9d8baf0edac6f37da032");
+    const SENDER: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+    const PAYMASTER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+    const TOKEN: Address = address!("cccccccccccccccccccccccccccccccccccccccc");
+    const FACTORY: Address = address!("dddddddddddddddddddddddddddddddddddddddd");
+
+    /// CALL(GAS, address, value, 0, 0, 0, 0) POP
+    fn call(address: Address, value: u8) -> Vec<u8> {
+        let mut code = hex!("600060006000600060").to_vec();
+        code.push(value);
+        code.push(0x73);
+        code.extend_from_slice(address.as_slice());
+        code.extend_from_slice(&hex!("5af150"));
+        code
+    }
+
+    /// MSTORE(0, input) CALL(GAS, address, 0, 0, input.len(), 0, 0) POP, with `input` up to
+    /// 32 bytes.
+    fn call_with_input(address: Address, input: &[u8]) -> Vec<u8> {
+        let mut code = vec![0x7f];
+        code.extend_from_slice(B256::right_padding_from(input).as_slice());
+        code.extend_from_slice(&[0x5f, 0x52, 0x5f, 0x5f, 0x60, input.len() as u8, 0x5f, 0x5f]);
+        code.push(0x73);
+        code.extend_from_slice(address.as_slice());
+        code.extend_from_slice(&hex!("5af150"));
+        code
+    }
+
+    fn run(
+        sender: Vec<u8>,
+        paymaster: Vec<u8>,
+        tracer: UserOpValidationTracer,
+    ) -> UserOpValidationTracer {
+        run_entities(&[(SENDER, sender), (PAYMASTER, paymaster)], tracer)
+    }
+
+    /// Executes the EntryPoint that calls the entities in order. Entities without code are not
+    /// inserted into the database.
+    fn run_entities(
+        entities: &[(Address, Vec<u8>)],
+        tracer: UserOpValidationTracer,
+    ) -> UserOpValidationTracer {
+        // Calls made by the entities to the EntryPoint stop.
+        // JUMPI(7, EQ(CALLER, ORIGIN)) STOP JUMPDEST
+        let mut entry_point = hex!("333214600757005b").to_vec();
+        for (address, _) in entities {
+            entry_point.extend_from_slice(&call(*address, 0));
+        }
+        // MSTORE(0, SENDER) SLOAD(keccak256(SENDER || 0)) SLOAD(5)
+        let mut token = vec![0x73];
+        token.extend_from_slice(SENDER.as_slice());
+        token.extend_from_slice(&hex!("600052604060002054506005545000"));
+
+        let contracts = [(ENTRY_POINT, entry_point), (TOKEN, token)]
+            .into_iter()
+            .chain(
+                entities
+                    .iter()
+                    .filter(|(_, code)| !code.is_empty())
+                    .cloned(),
+            )
+            .map(|(address, code)| (address, Bytes::from(code)));
+        let mut evm = inspector_builder(tracer, contracts)
+            .modify_tx_env(|tx| tx.transact_to = TxKind::Call(ENTRY_POINT))
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+        evm.into_context().external
+    }
+
+    fn rules(tracer: &UserOpValidationTracer) -> Vec<(Entity, ValidationRule, usize)> {
+        tracer
+            .violations()
+            .map(|violation| (violation.entity, violation.rule, violation.pc))
+            .collect()
+    }
+
+    #[test]
+    fn phases() {
+        let tracer = run(
+            hex!("00").to_vec(),
+            hex!("00").to_vec(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, false),
+        );
+        let phases: Vec<_> = tracer
+            .phases()
+            .iter()
+            .map(|phase| (phase.entity, phase.address, phase.result))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                (Entity::Account, SENDER, Some(InstructionResult::Stop)),
+                (Entity::Paymaster, PAYMASTER, Some(InstructionResult::Stop)),
+            ]
+        );
+        assert!(tracer.is_valid());
+    }
+
+    #[test]
+    fn banned_opcodes() {
+        // TIMESTAMP POP GAS POP, then a call with value after GAS.
+        let sender = [
+            hex!("42505a50").to_vec(),
+            call(TOKEN, 1),
+            hex!("00").to_vec(),
+        ]
+        .concat();
+        // SELFBALANCE POP
+        let paymaster = hex!("475000").to_vec();
+        let tracer = run(
+            sender.clone(),
+            paymaster.clone(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, false),
+        );
+        assert_eq!(
+            rules(&tracer),
+            [
+                (
+                    Entity::Account,
+                    ValidationRule::BannedOpcode(opcode::TIMESTAMP),
+                    0
+                ),
+                (Entity::Account, ValidationRule::GasNotFollowedByCall, 2),
+                (Entity::Account, ValidationRule::CallWithValue(TOKEN), 36),
+                (
+                    Entity::Paymaster,
+                    ValidationRule::BannedOpcode(opcode::SELFBALANCE),
+                    0
+                ),
+            ]
+        );
+        assert_eq!(
+            tracer.violations().next().unwrap().rule.to_string(),
+            "OP-011: banned opcode TIMESTAMP"
+        );
+
+        // Staked entities may read balances.
+        let tracer = run(
+            sender,
+            paymaster,
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, true),
+        );
+        assert_eq!(tracer.violations().count(), 3);
+    }
+
+    #[test]
+    fn storage_rules() {
+        let sender = [call(TOKEN, 0), hex!("00").to_vec()].concat();
+        // SLOAD(0) POP
+        let paymaster = hex!("6000545000").to_vec();
+        let tracer = run(
+            sender.clone(),
+            paymaster.clone(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, false),
+        );
+        let storage = |address, slot: u64, rule| ValidationRule::Storage {
+            address,
+            slot: U256::from(slot).into(),
+            write: false,
+            rule,
+        };
+        // The slot associated with the sender is allowed, slot 5 is not.
+        let violations: Vec<_> = tracer
+            .violations()
+            .map(|violation| (violation.entity, violation.rule, violation.address))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                (
+                    Entity::Account,
+                    storage(TOKEN, 5, StorageRule::NonEntity),
+                    TOKEN
+                ),
+                (
+                    Entity::Paymaster,
+                    storage(PAYMASTER, 0, StorageRule::Entity),
+                    PAYMASTER
+                ),
+            ]
+        );
+        assert_eq!(tracer.violations().next().unwrap().depth, 2);
+
+        let ids: Vec<_> = tracer.violations().map(|v| v.rule.id()).collect();
+        assert_eq!(ids, ["STO-033", "STO-031"]);
+
+        // Storage associated with the sender needs a staked factory.
+        let tracer = run(
+            sender.clone(),
+            paymaster.clone(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER)
+                .with_factory(FACTORY, false)
+                .with_paymaster(PAYMASTER, true),
+        );
+        let ids: Vec<_> = tracer.violations().map(|v| v.rule.id()).collect();
+        assert_eq!(ids, ["STO-022", "STO-033"]);
+
+        let tracer = run(
+            sender,
+            paymaster,
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER)
+                .with_staked_account(true)
+                .with_paymaster(PAYMASTER, true),
+        );
+        assert!(tracer.is_valid());
+    }
+
+    #[test]
+    fn creation_rules() {
+        // CREATE2(0, 0, 0, salt) POP
+        let create2 = |salt: u8| vec![0x60, salt, 0x5f, 0x5f, 0x5f, 0xf5, 0x50];
+        // CREATE(0, 0, 0) POP
+        let create = hex!("5f5f5ff050").to_vec();
+        let sender = FACTORY.create2(B256::ZERO, KECCAK_EMPTY);
+
+        // The factory deploys the sender once, further creations are not allowed.
+        let factory = [create2(0), create2(1), create.clone(), vec![0x00]].concat();
+        let tracer = run_entities(
+            &[(FACTORY, factory), (sender, Vec::new())],
+            UserOpValidationTracer::new(ENTRY_POINT, sender).with_factory(FACTORY, false),
+        );
+        assert_eq!(
+            rules(&tracer),
+            [
+                (Entity::Factory, ValidationRule::Create2, 12),
+                (Entity::Factory, ValidationRule::Create, 17),
+            ]
+        );
+        let ids: Vec<_> = tracer.violations().map(|v| v.rule.id()).collect();
+        assert_eq!(ids, ["OP-031", "OP-032"]);
+
+        // The sender may use `CREATE` only if it is deployed by a factory.
+        let sender = [create, vec![0x00]].concat();
+        let tracer = run(
+            sender.clone(),
+            hex!("00").to_vec(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER),
+        );
+        assert_eq!(
+            rules(&tracer),
+            [(Entity::Account, ValidationRule::Create, 3)]
+        );
+        let tracer = run(
+            sender,
+            hex!("00").to_vec(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_factory(FACTORY, true),
+        );
+        assert!(tracer.is_valid());
+    }
+
+    #[test]
+    fn gas_and_code_access() {
+        const EMPTY: Address = address!("eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+        // CALL(100, TOKEN, 0, 0, 0, 0, 0) POP, the token runs out of gas.
+        let mut sender = hex!("5f5f5f5f5f73").to_vec();
+        sender.extend_from_slice(TOKEN.as_slice());
+        sender.extend_from_slice(&hex!("6064f150"));
+        // EXTCODESIZE(EMPTY) POP, then a call of it.
+        let mut paymaster = vec![0x73];
+        paymaster.extend_from_slice(EMPTY.as_slice());
+        paymaster.extend_from_slice(&hex!("3b50"));
+        paymaster.extend_from_slice(&call(EMPTY, 0));
+        let tracer = run(
+            [sender, vec![0x00]].concat(),
+            [paymaster, vec![0x00]].concat(),
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, false),
+        );
+        let violations: Vec<_> = tracer
+            .violations()
+            .map(|v| (v.entity, v.rule, v.rule.id(), v.address))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                (Entity::Account, ValidationRule::OutOfGas, "OP-020", TOKEN),
+                (
+                    Entity::Paymaster,
+                    ValidationRule::CodeAccessWithoutCode(EMPTY),
+                    "OP-041",
+                    PAYMASTER
+                ),
+                (
+                    Entity::Paymaster,
+                    ValidationRule::CodeAccessWithoutCode(EMPTY),
+                    "OP-041",
+                    PAYMASTER
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_point_access() {
+        let extcode = |op: u8| {
+            let mut code = vec![0x73];
+            code.extend_from_slice(ENTRY_POINT.as_slice());
+            code.extend_from_slice(&[op, 0x15, 0x50]);
+            code
+        };
+        // `EXTCODESIZE ISZERO`, `depositTo` and the fallback are allowed for the sender.
+        let sender = [
+            extcode(opcode::EXTCODESIZE),
+            extcode(opcode::EXTCODEHASH),
+            call_with_input(ENTRY_POINT, &DEPOSIT_TO_SELECTOR),
+            call(ENTRY_POINT, 0),
+            call_with_input(ENTRY_POINT, &hex!("12345678")),
+            vec![0x00],
+        ]
+        .concat();
+        let paymaster = [
+            call_with_input(ENTRY_POINT, &DEPOSIT_TO_SELECTOR),
+            call(ENTRY_POINT, 0),
+            vec![0x00],
+        ]
+        .concat();
+        let tracer = run(
+            sender,
+            paymaster,
+            UserOpValidationTracer::new(ENTRY_POINT, SENDER).with_paymaster(PAYMASTER, true),
+        );
+        let violations: Vec<_> = tracer
+            .violations()
+            .map(|v| (v.entity, v.rule.id()))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                (Entity::Account, "OP-051"),
+                (Entity::Account, "OP-054"),
+                (Entity::Paymaster, "OP-052"),
+                (Entity::Paymaster, "OP-053"),
+            ]
+        );
+    }
+}
@@ -168,6 +168,46 @@ impl JournaledState {
         self.set_code_with_hash(address, code, hash)
     }
 
+    /// Replaces the code of the account, reverting restores the previous code.
+    ///
+    /// Assume account is warm.
+    #[inline]
+    pub fn replace_code(&mut self, address: Address, code: Bytecode) {
+        let account = self.state.get_mut(&address).unwrap();
+        Self::touch_account(self.journal.last_mut().unwrap(), &address, account);
+
+        self.journal
+            .last_mut()
+            .unwrap()
+            .push(JournalEntry::CodeReplaced {
+                address,
+                had_code: account.info.code.take(),
+                had_hash: account.info.code_hash,
+            });
+
+        account.info.code_hash = code.hash_slow();
+        account.info.code = Some(code);
+    }
+
+    /// Sets the balance of the account.
+    ///
+    /// Assume account is warm.
+    #[inline]
+    pub fn set_balance(&mut self, address: Address, balance: U256) {
+        let account = self.state.get_mut(&address).unwrap();
+        Self::touch_account(self.journal.last_mut().unwrap(), &address, account);
+
+        self.journal
+            .last_mut()
+            .unwrap()
+            .push(JournalEntry::BalanceChange {
+                address,
+                had_balance: account.info.balance,
+            });
+
+        account.info.balance = balance;
+    }
+
     #[inline]
     pub fn inc_nonce(&mut self, address: Address) -> Option<u64> {
         let account = self.state.get_mut(&address).unwrap();
@@ -412,6 +452,21 @@ impl JournaledState {
                     acc.info.code_hash = KECCAK_EMPTY;
                     acc.info.code = None;
                 }
+                JournalEntry::CodeReplaced {
+                    address,
+                    had_code,
+                    had_hash,
+                } => {
+                    let acc = state.get_mut(&address).unwrap();
+                    acc.info.code_hash = had_hash;
+                    acc.info.code = had_code;
+                }
+                JournalEntry::BalanceChange {
+                    address,
+                    had_balance,
+                } => {
+                    state.get_mut(&address).unwrap().info.balance = had_balance;
+                }
             }
         }
     }
@@ -437,10 +492,39 @@ impl JournaledState {
     /// Reverts all changes to state until given checkpoint.
     #[inline]
     pub fn checkpoint_revert(&mut self, checkpoint: JournalCheckpoint) {
+        self.depth -= 1;
+        self.revert_entries(checkpoint);
+        self.journal.truncate(checkpoint.journal_i);
+    }
+
+    /// Makes a checkpoint inside of the current call, without entering a new depth.
+    ///
+    /// Changes made after it can be reverted with [JournaledState::revert_to_snapshot] while
+    /// the call keeps executing.
+    #[inline]
+    pub fn snapshot(&mut self) -> JournalCheckpoint {
+        let checkpoint = JournalCheckpoint {
+            log_i: self.logs.len(),
+            journal_i: self.journal.len(),
+        };
+        self.journal.push(Default::default());
+        checkpoint
+    }
+
+    /// Reverts all changes to state until given snapshot.
+    ///
+    /// Depth and the number of journal entry sets are kept, so checkpoints of the calls that are
+    /// still executing stay valid. Snapshot is invalid if a checkpoint made before it was reverted.
+    #[inline]
+    pub fn revert_to_snapshot(&mut self, snapshot: JournalCheckpoint) {
+        self.revert_entries(snapshot);
+    }
+
+    /// Reverts the journal entries and logs made after the checkpoint, leaving empty entry sets.
+    fn revert_entries(&mut self, checkpoint: JournalCheckpoint) {
         let is_spurious_dragon_enabled = SpecId::enabled(self.spec, SPURIOUS_DRAGON);
         let state = &mut self.state;
         let transient_storage = &mut self.transient_storage;
-        self.depth -= 1;
         // iterate over last N journals sets and revert our global state
         let leng = self.journal.len();
         self.journal
@@ -457,7 +541,6 @@ impl JournaledState {
             });
 
         self.logs.truncate(checkpoint.log_i);
-        self.journal.truncate(checkpoint.journal_i);
     }
 
     /// Performances selfdestruct action.
@@ -867,6 +950,18 @@ pub enum JournalEntry {
     /// Action: Account code changed
     /// Revert: Revert to previous bytecode.
     CodeChange { address: Address },
+    /// Code replaced
+    /// Action: Account code replaced
+    /// Revert: Restore the previous bytecode and hash.
+    CodeReplaced {
+        address: Address,
+        had_code: Option<Bytecode>,
+        had_hash: B256,
+    },
+    /// Balance set
+    /// Action: Account balance set
+    /// Revert: Restore the previous balance.
+    BalanceChange { address: Address, had_balance: U256 },
 }
 
 /// SubRoutine checkpoint that will help us to go back from this
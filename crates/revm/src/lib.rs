@@ -8,9 +8,14 @@ extern crate alloc as std;
 
 // Define modules.
 
+pub mod abi;
+mod access_list;
 mod builder;
 mod context;
+mod estimate;
 
+#[cfg(test)]
+mod test_fixtures;
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
 
@@ -22,9 +27,13 @@ mod inspector;
 mod journaled_state;
 #[cfg(feature = "optimism")]
 pub mod optimism;
+#[cfg(feature = "serde")]
+mod quantity;
+pub mod simulate;
 
 // Export items.
 
+pub use access_list::AccessListResult;
 pub use builder::EvmBuilder;
 pub use context::{
     Context, ContextPrecompile, ContextPrecompiles, ContextStatefulPrecompile,
@@ -35,6 +44,7 @@ pub use db::{
     CacheState, DBBox, State, StateBuilder, StateDBBox, TransitionAccount, TransitionState,
 };
 pub use db::{Database, DatabaseCommit, DatabaseRef, InMemoryDB};
+pub use estimate::{EstimateGasError, GasEstimate};
 pub use evm::{Evm, CALL_STACK_LIMIT};
 pub use frame::{CallFrame, CreateFrame, Frame, FrameData, FrameOrResult, FrameResult};
 pub use handler::Handler;
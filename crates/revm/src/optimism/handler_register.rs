@@ -371,6 +371,8 @@ pub fn end<SPEC: Spec, EXT, DB: Database>(
                     gas_used,
                 },
                 state,
+                #[cfg(feature = "halt_diagnostics")]
+                diagnostics: None,
             })
         } else {
             Err(err)
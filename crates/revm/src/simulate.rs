@@ -0,0 +1,513 @@
+//! Simulation of a sequence of blocks, equivalent to `eth_simulateV1`.
+//!
+//! Every block is executed on top of the state left by the previous one. Each block can
+//! override parts of the block environment and of the state before its calls are executed.
+use crate::{
+    db::{apply_precompile_moves, BlockOverrides, OverrideDB, StateOverride, StateOverrideError},
+    inspector_handle_register,
+    inspectors::TransferInspector,
+    primitives::{BlockEnv, CfgEnvWithHandlerCfg, EVMError, ExecutionResult, Log, TxEnv, U256},
+    Database, DatabaseCommit, Evm,
+};
+use core::fmt;
+use std::{sync::Arc, vec::Vec};
+
+/// Seconds between simulated blocks whose timestamp is not overridden.
+pub const SIMULATED_BLOCK_TIME: u64 = 12;
+
+/// Block to simulate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimBlock {
+    /// Overrides of the block environment.
+    ///
+    /// Block number and timestamp that are not overridden are incremented from the
+    /// previous block, by one and by [SIMULATED_BLOCK_TIME] respectively.
+    pub block_overrides: BlockOverrides,
+    /// Overrides of the state, applied before the calls of the block.
+    pub state_overrides: StateOverride,
+    /// Calls executed in the block.
+    ///
+    /// Missing nonce is filled with the nonce of the caller. Gas limit that is left to
+    /// its default of `u64::MAX` is filled with the gas remaining in the block.
+    pub calls: Vec<TxEnv>,
+}
+
+/// Simulation options.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimulateOptions {
+    /// Validate calls as transactions of a real block.
+    ///
+    /// If disabled, nonces are not checked, base fee not overridden is set to zero and,
+    /// with `optional_eip3607` feature, callers with code are allowed.
+    pub validation: bool,
+    /// Add synthetic logs for ETH transfers. See [TransferInspector].
+    pub trace_transfers: bool,
+}
+
+/// Result of a simulated call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimCallResult {
+    /// Execution result.
+    pub result: ExecutionResult,
+    /// Logs of the call, including transfer logs if they are traced.
+    pub logs: Vec<Log>,
+}
+
+/// Result of a simulated block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimulatedBlock {
+    /// Block environment the calls were executed in.
+    pub env: BlockEnv,
+    /// Gas used by all calls of the block.
+    pub gas_used: u64,
+    /// Results of the calls, in order.
+    pub calls: Vec<SimCallResult>,
+}
+
+/// Errors that abort the simulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulateError<DBError> {
+    /// Invalid state override.
+    StateOverride {
+        /// Index of the block.
+        block: usize,
+        /// Error.
+        error: StateOverrideError,
+    },
+    /// Block number is not greater than the number of the previous block.
+    BlockNumberNotIncreasing {
+        /// Index of the block.
+        block: usize,
+    },
+    /// Block timestamp is not greater than the timestamp of the previous block.
+    BlockTimestampNotIncreasing {
+        /// Index of the block.
+        block: usize,
+    },
+    /// Gas limit of the call is larger than the gas remaining in the block.
+    BlockGasLimitReached {
+        /// Index of the block.
+        block: usize,
+        /// Index of the call in the block.
+        call: usize,
+        /// Gas limit of the call.
+        gas_limit: u64,
+        /// Gas remaining in the block.
+        remaining: u64,
+    },
+    /// Database error while applying state overrides.
+    Database(DBError),
+    /// Call could not be executed.
+    Evm {
+        /// Index of the block.
+        block: usize,
+        /// Index of the call in the block.
+        call: usize,
+        /// Error.
+        error: EVMError<DBError>,
+    },
+}
+
+impl<DBError: fmt::Display> fmt::Display for SimulateError<DBError> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StateOverride { block, error } => {
+                write!(f, "block {block}: invalid state override: {error}")
+            }
+            Self::BlockNumberNotIncreasing { block } => {
+                write!(f, "block {block}: block numbers must be increasing")
+            }
+            Self::BlockTimestampNotIncreasing { block } => {
+                write!(f, "block {block}: block timestamps must be increasing")
+            }
+            Self::BlockGasLimitReached {
+                block,
+                call,
+                gas_limit,
+                remaining,
+            } => write!(
+                f,
+                "block {block} call {call}: gas limit {gas_limit} exceeds remaining block gas {remaining}"
+            ),
+            Self::Database(e) => write!(f, "database error: {e}"),
+            Self::Evm { block, call, error } => write!(f, "block {block} call {call}: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<DBError: fmt::Debug + fmt::Display> std::error::Error for SimulateError<DBError> {}
+
+/// Simulate `blocks` on top of `db`, with `parent` as the environment of the block before them.
+///
+/// Changes of all blocks are committed to `db`, pass a reference to keep the database.
+pub fn simulate<DB: Database + DatabaseCommit>(
+    db: DB,
+    cfg: CfgEnvWithHandlerCfg,
+    parent: BlockEnv,
+    blocks: Vec<SimBlock>,
+    options: SimulateOptions,
+) -> Result<Vec<SimulatedBlock>, SimulateError<DB::Error>> {
+    #[cfg(feature = "optional_eip3607")]
+    let cfg = {
+        let mut cfg = cfg;
+        cfg.cfg_env.disable_eip3607 |= !options.validation;
+        cfg
+    };
+
+    let mut db = OverrideDB::new(db, StateOverride::default()).expect("no overrides");
+    let mut previous = parent;
+    let mut simulated = Vec::with_capacity(blocks.len());
+    for (block_index, block) in blocks.into_iter().enumerate() {
+        let env = next_block_env(&previous, &block.block_overrides, options);
+        if env.number <= previous.number {
+            return Err(SimulateError::BlockNumberNotIncreasing { block: block_index });
+        }
+        if env.timestamp <= previous.timestamp {
+            return Err(SimulateError::BlockTimestampNotIncreasing { block: block_index });
+        }
+        db = db.with_block_overrides(&block.block_overrides);
+        db.set_overrides(block.state_overrides.clone())
+            .map_err(|error| SimulateError::StateOverride {
+                block: block_index,
+                error,
+            })?;
+        db.commit_overrides().map_err(SimulateError::Database)?;
+
+        let mut evm = Evm::builder()
+            .with_db(&mut db)
+            .with_external_context(TransferInspector::default())
+            .with_cfg_env_with_handler_cfg(cfg.clone())
+            .with_block_env(env.clone())
+            .build();
+        if options.trace_transfers {
+            evm = evm
+                .modify()
+                .append_handler_register(inspector_handle_register)
+                .build();
+        }
+        let mut precompiles = evm.handler.pre_execution.load_precompiles();
+        apply_precompile_moves(&mut precompiles, &block.state_overrides).map_err(|error| {
+            SimulateError::StateOverride {
+                block: block_index,
+                error,
+            }
+        })?;
+        evm.handler.pre_execution.load_precompiles = Arc::new(move || precompiles.clone());
+
+        let gas_limit = env.gas_limit.saturating_to::<u64>();
+        let mut gas_used = 0u64;
+        let mut calls = Vec::with_capacity(block.calls.len());
+        for (call_index, mut tx) in block.calls.into_iter().enumerate() {
+            let evm_error = |error| SimulateError::Evm {
+                block: block_index,
+                call: call_index,
+                error,
+            };
+            let remaining = gas_limit - gas_used;
+            if tx.gas_limit == u64::MAX {
+                tx.gas_limit = remaining;
+            } else if tx.gas_limit > remaining {
+                return Err(SimulateError::BlockGasLimitReached {
+                    block: block_index,
+                    call: call_index,
+                    gas_limit: tx.gas_limit,
+                    remaining,
+                });
+            }
+            if !options.validation {
+                tx.nonce = None;
+            } else if tx.nonce.is_none() {
+                let nonce = evm
+                    .db_mut()
+                    .basic(tx.caller)
+                    .map_err(|e| evm_error(EVMError::Database(e)))?
+                    .map(|info| info.nonce)
+                    .unwrap_or_default();
+                tx.nonce = Some(nonce);
+            }
+
+            *evm.tx_mut() = tx;
+            let result = evm.transact_commit().map_err(evm_error)?;
+            let inspector_logs = evm.context.external.take_logs();
+            let logs = if options.trace_transfers {
+                inspector_logs
+            } else {
+                result.logs().to_vec()
+            };
+            gas_used += result.gas_used();
+            calls.push(SimCallResult { result, logs });
+        }
+
+        previous = env.clone();
+        simulated.push(SimulatedBlock {
+            env,
+            gas_used,
+            calls,
+        });
+    }
+    Ok(simulated)
+}
+
+/// Returns environment of the block after `previous` with `overrides` applied.
+fn next_block_env(
+    previous: &BlockEnv,
+    overrides: &BlockOverrides,
+    options: SimulateOptions,
+) -> BlockEnv {
+    let mut env = previous.clone();
+    env.number = previous.number.saturating_add(U256::from(1));
+    env.timestamp = previous
+        .timestamp
+        .saturating_add(U256::from(SIMULATED_BLOCK_TIME));
+    if !options.validation {
+        env.basefee = U256::ZERO;
+    }
+    overrides.apply(&mut env);
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{AccountOverride, CacheDB, EmptyDB},
+        inspectors::TRANSFER_LOG_ADDRESS,
+        primitives::{
+            AccountInfo, Address, Bytes, CfgEnv, HashMap, InvalidTransaction, SpecId, TxKind, B256,
+        },
+    };
+
+    const ETHER: u64 = 1_000_000_000_000_000_000;
+
+    fn funded_db(address: Address) -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            address,
+            AccountInfo {
+                balance: U256::from(ETHER),
+                ..Default::default()
+            },
+        );
+        db
+    }
+
+    fn transfer(from: Address, to: Address, value: u64) -> TxEnv {
+        TxEnv {
+            caller: from,
+            transact_to: TxKind::Call(to),
+            value: U256::from(value),
+            ..Default::default()
+        }
+    }
+
+    fn cfg() -> CfgEnvWithHandlerCfg {
+        CfgEnvWithHandlerCfg::new_with_spec_id(CfgEnv::default(), SpecId::LATEST)
+    }
+
+    fn parent() -> BlockEnv {
+        BlockEnv {
+            number: U256::from(100),
+            timestamp: U256::from(1000),
+            gas_limit: U256::from(30_000_000),
+            basefee: U256::from(7),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn state_is_carried_between_blocks() {
+        let alice = Address::repeat_byte(0xa1);
+        let bob = Address::repeat_byte(0xb0);
+        let logger = Address::repeat_byte(0xc0);
+        let mut db = funded_db(alice);
+
+        let blocks = vec![
+            SimBlock {
+                calls: vec![transfer(alice, bob, 10), transfer(alice, bob, 20)],
+                ..Default::default()
+            },
+            SimBlock {
+                block_overrides: BlockOverrides {
+                    time: Some(U256::from(2000)),
+                    ..Default::default()
+                },
+                // CALLVALUE PUSH0 MSTORE PUSH1 32 PUSH0 LOG0 STOP
+                state_overrides: HashMap::from([(
+                    logger,
+                    AccountOverride {
+                        code: Some(Bytes::from_static(&[
+                            0x34, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xa0, 0x00,
+                        ])),
+                        ..Default::default()
+                    },
+                )]),
+                calls: vec![transfer(bob, logger, 5)],
+            },
+        ];
+        let result = simulate(
+            &mut db,
+            cfg(),
+            parent(),
+            blocks,
+            SimulateOptions {
+                validation: false,
+                trace_transfers: true,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].env.number, U256::from(101));
+        assert_eq!(result[0].env.timestamp, U256::from(1012));
+        assert_eq!(result[0].env.basefee, U256::ZERO);
+        assert_eq!(result[0].gas_used, 2 * 21_000);
+        assert_eq!(
+            result[0].calls[1].logs,
+            vec![TransferInspector::transfer_log(alice, bob, U256::from(20))]
+        );
+        assert_eq!(result[1].env.number, U256::from(102));
+        assert_eq!(result[1].env.timestamp, U256::from(2000));
+
+        let logs = &result[1].calls[0].logs;
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].address, TRANSFER_LOG_ADDRESS);
+        assert_eq!(logs[1].address, logger);
+        assert_eq!(
+            logs[1].data.data,
+            Bytes::from(U256::from(5).to_be_bytes_vec())
+        );
+
+        assert_eq!(db.accounts[&alice].info.nonce, 2);
+        assert_eq!(db.accounts[&bob].info.balance, U256::from(25));
+        assert_eq!(db.accounts[&logger].info.balance, U256::from(5));
+    }
+
+    #[test]
+    fn validation() {
+        let alice = Address::repeat_byte(0xa1);
+        let bob = Address::repeat_byte(0xb0);
+
+        let blocks = vec![SimBlock {
+            calls: vec![
+                TxEnv {
+                    gas_price: U256::from(7),
+                    ..transfer(alice, bob, 1)
+                },
+                TxEnv {
+                    gas_price: U256::from(7),
+                    ..transfer(alice, bob, 1)
+                },
+            ],
+            ..Default::default()
+        }];
+        let options = SimulateOptions {
+            validation: true,
+            trace_transfers: false,
+        };
+
+        // nonces are filled.
+        let result = simulate(funded_db(alice), cfg(), parent(), blocks.clone(), options).unwrap();
+        assert_eq!(result[0].env.basefee, U256::from(7));
+        assert!(result[0].calls.iter().all(|call| call.result.is_success()));
+        assert!(result[0].calls.iter().all(|call| call.logs.is_empty()));
+
+        // gas price is below the base fee.
+        let mut underpriced = blocks.clone();
+        underpriced[0].calls[1].gas_price = U256::from(1);
+        assert_eq!(
+            simulate(
+                funded_db(alice),
+                cfg(),
+                parent(),
+                underpriced.clone(),
+                options,
+            ),
+            Err(SimulateError::Evm {
+                block: 0,
+                call: 1,
+                error: EVMError::Transaction(InvalidTransaction::GasPriceLessThanBasefee)
+            })
+        );
+        assert!(simulate(
+            funded_db(alice),
+            cfg(),
+            parent(),
+            underpriced,
+            SimulateOptions::default(),
+        )
+        .is_ok());
+
+        // wrong nonce.
+        let mut wrong_nonce = blocks.clone();
+        wrong_nonce[0].calls[0].nonce = Some(5);
+        assert!(matches!(
+            simulate(funded_db(alice), cfg(), parent(), wrong_nonce, options,),
+            Err(SimulateError::Evm {
+                error: EVMError::Transaction(InvalidTransaction::NonceTooHigh { .. }),
+                ..
+            })
+        ));
+
+        // block does not have enough gas.
+        let mut out_of_gas = blocks;
+        out_of_gas[0].block_overrides.gas_limit = Some(U256::from(30_000));
+        out_of_gas[0].calls[1].gas_limit = 21_000;
+        assert_eq!(
+            simulate(funded_db(alice), cfg(), parent(), out_of_gas, options,),
+            Err(SimulateError::BlockGasLimitReached {
+                block: 0,
+                call: 1,
+                gas_limit: 21_000,
+                remaining: 9_000,
+            })
+        );
+    }
+
+    #[test]
+    fn block_order_and_overrides() {
+        let alice = Address::repeat_byte(0xa1);
+        let blocks = vec![SimBlock {
+            block_overrides: BlockOverrides {
+                number: Some(U256::from(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+        assert_eq!(
+            simulate(
+                funded_db(alice),
+                cfg(),
+                parent(),
+                blocks,
+                SimulateOptions::default(),
+            ),
+            Err(SimulateError::BlockNumberNotIncreasing { block: 0 })
+        );
+
+        let blocks = vec![SimBlock {
+            state_overrides: HashMap::from([(
+                alice,
+                AccountOverride {
+                    state: Some(HashMap::default()),
+                    state_diff: Some(HashMap::from([(B256::ZERO, B256::ZERO)])),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }];
+        assert_eq!(
+            simulate(
+                funded_db(alice),
+                cfg(),
+                parent(),
+                blocks,
+                SimulateOptions::default(),
+            ),
+            Err(SimulateError::StateOverride {
+                block: 0,
+                error: StateOverrideError::StateAndStateDiff(alice)
+            })
+        );
+    }
+}
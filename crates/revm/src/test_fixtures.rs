@@ -0,0 +1,145 @@
+//! Bytecode builder and EVM fixture shared by the tests.
+
+use crate::{
+    builder::{HandlerStage, SetGenericStage},
+    db::InMemoryDB,
+    inspector_handle_register,
+    primitives::{
+        eof::{EofBody, TypesSection},
+        AccountInfo, Address, Bytecode, Bytes, TxKind, B256,
+    },
+    Evm, EvmBuilder, GetInspector,
+};
+use std::{vec, vec::Vec};
+
+/// Sender of the transactions.
+pub(crate) const CALLER: Address = Address::repeat_byte(0x11);
+/// Contract called by the transactions.
+pub(crate) const TARGET: Address = Address::repeat_byte(0xaa);
+/// Contract called by [TARGET].
+pub(crate) const CHILD: Address = Address::repeat_byte(0xcc);
+
+/// `PUSH1 0 PUSH1 0 REVERT`.
+pub(crate) const REVERT: &[u8] = &[0x60, 0x00, 0x60, 0x00, 0xfd];
+
+/// Builder of legacy bytecode.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Code(Vec<u8>);
+
+impl Code {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the length of the code.
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Appends raw bytes, instructions with their immediates.
+    pub(crate) fn bytes(mut self, bytes: &[u8]) -> Self {
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    /// Appends the other code.
+    pub(crate) fn append(self, code: Code) -> Self {
+        self.bytes(&code.0)
+    }
+
+    /// Appends an instruction.
+    pub(crate) fn op(self, op: u8) -> Self {
+        self.bytes(&[op])
+    }
+
+    /// Appends `PUSH1 value`.
+    pub(crate) fn push(self, value: u8) -> Self {
+        self.bytes(&[0x60, value])
+    }
+
+    /// Appends `PUSH20 address`.
+    pub(crate) fn push_address(self, address: Address) -> Self {
+        self.op(0x73).bytes(address.as_slice())
+    }
+
+    /// Appends `PUSH32 word`.
+    pub(crate) fn push_word(self, word: B256) -> Self {
+        self.op(0x7f).bytes(word.as_slice())
+    }
+
+    /// Appends `CALL(GAS, address, value, 0, 0, 0, 0)`, the success flag is left on the stack.
+    pub(crate) fn call(self, address: Address, value: u8) -> Self {
+        self.push(0)
+            .push(0)
+            .push(0)
+            .push(0)
+            .push(value)
+            .push_address(address)
+            .bytes(&[0x5a, 0xf1])
+    }
+
+    /// Appends `REVERT(0, 0)`.
+    pub(crate) fn revert(self) -> Self {
+        self.bytes(REVERT)
+    }
+
+    /// Appends the revert with the return data of the last call if the flag on the stack is
+    /// zero, as Solidity bubbles up failures.
+    pub(crate) fn forward_revert(self) -> Self {
+        // PUSH1 dest JUMPI RETURNDATASIZE PUSH1 0 PUSH1 0 RETURNDATACOPY RETURNDATASIZE PUSH1 0
+        // REVERT JUMPDEST
+        let dest = u8::try_from(self.len() + 13).expect("code is short");
+        self.push(dest).bytes(&[
+            0x57, 0x3d, 0x60, 0x00, 0x60, 0x00, 0x3e, 0x3d, 0x60, 0x00, 0xfd, 0x5b,
+        ])
+    }
+
+    pub(crate) fn build(self) -> Bytes {
+        self.0.into()
+    }
+}
+
+impl From<Code> for Bytes {
+    fn from(code: Code) -> Self {
+        code.build()
+    }
+}
+
+/// Returns the EOF container with a single non-returning code section and the subcontainers.
+pub(crate) fn eof(code: Code, containers: impl IntoIterator<Item = Bytes>) -> Bytes {
+    EofBody {
+        types_section: vec![TypesSection::new(0, 0x80, 16)],
+        code_section: vec![code.build()],
+        container_section: containers.into_iter().collect(),
+        data_section: Bytes::new(),
+        is_data_filled: true,
+    }
+    .into_eof()
+    .raw
+}
+
+/// Returns the builder of an EVM with the contracts deployed, and a transaction from [CALLER]
+/// to [TARGET] with a gas limit of 1_000_000.
+pub(crate) fn builder<'a>(
+    contracts: impl IntoIterator<Item = (Address, Bytes)>,
+) -> EvmBuilder<'a, SetGenericStage, (), InMemoryDB> {
+    let mut db = InMemoryDB::default();
+    for (address, code) in contracts {
+        db.insert_account_info(address, AccountInfo::from_bytecode(Bytecode::new_raw(code)));
+    }
+    Evm::builder().with_db(db).modify_tx_env(|tx| {
+        tx.caller = CALLER;
+        tx.transact_to = TxKind::Call(TARGET);
+        tx.gas_limit = 1_000_000;
+    })
+}
+
+/// Returns the [builder] with the inspector registered.
+pub(crate) fn inspector_builder<'a, I: GetInspector<InMemoryDB>>(
+    inspector: I,
+    contracts: impl IntoIterator<Item = (Address, Bytes)>,
+) -> EvmBuilder<'a, HandlerStage, I, InMemoryDB> {
+    builder(contracts)
+        .with_external_context(inspector)
+        .append_handler_register(inspector_handle_register)
+}